@@ -18,6 +18,17 @@ fn main() {
     )
     .expect("Music player selector build failed");
 
+    // Compile window switcher panel
+    slint_build::compile_with_config(
+        "ui/panels/window_switcher/window_switcher.slint",
+        config.clone(),
+    )
+    .expect("Window switcher build failed");
+
+    // Compile workspace overview panel
+    slint_build::compile_with_config("ui/panels/overview/overview.slint", config.clone())
+        .expect("Overview build failed");
+
     // Add more panels as you create them:
     // slint_build::compile_with_config("ui/panels/menu/menu.slint", config.clone()).expect("Menu build failed");
     // slint_build::compile_with_config("ui/panels/osd/osd.slint", config.clone()).expect("OSD build failed");
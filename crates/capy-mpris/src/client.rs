@@ -1,86 +1,86 @@
 //! MPRIS client implementation
 //!
-//! Single D-Bus connection with proper cleanup:
-//! - Listen to property signals as triggers
-//! - When triggered, fetch ALL properties fresh
-//! - Explicit stream cleanup on session exit
+//! Follows one active player on top of a [`PlayerTracker`]:
+//! - The tracker watches every player, the client picks which one is shown
+//! - Sources and auto switching are derived from the tracked player states
+//! - Players appearing and leaving are picked up from NameOwnerChanged, no polling
+//! - Changes arrive as a stream of [`MprisEvent`]s, commands resolve with their result
 
 use crate::error::MprisError;
+use crate::player::CommandReply;
 use crate::sources::{PlayerSource, SourcePreference};
-use crate::types::{MprisData, PlaybackStatus, PlayerCommand};
-use futures_util::StreamExt;
+use crate::tracker::{PlayerTracker, PlayerUpdate};
+use crate::types::{MprisData, PlayerCommand};
+use futures_util::Stream;
 use log::{debug, info, warn};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::sync::{mpsc, oneshot};
 use zbus::Connection;
-use zbus::zvariant::OwnedValue;
-
-const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
-
-/// D-Bus proxy for MPRIS player interface
-#[zbus::proxy(
-    interface = "org.mpris.MediaPlayer2.Player",
-    default_path = "/org/mpris/MediaPlayer2"
-)]
-trait MprisPlayer {
-    // Methods
-    fn play_pause(&self) -> zbus::Result<()>;
-    fn next(&self) -> zbus::Result<()>;
-    fn previous(&self) -> zbus::Result<()>;
-    fn seek(&self, offset: i64) -> zbus::Result<()>;
-    fn set_position(
-        &self,
-        track_id: &zbus::zvariant::ObjectPath<'_>,
-        position: i64,
-    ) -> zbus::Result<()>;
-
-    // Properties
-    #[zbus(property)]
-    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
-
-    #[zbus(property)]
-    fn playback_status(&self) -> zbus::Result<String>;
-
-    #[zbus(property(emits_changed_signal = "false"))]
-    fn position(&self) -> zbus::Result<i64>;
-
-    #[zbus(property)]
-    fn can_seek(&self) -> zbus::Result<bool>;
-
-    // Signal for property changes - we use this as a TRIGGER only
-    #[zbus(signal)]
-    fn seeked(&self, position: i64) -> zbus::Result<()>;
+
+/// Change reported by [`MprisClient`]
+#[derive(Debug)]
+pub enum MprisEvent {
+    /// A player allowed by the source policy appeared
+    PlayerAdded(PlayerSource),
+    /// A player left the bus or was blocked, passes its bus name
+    PlayerRemoved(String),
+    /// The state of a player allowed by the source policy changed, passes its bus name
+    PlayerChanged(String),
+    /// The state of the active player changed
+    Updated(Box<MprisData>), // Boxed to keep enum size small
+    /// Another player became active, None once no player is left
+    ActiveChanged(Option<String>),
+    /// Something failed, the client keeps running
+    Error(MprisError),
+}
+
+/// Sends commands to the active player, cheap to clone
+#[derive(Clone)]
+pub struct MprisCommands {
+    sender: mpsc::Sender<(PlayerCommand, CommandReply)>,
 }
 
-/// D-Bus proxy for MPRIS root interface
-#[zbus::proxy(
-    interface = "org.mpris.MediaPlayer2",
-    default_path = "/org/mpris/MediaPlayer2"
-)]
-trait MprisRoot {
-    #[zbus(property)]
-    fn identity(&self) -> zbus::Result<String>;
+impl MprisCommands {
+    /// Run a command, resolves once it was carried out
+    pub async fn send(&self, command: PlayerCommand) -> Result<(), MprisError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.sender
+            .send((command, reply_tx))
+            .await
+            .map_err(|_| MprisError::Disconnected)?;
+        reply_rx.await.map_err(|_| MprisError::Disconnected)?
+    }
 }
 
-/// MPRIS client
-pub struct MprisClient;
+/// MPRIS client following one active player.
+/// It is a stream of [`MprisEvent`]s and stops once it is dropped together with
+/// every [`MprisCommands`] handle.
+pub struct MprisClient {
+    commands: MprisCommands,
+    events: mpsc::UnboundedReceiver<MprisEvent>,
+    sources: Arc<RwLock<Vec<PlayerSource>>>,
+    tracker: PlayerTracker,
+}
 
 impl MprisClient {
     /// Start the MPRIS client.
-    pub async fn start<F, G>(
-        on_update: F,
-        on_sources_changed: G,
+    pub async fn start(config_path: Option<PathBuf>) -> Result<Self, MprisError> {
+        Self::start_with_connection(Connection::session().await?, config_path).await
+    }
+
+    /// Start the MPRIS client on an existing connection.
+    pub async fn start_with_connection(
+        connection: Connection,
         config_path: Option<PathBuf>,
-    ) -> Result<mpsc::Sender<PlayerCommand>, MprisError>
-    where
-        F: Fn(MprisData) + Send + Sync + 'static,
-        G: Fn(Vec<PlayerSource>, Option<String>) + Send + Sync + 'static,
-    {
-        let connection = Connection::session().await?;
-        let (cmd_tx, cmd_rx) = mpsc::channel::<PlayerCommand>(32);
+    ) -> Result<Self, MprisError> {
+        let (cmd_tx, cmd_rx) = mpsc::channel(32);
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let sources = Arc::new(RwLock::new(Vec::new()));
 
         // Load preferences
         let preference = config_path
@@ -88,436 +88,486 @@ impl MprisClient {
             .map(|p| SourcePreference::load(p))
             .unwrap_or_default();
 
-        // Find initial player
-        let sources = discover_sources(&connection).await?;
-        let active_bus = preference
-            .select_source(&sources)
-            .map(|s| s.bus_name.clone());
+        // Players already on the bus are tracked once this returns
+        let (update_tx, update_rx) = mpsc::unbounded_channel();
+        let tracker = PlayerTracker::start_with_connection(connection, move |update| {
+            let _ = update_tx.send(update);
+        })
+        .await?;
+
+        // Players playing right now did not just start playing
+        let playing = tracker
+            .players()
+            .into_iter()
+            .filter(|data| data.status.is_playing())
+            .map(|data| data.source_bus_name)
+            .collect();
+
+        let mut context = ClientContext {
+            tracker: tracker.clone(),
+            preference,
+            config_path,
+            events: event_tx,
+            sources: sources.clone(),
+            active: None,
+            selected_at: Instant::now(),
+            playing,
+        };
 
-        on_sources_changed(sources.clone(), active_bus.clone());
+        // Find initial player, its state arrives with the first tracker update
+        let discovered = context.discover();
+        let active = context.preferred(&discovered);
+        context.publish(discovered, active);
 
         // Spawn the main loop
-        let on_update = Arc::new(on_update);
-        let on_sources_changed = Arc::new(on_sources_changed);
-
-        tokio::spawn(async move {
-            run_loop(
-                connection,
-                active_bus,
-                cmd_rx,
-                preference,
-                config_path,
-                on_update,
-                on_sources_changed,
-            )
-            .await;
-        });
-
-        Ok(cmd_tx)
+        tokio::spawn(run_loop(context, update_rx, cmd_rx));
+
+        Ok(Self {
+            commands: MprisCommands { sender: cmd_tx },
+            events: event_rx,
+            sources,
+            tracker,
+        })
     }
-}
 
-/// Main event loop - simplified like the Dart version
-async fn run_loop<F, G>(
-    connection: Connection,
-    mut active_bus: Option<String>,
-    mut cmd_rx: mpsc::Receiver<PlayerCommand>,
-    mut preference: SourcePreference,
-    config_path: Option<PathBuf>,
-    on_update: Arc<F>,
-    on_sources_changed: Arc<G>,
-) where
-    F: Fn(MprisData) + Send + Sync + 'static,
-    G: Fn(Vec<PlayerSource>, Option<String>) + Send + Sync + 'static,
-{
-    loop {
-        // Clone the bus name first to avoid borrow conflicts
-        let current_bus = active_bus.clone();
-
-        if let Some(bus_name) = current_bus {
-            info!("Connecting to player: {}", bus_name);
-
-            match run_player_session(
-                &connection,
-                &bus_name,
-                &mut cmd_rx,
-                &mut preference,
-                &config_path,
-                &on_update,
-                &on_sources_changed,
-                &mut active_bus,
-            )
-            .await
-            {
-                Ok(()) => {
-                    info!("Player session ended normally");
-                }
-                Err(e) => {
-                    warn!("Player session error: {}", e);
-                }
-            }
+    /// Handle for sending commands from elsewhere, e.g. UI callbacks
+    pub fn commands(&self) -> MprisCommands {
+        self.commands.clone()
+    }
 
-            // Player disconnected - try to find new one
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            if let Ok(sources) = discover_sources(&connection).await {
-                active_bus = preference
-                    .select_source(&sources)
-                    .map(|s| s.bus_name.clone());
-                on_sources_changed(sources, active_bus.clone());
-            }
-        } else {
-            // No player - wait and poll
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-            if let Ok(sources) = discover_sources(&connection).await {
-                active_bus = preference
-                    .select_source(&sources)
-                    .map(|s| s.bus_name.clone());
-                if active_bus.is_some() {
-                    on_sources_changed(sources, active_bus.clone());
-                }
-            }
-        }
+    /// Run a command on the active player, resolves once it was carried out
+    pub async fn send(&self, command: PlayerCommand) -> Result<(), MprisError> {
+        self.commands.send(command).await
     }
-}
 
-/// Run a session connected to a specific player
-async fn run_player_session<F, G>(
-    connection: &Connection,
-    bus_name: &str,
-    cmd_rx: &mut mpsc::Receiver<PlayerCommand>,
-    preference: &mut SourcePreference,
-    config_path: &Option<PathBuf>,
-    on_update: &Arc<F>,
-    on_sources_changed: &Arc<G>,
-    active_bus: &mut Option<String>,
-) -> Result<(), MprisError>
-where
-    F: Fn(MprisData) + Send + Sync + 'static,
-    G: Fn(Vec<PlayerSource>, Option<String>) + Send + Sync + 'static,
-{
-    // Create proxy for this player
-    let proxy = MprisPlayerProxy::builder(connection)
-        .destination(bus_name)?
-        .build()
-        .await?;
+    /// Players allowed by the source policy, in order of preference
+    pub fn sources(&self) -> Vec<PlayerSource> {
+        self.sources
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
 
-    // Subscribe to property change signals - zbus generates these from #[zbus(property)]
-    let mut status_stream = proxy.receive_playback_status_changed().await;
-    let mut metadata_stream = proxy.receive_metadata_changed().await;
-    let mut seeked_stream = proxy.receive_seeked().await?;
-
-    // Wait a bit for UI to initialize and subscribe to event bus
-    // This fixes race condition where initial state is sent before UI subscribes
-    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-
-    // Send initial state
-    fetch_and_send_state(bus_name, &proxy, on_update).await;
-
-    // Send again after a short delay to catch any late-subscribing UIs
-    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-    fetch_and_send_state(bus_name, &proxy, on_update).await;
-
-    let result = run_session_loop(
-        connection,
-        bus_name,
-        &proxy,
-        &mut status_stream,
-        &mut metadata_stream,
-        &mut seeked_stream,
-        cmd_rx,
-        preference,
-        config_path,
-        on_update,
-        on_sources_changed,
-        active_bus,
-    )
-    .await;
-
-    // CRITICAL: Explicit cleanup to prevent subscription accumulation
-    // Drop streams explicitly before returning
-    drop(status_stream);
-    drop(metadata_stream);
-    drop(seeked_stream);
-    // Yield to allow async cleanup tasks to run
-    tokio::task::yield_now().await;
-
-    result
+    /// Tracker behind this client, holds the state of every player including blocked ones.
+    /// Keeps tracking the players as long as a handle is kept.
+    pub fn tracker(&self) -> PlayerTracker {
+        self.tracker.clone()
+    }
 }
 
-/// Inner session loop - separated for cleaner stream cleanup
-async fn run_session_loop<F, G>(
-    connection: &Connection,
-    bus_name: &str,
-    proxy: &MprisPlayerProxy<'_>,
-    status_stream: &mut zbus::PropertyStream<'_, String>,
-    metadata_stream: &mut zbus::PropertyStream<'_, HashMap<String, OwnedValue>>,
-    seeked_stream: &mut SeekedStream<'_>,
-    cmd_rx: &mut mpsc::Receiver<PlayerCommand>,
-    preference: &mut SourcePreference,
-    config_path: &Option<PathBuf>,
-    on_update: &Arc<F>,
-    on_sources_changed: &Arc<G>,
-    active_bus: &mut Option<String>,
-) -> Result<(), MprisError>
-where
-    F: Fn(MprisData) + Send + Sync + 'static,
-    G: Fn(Vec<PlayerSource>, Option<String>) + Send + Sync + 'static,
-{
-    loop {
-        tokio::select! {
-            // PlaybackStatus changed
-            Some(_) = status_stream.next() => {
-                debug!("PlaybackStatus changed signal received");
-                fetch_and_send_state(bus_name, proxy, on_update).await;
-            }
+impl Stream for MprisClient {
+    type Item = MprisEvent;
 
-            // Metadata changed (track change)
-            Some(_) = metadata_stream.next() => {
-                debug!("Metadata changed signal received");
-                fetch_and_send_state(bus_name, proxy, on_update).await;
-            }
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<MprisEvent>> {
+        self.get_mut().events.poll_recv(cx)
+    }
+}
 
-            // Seeked signal
-            Some(_) = seeked_stream.next() => {
-                debug!("Seeked signal received");
-                fetch_and_send_state(bus_name, proxy, on_update).await;
-            }
+/// State of the client loop
+struct ClientContext {
+    tracker: PlayerTracker,
+    preference: SourcePreference,
+    config_path: Option<PathBuf>,
+    events: mpsc::UnboundedSender<MprisEvent>,
+    // Last published sources and active player
+    sources: Arc<RwLock<Vec<PlayerSource>>>,
+    active: Option<String>,
+    /// When the active player was selected, for the stickiness of auto switching
+    selected_at: Instant,
+    /// Players that were playing on their last update
+    playing: HashSet<String>,
+}
 
-            // Commands from UI
-            Some(cmd) = cmd_rx.recv() => {
-                match cmd {
-                    PlayerCommand::PlayPause => {
-                        debug!("Sending PlayPause command");
-                        let _ = proxy.play_pause().await;
-                        // Signal should trigger update, but add small delay + poll as backup
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                        fetch_and_send_state(bus_name, proxy, on_update).await;
-                    }
-                    PlayerCommand::Next => {
-                        debug!("Sending Next command");
-                        let _ = proxy.next().await;
-                        // Spotify may not emit signal when paused - poll after delay
-                        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
-                        fetch_and_send_state(bus_name, proxy, on_update).await;
-                    }
-                    PlayerCommand::Previous => {
-                        debug!("Sending Previous command");
-                        let _ = proxy.previous().await;
-                        // Spotify may not emit signal when paused - poll after delay
-                        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
-                        fetch_and_send_state(bus_name, proxy, on_update).await;
-                    }
-                    PlayerCommand::Seek(offset) => {
-                        debug!("Sending Seek command: offset={}us", offset);
-                        let _ = proxy.seek(offset).await;
-                        // Seeked signal should fire, but poll as backup
-                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                        fetch_and_send_state(bus_name, proxy, on_update).await;
-                    }
-                    PlayerCommand::SetPosition(position) => {
-                        debug!("Sending SetPosition command: position={}us", position);
-                        if let Ok(metadata) = proxy.metadata().await {
-                            if let Some(track_id) = extract_track_id(&metadata) {
-                                if let Ok(path) = zbus::zvariant::ObjectPath::try_from(track_id.as_str()) {
-                                    let _ = proxy.set_position(&path, position).await;
-                                    // Seeked signal should fire, but poll as backup
-                                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                                    fetch_and_send_state(bus_name, proxy, on_update).await;
-                                }
-                            }
-                        }
-                    }
-                    PlayerCommand::SwitchSource(short_name) => {
-                        if let Ok(sources) = discover_sources(connection).await {
-                            if let Some(src) = sources.iter().find(|s| s.short_name == short_name) {
-                                *active_bus = Some(src.bus_name.clone());
-                                on_sources_changed(sources, active_bus.clone());
-                                return Ok(()); // Exit to reconnect to new player
-                            }
-                        }
-                    }
-                    PlayerCommand::SetFavorite(short_name) => {
-                        preference.set_favorite(short_name);
-                        if let Some(path) = config_path {
-                            let _ = preference.save(path);
-                        }
-                    }
-                    PlayerCommand::ClearFavorite => {
-                        preference.clear_favorite();
-                        if let Some(path) = config_path {
-                            let _ = preference.save(path);
-                        }
-                    }
-                }
-            }
+impl ClientContext {
+    fn emit(&self, event: MprisEvent) {
+        // Nobody listens once the client was dropped
+        let _ = self.events.send(event);
+    }
 
-            // Timeout - check if player still exists (increased to 30s to reduce churn)
-            _ = tokio::time::sleep(std::time::Duration::from_secs(30)) => {
-                // Ping the player to check if still alive
-                if proxy.playback_status().await.is_err() {
-                    warn!("Player {} no longer responding", bus_name);
-                    return Err(MprisError::Disconnected);
-                }
-            }
-        }
+    /// The tracked players allowed by the policy, in order of preference
+    fn discover(&self) -> Vec<PlayerSource> {
+        let mut sources: Vec<PlayerSource> = self
+            .tracker
+            .players()
+            .into_iter()
+            .map(|data| PlayerSource {
+                bus_name: data.source_bus_name,
+                identity: data.source_identity,
+                short_name: data.source_name,
+                can_play: data.can_play,
+                can_pause: data.can_pause,
+                can_seek: data.can_seek,
+            })
+            .collect();
+        self.preference.sort_sources(&mut sources);
+        sources
     }
-}
 
-/// Fetch all properties and send update - like Dart's getPlayerData()
-#[inline]
-async fn fetch_and_send_state<F>(bus_name: &str, proxy: &MprisPlayerProxy<'_>, on_update: &Arc<F>)
-where
-    F: Fn(MprisData) + Send + Sync + 'static,
-{
-    // Fetch all properties fresh - this is the key insight from Dart
-    let metadata = proxy.metadata().await.unwrap_or_default();
-    let status_str = proxy.playback_status().await.unwrap_or_default();
-    let position_us = proxy.position().await.unwrap_or(0);
-
-    debug!(
-        "Fetched state: status='{}', position={}us",
-        status_str, position_us
-    );
-
-    let now_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .unwrap_or(0);
-
-    let data = MprisData {
-        title: extract_string(&metadata, "xesam:title").unwrap_or_default(),
-        artist: extract_str_array(&metadata, "xesam:artist").unwrap_or_default(),
-        album: extract_string(&metadata, "xesam:album").unwrap_or_default(),
-        art_url: extract_string(&metadata, "mpris:artUrl").unwrap_or_default(),
-        length_us: extract_i64(&metadata, "mpris:length").unwrap_or(0),
-        status: PlaybackStatus::from_str(&status_str),
-        track_id: extract_track_id(&metadata),
-        position_us,
-        position_timestamp_ms: now_ms,
-        source_name: PlayerSource::extract_short_name(bus_name),
-        source_bus_name: bus_name.to_string(),
-    };
+    /// Bus name of the preferred source
+    fn preferred(&self, sources: &[PlayerSource]) -> Option<String> {
+        self.preference
+            .select_source(sources)
+            .map(|s| s.bus_name.clone())
+    }
 
-    on_update(data);
-}
+    /// Store the sources and the active player, and report what changed.
+    /// Returns whether another player became active.
+    fn publish(&mut self, sources: Vec<PlayerSource>, active: Option<String>) -> bool {
+        let (added, removed) = {
+            let mut current = self.sources.write().unwrap_or_else(PoisonError::into_inner);
+            let added: Vec<PlayerSource> = sources
+                .iter()
+                .filter(|s| !current.iter().any(|c| c.bus_name == s.bus_name))
+                .cloned()
+                .collect();
+            let removed: Vec<String> = current
+                .iter()
+                .filter(|c| !sources.iter().any(|s| s.bus_name == c.bus_name))
+                .map(|c| c.bus_name.clone())
+                .collect();
+            *current = sources;
+            (added, removed)
+        };
 
-/// Discover all MPRIS players
-async fn discover_sources(connection: &Connection) -> Result<Vec<PlayerSource>, MprisError> {
-    let dbus_proxy = zbus::fdo::DBusProxy::new(connection).await?;
-    let names = dbus_proxy.list_names().await?;
+        for bus_name in removed {
+            self.emit(MprisEvent::PlayerRemoved(bus_name));
+        }
+        for source in added {
+            self.emit(MprisEvent::PlayerAdded(source));
+        }
+        if active == self.active {
+            return false;
+        }
+        self.active = active.clone();
+        self.selected_at = Instant::now();
+        self.emit(MprisEvent::ActiveChanged(active));
+        true
+    }
 
-    let mut sources = Vec::new();
+    /// Publish the sources, and the state of the active player if it changed
+    fn publish_and_show(&mut self, sources: Vec<PlayerSource>, active: Option<String>) {
+        if !self.publish(sources, active) {
+            return;
+        }
+        if let Some(data) = self
+            .active
+            .as_deref()
+            .and_then(|bus| self.tracker.player(bus))
+        {
+            self.emit(MprisEvent::Updated(Box::new(data)));
+        }
+    }
 
-    for name in names.iter().filter(|n| n.starts_with(MPRIS_PREFIX)) {
-        let bus_name = name.to_string();
-        let short_name = PlayerSource::extract_short_name(&bus_name);
+    /// A tracked player appeared or its state changed
+    fn player_updated(&mut self, data: MprisData) {
+        let bus_name = data.source_bus_name.clone();
+        let started_playing = if data.status.is_playing() {
+            self.playing.insert(bus_name.clone())
+        } else {
+            self.playing.remove(&bus_name);
+            false
+        };
 
-        // Try to get identity
-        let identity = match MprisRootProxy::builder(connection)
-            .destination(bus_name.as_str())?
-            .build()
-            .await
+        let sources = self.discover();
+        let allowed = sources.iter().any(|s| s.bus_name == bus_name);
+        let is_active = self.active.as_deref() == Some(bus_name.as_str());
+
+        let active = if self.active.is_none() {
+            self.preferred(&sources)
+        } else if allowed
+            && !is_active
+            && started_playing
+            && self
+                .preference
+                .allows_auto_switch(self.selected_at.elapsed())
         {
-            Ok(proxy) => proxy
-                .identity()
-                .await
-                .unwrap_or_else(|_| short_name.clone()),
-            Err(_) => short_name.clone(),
+            info!("Switching to {}, it started playing", bus_name);
+            Some(bus_name.clone())
+        } else {
+            self.active.clone()
         };
 
-        // Try to get capabilities
-        let can_seek = match MprisPlayerProxy::builder(connection)
-            .destination(bus_name.as_str())?
-            .build()
-            .await
-        {
-            Ok(proxy) => proxy.can_seek().await.unwrap_or(false),
-            Err(_) => false,
+        // The new active player is shown right away, this update only if it belongs to it
+        let switched = active != self.active;
+        self.publish_and_show(sources, active);
+        if !switched && is_active {
+            self.emit(MprisEvent::Updated(Box::new(data)));
+        }
+        if allowed {
+            self.emit(MprisEvent::PlayerChanged(bus_name));
+        }
+    }
+
+    /// A tracked player left the bus
+    fn player_removed(&mut self, bus_name: &str) {
+        self.playing.remove(bus_name);
+        let sources = self.discover();
+        let active = match &self.active {
+            Some(active) if active == bus_name => {
+                info!("Player {} left the bus", bus_name);
+                self.preferred(&sources)
+            }
+            active => active.clone(),
         };
+        self.publish_and_show(sources, active);
+    }
 
-        sources.push(PlayerSource {
-            bus_name,
-            identity,
-            short_name,
-            can_play: true,
-            can_pause: true,
-            can_seek,
-        });
+    fn save_preference(&self) -> Result<(), MprisError> {
+        if let Some(path) = &self.config_path {
+            self.preference.save(path)?;
+        }
+        Ok(())
     }
+}
 
-    // Sort: prefer spotify
-    sources.sort_by(|a, b| {
-        let a_spotify = a.short_name == "spotify";
-        let b_spotify = b.short_name == "spotify";
-        match (a_spotify, b_spotify) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.short_name.cmp(&b.short_name),
+/// Main event loop, follows the tracker and runs commands
+async fn run_loop(
+    mut context: ClientContext,
+    mut updates: mpsc::UnboundedReceiver<PlayerUpdate>,
+    mut cmd_rx: mpsc::Receiver<(PlayerCommand, CommandReply)>,
+) {
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Some(PlayerUpdate::Updated(data)) => context.player_updated(*data),
+                Some(PlayerUpdate::Removed(bus_name)) => context.player_removed(&bus_name),
+                None => {
+                    warn!("D-Bus connection closed, stopping MPRIS client");
+                    context.emit(MprisEvent::Error(MprisError::Disconnected));
+                    break;
+                }
+            },
+
+            request = cmd_rx.recv() => {
+                // The client and every command handle were dropped
+                let Some((cmd, reply)) = request else {
+                    break;
+                };
+                handle_command(&mut context, cmd, reply);
+            }
         }
-    });
+    }
 
-    Ok(sources)
+    debug!("MPRIS client stopped");
 }
 
-// ============ Metadata extraction helpers ============
+/// Run a command, against the active player unless it is a source command
+fn handle_command(context: &mut ClientContext, cmd: PlayerCommand, reply: CommandReply) {
+    let result = match cmd {
+        PlayerCommand::SwitchSource(short_name) => {
+            let sources = context.discover();
+            match sources.iter().find(|s| s.short_name == short_name) {
+                Some(source) => {
+                    let bus_name = source.bus_name.clone();
+                    context.publish_and_show(sources, Some(bus_name));
+                    Ok(())
+                }
+                None => Err(MprisError::NoPlayer),
+            }
+        }
+        PlayerCommand::SetPreference(preference) => {
+            context.preference = preference;
+            let sources = context.discover();
+
+            // Keep the active player unless the new policy blocks it
+            let active = context
+                .active
+                .clone()
+                .filter(|bus_name| sources.iter().any(|s| s.bus_name == *bus_name))
+                .or_else(|| context.preferred(&sources));
+            context.publish_and_show(sources, active);
+            context.save_preference()
+        }
+        cmd => {
+            let Some(bus_name) = context.active.clone() else {
+                let _ = reply.send(Err(MprisError::NoPlayer));
+                return;
+            };
+            // The tracker runs it, updates keep flowing in the meantime
+            let tracker = context.tracker.clone();
+            tokio::spawn(async move {
+                let _ = reply.send(tracker.send(&bus_name, cmd).await);
+            });
+            return;
+        }
+    };
+    let _ = reply.send(result);
+}
 
-#[inline]
-fn extract_string(map: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
-    use std::ops::Deref;
-    use zbus::zvariant::Value;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::MprisPlayerProxy;
+    use crate::test_bus::{EVENT_TIMEOUT, TestBus};
+    use futures_util::StreamExt;
+
+    /// Skip events until `matches` picks one, failing the test after a timeout
+    async fn wait_for<T>(
+        client: &mut MprisClient,
+        mut matches: impl FnMut(MprisEvent) -> Option<T>,
+    ) -> T {
+        let find = async {
+            while let Some(event) = client.next().await {
+                if let Some(found) = matches(event) {
+                    return found;
+                }
+            }
+            panic!("client stopped");
+        };
+        tokio::time::timeout(EVENT_TIMEOUT * 2, find)
+            .await
+            .expect("timed out waiting for an event")
+    }
 
-    map.get(key).and_then(|v| match v.deref() {
-        Value::Str(s) => Some(s.to_string()),
-        _ => None,
-    })
-}
+    fn active_changed(event: MprisEvent) -> Option<Option<String>> {
+        match event {
+            MprisEvent::ActiveChanged(active) => Some(active),
+            _ => None,
+        }
+    }
 
-#[inline]
-fn extract_i64(map: &HashMap<String, OwnedValue>, key: &str) -> Option<i64> {
-    use std::ops::Deref;
-    use zbus::zvariant::Value;
-
-    map.get(key).and_then(|v| match v.deref() {
-        Value::I64(i) => Some(*i),
-        Value::U64(u) => Some(*u as i64),
-        Value::I32(i) => Some(*i as i64),
-        Value::U32(u) => Some(*u as i64),
-        _ => None,
-    })
-}
+    #[tokio::test]
+    async fn test_follows_players_joining_and_leaving() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+        let mut client = MprisClient::start_with_connection(bus.connect().await, None)
+            .await
+            .unwrap();
+        assert!(client.sources().is_empty());
+
+        let player = bus.add_player("first").await;
+        let added = wait_for(&mut client, |event| match event {
+            MprisEvent::PlayerAdded(source) => Some(source),
+            _ => None,
+        })
+        .await;
+        assert_eq!(added.identity, "Fake first");
+        let active = wait_for(&mut client, active_changed).await;
+        assert_eq!(active.as_deref(), Some("org.mpris.MediaPlayer2.first"));
+        let data = wait_for(&mut client, |event| match event {
+            MprisEvent::Updated(data) => Some(data),
+            _ => None,
+        })
+        .await;
+        assert_eq!(data.title, "first track");
+
+        drop(player);
+        let removed = wait_for(&mut client, |event| match event {
+            MprisEvent::PlayerRemoved(bus_name) => Some(bus_name),
+            _ => None,
+        })
+        .await;
+        assert_eq!(removed, "org.mpris.MediaPlayer2.first");
+        assert_eq!(wait_for(&mut client, active_changed).await, None);
+        assert!(client.sources().is_empty());
+    }
 
-#[inline]
-fn extract_str_array(map: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
-    use std::ops::Deref;
-    use zbus::zvariant::Value;
+    #[tokio::test]
+    async fn test_auto_switch_to_playing_player() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+        let _first = bus.add_player("first").await;
+        let _second = bus.add_player("second").await;
+        let _blocked = bus.add_player("blocked").await;
+
+        let config_path =
+            std::env::temp_dir().join(format!("capy-mpris-client-{}.json", std::process::id()));
+        let preference = SourcePreference {
+            priority: vec!["first".to_string()],
+            blocklist: vec!["blocked".to_string()],
+            auto_switch: true,
+            stickiness_secs: 0,
+        };
+        preference.save(&config_path).unwrap();
 
-    map.get(key).and_then(|v| match v.deref() {
-        Value::Array(arr) => {
-            let strings: Vec<String> = arr
-                .iter()
-                .filter_map(|item| match item {
-                    Value::Str(s) => Some(s.to_string()),
-                    _ => None,
-                })
-                .collect();
-            if strings.is_empty() {
-                None
-            } else {
-                Some(strings.join(", "))
+        let mut client =
+            MprisClient::start_with_connection(bus.connect().await, Some(config_path.clone()))
+                .await
+                .unwrap();
+        let _ = std::fs::remove_file(&config_path);
+
+        let names: Vec<String> = client.sources().into_iter().map(|s| s.short_name).collect();
+        assert_eq!(names, ["first", "second"]);
+        // The tracker behind the client also holds the blocked player
+        assert_eq!(client.tracker().players().len(), 3);
+        let active = wait_for(&mut client, active_changed).await;
+        assert_eq!(active.as_deref(), Some("org.mpris.MediaPlayer2.first"));
+
+        let connection = bus.connect().await;
+        let play = |name: &'static str| {
+            let connection = connection.clone();
+            async move {
+                MprisPlayerProxy::builder(&connection)
+                    .destination(format!("org.mpris.MediaPlayer2.{}", name))
+                    .unwrap()
+                    .build()
+                    .await
+                    .unwrap()
+                    .play_pause()
+                    .await
+                    .unwrap();
             }
-        }
-        _ => None,
-    })
-}
+        };
 
-#[inline]
-fn extract_track_id(map: &HashMap<String, OwnedValue>) -> Option<String> {
-    use std::ops::Deref;
-    use zbus::zvariant::Value;
+        // Blocked players never take over
+        play("blocked").await;
+        play("second").await;
+        let active = wait_for(&mut client, active_changed).await;
+        assert_eq!(active.as_deref(), Some("org.mpris.MediaPlayer2.second"));
+    }
 
-    map.get("mpris:trackid").and_then(|v| match v.deref() {
-        Value::ObjectPath(p) => Some(p.to_string()),
-        Value::Str(s) => Some(s.to_string()),
-        _ => None,
-    })
+    #[tokio::test]
+    async fn test_command_errors() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+        let mut client = MprisClient::start_with_connection(bus.connect().await, None)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            client.send(PlayerCommand::PlayPause).await,
+            Err(MprisError::NoPlayer)
+        ));
+        assert!(matches!(
+            client
+                .send(PlayerCommand::SwitchSource("missing".to_string()))
+                .await,
+            Err(MprisError::NoPlayer)
+        ));
+
+        // Sources come from the tracker, switch once it picked the player up
+        let _player = bus.add_player_without_track("first").await;
+        wait_for(&mut client, |event| match event {
+            MprisEvent::PlayerAdded(_) => Some(()),
+            _ => None,
+        })
+        .await;
+        client
+            .send(PlayerCommand::SwitchSource("first".to_string()))
+            .await
+            .unwrap();
+        client.send(PlayerCommand::PlayPause).await.unwrap();
+        assert!(matches!(
+            client.send(PlayerCommand::SetPosition(0)).await,
+            Err(MprisError::NoTrackId)
+        ));
+
+        let _tracked = bus.add_player("second").await;
+        wait_for(&mut client, |event| match event {
+            MprisEvent::PlayerAdded(_) => Some(()),
+            _ => None,
+        })
+        .await;
+        client
+            .send(PlayerCommand::SwitchSource("second".to_string()))
+            .await
+            .unwrap();
+        client
+            .send(PlayerCommand::SetPosition(5_000_000))
+            .await
+            .unwrap();
+    }
 }
@@ -12,6 +12,9 @@ pub enum MprisError {
     #[error("No MPRIS player found")]
     NoPlayer,
 
+    #[error("Player has no mpris:trackid to set the position of")]
+    NoTrackId,
+
     #[error("Player disconnected")]
     Disconnected,
 
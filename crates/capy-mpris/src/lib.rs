@@ -3,14 +3,20 @@
 //! Features:
 //! - Single D-Bus connection (no memory leaks)
 //! - Client-side time interpolation
-//! - Multi-source support with favorites
+//! - Multi-source support with a priority, blocklist and auto switch policy
+//! - Tracking of all players at once
 
 pub mod client;
 pub mod error;
+mod player;
 pub mod sources;
+#[cfg(test)]
+mod test_bus;
+pub mod tracker;
 pub mod types;
 
-pub use client::MprisClient;
+pub use client::{MprisClient, MprisCommands, MprisEvent};
 pub use error::MprisError;
 pub use sources::{PlayerSource, SourcePreference};
-pub use types::{MprisData, PlaybackStatus, PlayerCommand};
+pub use tracker::{PlayerTracker, PlayerUpdate};
+pub use types::{LoopStatus, MprisData, PlaybackStatus, PlayerCommand};
@@ -0,0 +1,422 @@
+//! Connection to a single MPRIS player
+//!
+//! Used by the [`PlayerTracker`](crate::PlayerTracker), which watches every player at once.
+
+use crate::error::MprisError;
+use crate::sources::PlayerSource;
+use crate::types::{LoopStatus, MprisData, PlaybackStatus, PlayerCommand};
+use futures_util::StreamExt;
+use futures_util::future;
+use futures_util::stream::BoxStream;
+use log::debug;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use zbus::fdo::{DBusProxy, NameOwnerChanged, PropertiesChangedStream, PropertiesProxy};
+use zbus::message::Type as MessageType;
+use zbus::names::InterfaceName;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{Connection, MatchRule, MessageStream};
+
+pub(crate) const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+/// Namespace of the player bus names, for `arg0namespace` match rules
+const MPRIS_NAMESPACE: &str = "org.mpris.MediaPlayer2";
+pub(crate) const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// D-Bus proxy for MPRIS player interface
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub(crate) trait MprisPlayer {
+    // Methods
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn play(&self) -> zbus::Result<()>;
+    fn pause(&self) -> zbus::Result<()>;
+    fn stop(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+    fn set_position(
+        &self,
+        track_id: &zbus::zvariant::ObjectPath<'_>,
+        position: i64,
+    ) -> zbus::Result<()>;
+    fn open_uri(&self, uri: &str) -> zbus::Result<()>;
+
+    // Properties - state is read in one go with GetAll, see PlayerSession::fetch_state
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn can_seek(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_play(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_pause(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_shuffle(&self, value: bool) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn set_loop_status(&self, value: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn set_volume(&self, value: f64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn set_rate(&self, value: f64) -> zbus::Result<()>;
+
+    // Position changes without a property signal, players emit Seeked instead
+    #[zbus(signal)]
+    fn seeked(&self, position: i64) -> zbus::Result<()>;
+}
+
+/// D-Bus proxy for MPRIS root interface
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub(crate) trait MprisRoot {
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+}
+
+/// Proxies for one player, identified by its bus name
+pub(crate) struct PlayerSession {
+    pub bus_name: String,
+    pub identity: String,
+    pub player: MprisPlayerProxy<'static>,
+    properties: PropertiesProxy<'static>,
+}
+
+impl PlayerSession {
+    /// Create proxies for a player. State is fetched fresh on every change, no need for the cache
+    pub async fn connect(connection: &Connection, bus_name: &str) -> Result<Self, MprisError> {
+        let short_name = PlayerSource::extract_short_name(bus_name);
+        let identity = match MprisRootProxy::builder(connection)
+            .destination(bus_name.to_string())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+        {
+            Ok(proxy) => proxy.identity().await.unwrap_or(short_name),
+            Err(_) => short_name,
+        };
+
+        Ok(Self {
+            bus_name: bus_name.to_string(),
+            identity,
+            player: MprisPlayerProxy::builder(connection)
+                .destination(bus_name.to_string())?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?,
+            properties: PropertiesProxy::builder(connection)
+                .destination(bus_name.to_string())?
+                .path(MPRIS_PATH)?
+                .build()
+                .await?,
+        })
+    }
+
+    /// Streams that fire when the player state changes
+    pub async fn subscribe(
+        &self,
+    ) -> Result<(PropertiesChangedStream<'static>, SeekedStream<'static>), MprisError> {
+        // Any player property change triggers a refetch
+        let properties = self
+            .properties
+            .receive_properties_changed_with_args(&[(0, PLAYER_INTERFACE)])
+            .await?;
+        let seeked = self.player.receive_seeked().await?;
+        Ok((properties, seeked))
+    }
+
+    /// Fetch all properties - like Dart's getPlayerData()
+    pub async fn fetch_state(&self) -> MprisData {
+        // Fetch all properties fresh in one call - this is the key insight from Dart
+        let interface = InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE);
+        let properties = self
+            .properties
+            .get_all(Some(interface).into())
+            .await
+            .unwrap_or_default();
+        let metadata = extract_dict(&properties, "Metadata").unwrap_or_default();
+        let status_str = extract_string(&properties, "PlaybackStatus").unwrap_or_default();
+        let position_us = extract_i64(&properties, "Position").unwrap_or(0);
+
+        debug!(
+            "Fetched state of {}: status='{}', position={}us",
+            self.bus_name, status_str, position_us
+        );
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        let flag = |key| extract_bool(&properties, key).unwrap_or(false);
+        MprisData {
+            title: extract_string(&metadata, "xesam:title").unwrap_or_default(),
+            artist: extract_str_array(&metadata, "xesam:artist").unwrap_or_default(),
+            album: extract_string(&metadata, "xesam:album").unwrap_or_default(),
+            art_url: extract_string(&metadata, "mpris:artUrl").unwrap_or_default(),
+            length_us: extract_i64(&metadata, "mpris:length").unwrap_or(0),
+            status: PlaybackStatus::from_mpris(&status_str),
+            track_id: extract_track_id(&metadata),
+            shuffle: flag("Shuffle"),
+            loop_status: LoopStatus::from_mpris(
+                &extract_string(&properties, "LoopStatus").unwrap_or_default(),
+            ),
+            volume: extract_f64(&properties, "Volume").unwrap_or(1.0),
+            rate: extract_f64(&properties, "Rate").unwrap_or(1.0),
+            can_go_next: flag("CanGoNext"),
+            can_go_previous: flag("CanGoPrevious"),
+            can_play: flag("CanPlay"),
+            can_pause: flag("CanPause"),
+            can_seek: flag("CanSeek"),
+            can_control: flag("CanControl"),
+            position_us,
+            position_timestamp_ms: now_ms,
+            source_name: PlayerSource::extract_short_name(&self.bus_name),
+            source_bus_name: self.bus_name.clone(),
+            source_identity: self.identity.clone(),
+        }
+    }
+
+    /// Run a playback command.
+    /// Returns how long to wait before polling the state, in case the player emits no signal.
+    /// Source commands are not meant for a player and return None.
+    pub async fn run_command(&self, cmd: &PlayerCommand) -> Result<Option<Duration>, MprisError> {
+        let proxy = &self.player;
+        let poll_delay_ms = match cmd {
+            PlayerCommand::PlayPause => {
+                debug!("Sending PlayPause command");
+                proxy.play_pause().await?;
+                100
+            }
+            PlayerCommand::Play => {
+                debug!("Sending Play command");
+                proxy.play().await?;
+                100
+            }
+            PlayerCommand::Pause => {
+                debug!("Sending Pause command");
+                proxy.pause().await?;
+                100
+            }
+            PlayerCommand::Stop => {
+                debug!("Sending Stop command");
+                proxy.stop().await?;
+                100
+            }
+            PlayerCommand::Next => {
+                debug!("Sending Next command");
+                proxy.next().await?;
+                // Spotify may not emit signal when paused - poll after delay
+                200
+            }
+            PlayerCommand::Previous => {
+                debug!("Sending Previous command");
+                proxy.previous().await?;
+                // Spotify may not emit signal when paused - poll after delay
+                200
+            }
+            PlayerCommand::Seek(offset) => {
+                debug!("Sending Seek command: offset={}us", offset);
+                proxy.seek(*offset).await?;
+                // Seeked signal should fire, but poll as backup
+                100
+            }
+            PlayerCommand::SetPosition(position) => {
+                debug!("Sending SetPosition command: position={}us", position);
+                // SetPosition is ignored unless it names the current track
+                let metadata = proxy.metadata().await?;
+                let path = extract_track_id(&metadata)
+                    .and_then(|id| zbus::zvariant::ObjectPath::try_from(id).ok())
+                    .ok_or(MprisError::NoTrackId)?;
+                proxy.set_position(&path, *position).await?;
+                // Seeked signal should fire, but poll as backup
+                100
+            }
+            PlayerCommand::OpenUri(uri) => {
+                debug!("Sending OpenUri command: {}", uri);
+                proxy.open_uri(uri).await?;
+                200
+            }
+            PlayerCommand::SetShuffle(shuffle) => {
+                proxy.set_shuffle(*shuffle).await?;
+                100
+            }
+            PlayerCommand::SetLoopStatus(status) => {
+                proxy.set_loop_status(status.as_mpris()).await?;
+                100
+            }
+            PlayerCommand::SetVolume(volume) => {
+                proxy.set_volume(volume.clamp(0.0, 1.0)).await?;
+                100
+            }
+            PlayerCommand::SetRate(rate) => {
+                proxy.set_rate(*rate).await?;
+                100
+            }
+            PlayerCommand::SwitchSource(_) | PlayerCommand::SetPreference(_) => return Ok(None),
+        };
+        Ok(Some(Duration::from_millis(poll_delay_ms)))
+    }
+}
+
+/// Answers a command once it was carried out
+pub(crate) type CommandReply = oneshot::Sender<Result<(), MprisError>>;
+
+/// Bus names of all MPRIS players
+pub(crate) async fn list_players(connection: &Connection) -> Result<Vec<String>, MprisError> {
+    let dbus_proxy = DBusProxy::new(connection).await?;
+    let names = dbus_proxy.list_names().await?;
+    Ok(names
+        .iter()
+        .filter(|n| n.starts_with(MPRIS_PREFIX))
+        .map(|n| n.to_string())
+        .collect())
+}
+
+/// A player appearing on or leaving the bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BusChange {
+    Appeared(String),
+    Left(String),
+}
+
+/// Player changes, ends when the connection is closed
+pub(crate) type BusChanges = BoxStream<'static, BusChange>;
+
+/// Watch the bus for MPRIS players appearing and leaving.
+/// Subscribe before listing the players, so none is missed in between.
+pub(crate) async fn watch_players(connection: &Connection) -> Result<BusChanges, MprisError> {
+    // The bus only sends NameOwnerChanged for names under the MPRIS namespace
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg0ns(MPRIS_NAMESPACE)?
+        .build();
+    let messages = MessageStream::for_match_rule(rule, connection, None).await?;
+
+    let changes = messages.filter_map(|message| {
+        let change = message
+            .ok()
+            .and_then(NameOwnerChanged::from_message)
+            .and_then(|signal| {
+                let args = signal.args().ok()?;
+                let name = args.name().to_string();
+                // The namespace also matches the MPRIS root name itself
+                if !name.starts_with(MPRIS_PREFIX) {
+                    return None;
+                }
+                // A new owner taking over the name counts as the player appearing again
+                Some(match args.new_owner().as_ref() {
+                    Some(_) => BusChange::Appeared(name),
+                    None => BusChange::Left(name),
+                })
+            });
+        future::ready(change)
+    });
+    Ok(changes.fuse().boxed())
+}
+
+// ============ Metadata extraction helpers ============
+
+/// Look up a value, unwrapping variants nested in variants
+#[inline]
+fn get_value<'a>(map: &'a HashMap<String, OwnedValue>, key: &str) -> Option<&'a Value<'a>> {
+    let mut value: &Value = map.get(key)?;
+    while let Value::Value(inner) = value {
+        value = inner;
+    }
+    Some(value)
+}
+
+#[inline]
+fn extract_string(map: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    match get_value(map, key)? {
+        Value::Str(s) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+#[inline]
+fn extract_i64(map: &HashMap<String, OwnedValue>, key: &str) -> Option<i64> {
+    match get_value(map, key)? {
+        Value::I64(i) => Some(*i),
+        Value::U64(u) => Some(*u as i64),
+        Value::I32(i) => Some(*i as i64),
+        Value::U32(u) => Some(*u as i64),
+        _ => None,
+    }
+}
+
+#[inline]
+fn extract_f64(map: &HashMap<String, OwnedValue>, key: &str) -> Option<f64> {
+    match get_value(map, key)? {
+        Value::F64(f) => Some(*f),
+        _ => None,
+    }
+}
+
+#[inline]
+fn extract_bool(map: &HashMap<String, OwnedValue>, key: &str) -> Option<bool> {
+    match get_value(map, key)? {
+        Value::Bool(b) => Some(*b),
+        _ => None,
+    }
+}
+
+#[inline]
+fn extract_dict(
+    map: &HashMap<String, OwnedValue>,
+    key: &str,
+) -> Option<HashMap<String, OwnedValue>> {
+    match get_value(map, key)?.try_clone().ok()? {
+        Value::Dict(dict) => HashMap::try_from(dict).ok(),
+        _ => None,
+    }
+}
+
+#[inline]
+fn extract_str_array(map: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    match get_value(map, key)? {
+        Value::Array(arr) => {
+            let strings: Vec<String> = arr
+                .iter()
+                .filter_map(|item| match item {
+                    Value::Str(s) => Some(s.to_string()),
+                    _ => None,
+                })
+                .collect();
+            if strings.is_empty() {
+                None
+            } else {
+                Some(strings.join(", "))
+            }
+        }
+        _ => None,
+    }
+}
+
+#[inline]
+pub(crate) fn extract_track_id(map: &HashMap<String, OwnedValue>) -> Option<String> {
+    match get_value(map, "mpris:trackid")? {
+        Value::ObjectPath(p) => Some(p.to_string()),
+        Value::Str(s) => Some(s.to_string()),
+        _ => None,
+    }
+}
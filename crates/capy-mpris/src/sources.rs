@@ -1,8 +1,9 @@
-//! Multi-source tracking and favorite management
+//! Multi-source tracking and the source selection policy
 
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 /// A discovered MPRIS player source
 #[derive(Clone, Debug)]
@@ -33,11 +34,77 @@ impl PlayerSource {
     }
 }
 
-/// User preference for source selection
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// Policy for ordering and selecting sources, persisted in `mpris.json`:
+///
+/// ```json
+/// {
+///   "priority": ["spotify", "mpd"],
+///   "blocklist": ["kdeconnect"],
+///   "auto_switch": true,
+///   "stickiness_secs": 10
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredPreference")]
 pub struct SourcePreference {
-    /// Short name of favorite source (e.g. "spotify")
-    pub favorite: Option<String>,
+    /// Short names in order of preference, unlisted sources come after them by name
+    pub priority: Vec<String>,
+    /// Short names of sources that are never listed or selected
+    pub blocklist: Vec<String>,
+    /// Switch to a source as soon as it starts playing
+    pub auto_switch: bool,
+    /// Seconds a selected source is kept before auto switching may replace it
+    pub stickiness_secs: u64,
+}
+
+impl Default for SourcePreference {
+    fn default() -> Self {
+        Self {
+            priority: vec!["spotify".to_string()],
+            blocklist: Vec::new(),
+            auto_switch: false,
+            stickiness_secs: 10,
+        }
+    }
+}
+
+/// Policy as read from `mpris.json`, older versions stored a single favorite
+#[derive(Deserialize)]
+#[serde(default)]
+struct StoredPreference {
+    priority: Vec<String>,
+    blocklist: Vec<String>,
+    auto_switch: bool,
+    stickiness_secs: u64,
+    favorite: Option<String>,
+}
+
+impl Default for StoredPreference {
+    fn default() -> Self {
+        let preference = SourcePreference::default();
+        Self {
+            priority: preference.priority,
+            blocklist: preference.blocklist,
+            auto_switch: preference.auto_switch,
+            stickiness_secs: preference.stickiness_secs,
+            favorite: None,
+        }
+    }
+}
+
+impl From<StoredPreference> for SourcePreference {
+    fn from(stored: StoredPreference) -> Self {
+        let mut preference = Self {
+            priority: stored.priority,
+            blocklist: stored.blocklist,
+            auto_switch: stored.auto_switch,
+            stickiness_secs: stored.stickiness_secs,
+        };
+        if let Some(favorite) = stored.favorite {
+            preference.prioritize(favorite);
+        }
+        preference
+    }
 }
 
 impl SourcePreference {
@@ -58,25 +125,113 @@ impl SourcePreference {
         fs::write(path, json)
     }
 
+    /// Whether the source is on the blocklist
+    pub fn is_blocked(&self, source: &PlayerSource) -> bool {
+        self.blocklist.contains(&source.short_name)
+    }
+
+    /// Drop blocked sources and order the rest by priority
+    pub fn sort_sources(&self, sources: &mut Vec<PlayerSource>) {
+        sources.retain(|s| !self.is_blocked(s));
+        sources.sort_by(|a, b| {
+            self.rank(a)
+                .cmp(&self.rank(b))
+                .then_with(|| a.short_name.cmp(&b.short_name))
+        });
+    }
+
     /// Select the best source from available list
     pub fn select_source<'a>(&self, sources: &'a [PlayerSource]) -> Option<&'a PlayerSource> {
-        // If favorite exists and is available, use it
-        if let Some(fav) = &self.favorite {
-            if let Some(src) = sources.iter().find(|s| &s.short_name == fav) {
-                return Some(src);
-            }
+        sources
+            .iter()
+            .filter(|s| !self.is_blocked(s))
+            .min_by_key(|s| self.rank(s))
+    }
+
+    /// Whether a source that started playing may replace the one selected `selected_for` ago
+    pub fn allows_auto_switch(&self, selected_for: Duration) -> bool {
+        self.auto_switch && selected_for >= Duration::from_secs(self.stickiness_secs)
+    }
+
+    /// Move a source to the top of the priority list
+    pub fn prioritize(&mut self, short_name: String) {
+        self.priority.retain(|name| *name != short_name);
+        self.priority.insert(0, short_name);
+    }
+
+    /// Position in the priority list, unlisted sources rank last
+    fn rank(&self, source: &PlayerSource) -> usize {
+        self.priority
+            .iter()
+            .position(|name| *name == source.short_name)
+            .unwrap_or(self.priority.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(short_name: &str) -> PlayerSource {
+        PlayerSource {
+            bus_name: format!("org.mpris.MediaPlayer2.{}", short_name),
+            identity: short_name.to_string(),
+            short_name: short_name.to_string(),
+            can_play: true,
+            can_pause: true,
+            can_seek: true,
         }
-        // Otherwise use first available
-        sources.first()
     }
 
-    /// Set favorite source
-    pub fn set_favorite(&mut self, short_name: String) {
-        self.favorite = Some(short_name);
+    fn short_names(sources: &[PlayerSource]) -> Vec<&str> {
+        sources.iter().map(|s| s.short_name.as_str()).collect()
     }
 
-    /// Clear favorite
-    pub fn clear_favorite(&mut self) {
-        self.favorite = None;
+    #[test]
+    fn test_sources_ordered_by_policy() {
+        let preference = SourcePreference {
+            priority: vec!["mpd".to_string(), "spotify".to_string()],
+            blocklist: vec!["kdeconnect".to_string()],
+            ..Default::default()
+        };
+        let mut sources = ["vlc", "kdeconnect", "spotify", "firefox", "mpd"]
+            .map(source)
+            .to_vec();
+
+        preference.sort_sources(&mut sources);
+        assert_eq!(short_names(&sources), ["mpd", "spotify", "firefox", "vlc"]);
+
+        let all = ["kdeconnect", "vlc", "spotify"].map(source);
+        assert_eq!(
+            preference.select_source(&all).unwrap().short_name,
+            "spotify"
+        );
+        assert!(preference.select_source(&[source("kdeconnect")]).is_none());
+    }
+
+    #[test]
+    fn test_legacy_favorite_moves_to_top() {
+        let path = std::env::temp_dir().join(format!("capy-mpris-{}.json", std::process::id()));
+        fs::write(&path, r#"{"favorite": "vlc"}"#).unwrap();
+
+        let preference = SourcePreference::load(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(preference.priority, ["vlc", "spotify"]);
+        assert!(
+            !serde_json::to_string(&preference)
+                .unwrap()
+                .contains("favorite")
+        );
+    }
+
+    #[test]
+    fn test_auto_switch_waits_for_stickiness() {
+        let mut preference = SourcePreference::default();
+        assert!(!preference.allows_auto_switch(Duration::from_secs(60)));
+
+        preference.auto_switch = true;
+        assert!(!preference.allows_auto_switch(Duration::from_secs(3)));
+        assert!(preference.allows_auto_switch(Duration::from_secs(10)));
     }
 }
@@ -0,0 +1,184 @@
+//! Private D-Bus session with fake MPRIS players, for tests
+
+use crate::player::{MPRIS_PATH, MPRIS_PREFIX};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use zbus::object_server::SignalContext;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{Connection, connection};
+
+/// How long tests wait for an event
+pub(crate) const EVENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A `dbus-daemon` running for the duration of a test
+pub(crate) struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Start a private session bus, None if `dbus-daemon` is not installed
+    pub fn start() -> Option<Self> {
+        let mut daemon = match Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(daemon) => daemon,
+            Err(e) => {
+                eprintln!("Skipping test, failed to start dbus-daemon: {}", e);
+                return None;
+            }
+        };
+
+        let mut address = String::new();
+        let stdout = daemon.stdout.take()?;
+        BufReader::new(stdout).read_line(&mut address).ok()?;
+        Some(Self {
+            daemon,
+            address: address.trim().to_string(),
+        })
+    }
+
+    /// New client connection to the bus
+    pub async fn connect(&self) -> Connection {
+        connection::Builder::address(self.address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    /// Put a fake player on the bus as `org.mpris.MediaPlayer2.<name>`.
+    /// The player leaves the bus when the returned connection is dropped.
+    pub async fn add_player(&self, name: &str) -> Connection {
+        self.serve_player(name, true).await
+    }
+
+    /// Put a fake player on the bus that reports no `mpris:trackid`
+    pub async fn add_player_without_track(&self, name: &str) -> Connection {
+        self.serve_player(name, false).await
+    }
+
+    async fn serve_player(&self, name: &str, has_track_id: bool) -> Connection {
+        connection::Builder::address(self.address.as_str())
+            .unwrap()
+            .name(format!("{}{}", MPRIS_PREFIX, name))
+            .unwrap()
+            .serve_at(MPRIS_PATH, FakeRoot::new(name))
+            .unwrap()
+            .serve_at(MPRIS_PATH, FakePlayer::new(name, has_track_id))
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// Wait for the next event, failing the test after [`EVENT_TIMEOUT`]
+pub(crate) async fn next_event<T>(rx: &mut UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(EVENT_TIMEOUT, rx.recv())
+        .await
+        .expect("timed out waiting for an event")
+        .expect("event channel closed")
+}
+
+/// Root interface of a fake player
+struct FakeRoot {
+    identity: String,
+}
+
+impl FakeRoot {
+    fn new(name: &str) -> Self {
+        Self {
+            identity: format!("Fake {}", name),
+        }
+    }
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2")]
+impl FakeRoot {
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        self.identity.clone()
+    }
+}
+
+/// Player interface of a fake player, playing one track
+struct FakePlayer {
+    title: String,
+    has_track_id: bool,
+    playing: bool,
+    position: i64,
+}
+
+impl FakePlayer {
+    fn new(name: &str, has_track_id: bool) -> Self {
+        Self {
+            title: format!("{} track", name),
+            has_track_id,
+            playing: false,
+            position: 0,
+        }
+    }
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+impl FakePlayer {
+    async fn play_pause(&mut self, #[zbus(signal_context)] ctx: SignalContext<'_>) {
+        self.playing = !self.playing;
+        let _ = self.playback_status_changed(&ctx).await;
+    }
+
+    fn set_position(&mut self, _track_id: ObjectPath<'_>, position: i64) {
+        self.position = position;
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        self.position
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        if self.playing { "Playing" } else { "Paused" }.to_string()
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let track_id = ObjectPath::from_static_str_unchecked("/org/mpris/MediaPlayer2/Track/1");
+        let mut metadata = vec![("xesam:title", Value::from(self.title.as_str()))];
+        if self.has_track_id {
+            metadata.push(("mpris:trackid", Value::from(track_id)));
+        }
+        metadata
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.try_into().unwrap()))
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+}
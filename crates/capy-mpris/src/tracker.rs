@@ -0,0 +1,368 @@
+//! Tracking every MPRIS player at once
+//!
+//! The tracker keeps a live [`MprisData`] for every player on the bus. Each
+//! player is watched by its own task and commands are routed by bus name.
+//! Players are picked up and dropped as their bus names come and go.
+//! [`MprisClient`](crate::MprisClient) follows its active player through a tracker.
+
+use crate::error::MprisError;
+use crate::player::{
+    BusChange, BusChanges, CommandReply, PlayerSession, SeekedStream, list_players, watch_players,
+};
+use crate::types::{MprisData, PlayerCommand};
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use zbus::Connection;
+use zbus::fdo::PropertiesChangedStream;
+
+/// Change to one of the tracked players
+#[derive(Clone, Debug)]
+pub enum PlayerUpdate {
+    /// A player appeared or its state changed
+    Updated(Box<MprisData>), // Boxed to keep enum size small
+    /// A player left the bus, passes its bus name
+    Removed(String),
+}
+
+/// Handle to the tracked players, cheap to clone.
+/// Tracking stops once every handle is dropped.
+#[derive(Clone)]
+pub struct PlayerTracker {
+    players: Arc<RwLock<HashMap<String, MprisData>>>,
+    commands: UnboundedSender<(String, PlayerCommand, CommandReply)>,
+}
+
+impl PlayerTracker {
+    /// Start tracking all players. `on_update` is called for every change.
+    pub async fn start<F>(on_update: F) -> Result<Self, MprisError>
+    where
+        F: Fn(PlayerUpdate) + Send + Sync + 'static,
+    {
+        Self::start_with_connection(Connection::session().await?, on_update).await
+    }
+
+    /// Start tracking all players on an existing connection.
+    /// Players already on the bus are in [`players`](Self::players) once this returns.
+    pub async fn start_with_connection<F>(
+        connection: Connection,
+        on_update: F,
+    ) -> Result<Self, MprisError>
+    where
+        F: Fn(PlayerUpdate) + Send + Sync + 'static,
+    {
+        let changes = watch_players(&connection).await?;
+        let mut initial = Vec::new();
+        for bus_name in list_players(&connection).await? {
+            if let Some(player) = WatchedPlayer::connect(&connection, &bus_name).await {
+                initial.push(player);
+            }
+        }
+
+        let players = Arc::new(RwLock::new(
+            initial
+                .iter()
+                .map(|(_, data)| (data.source_bus_name.clone(), data.clone()))
+                .collect(),
+        ));
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(run_tracker(
+            connection,
+            initial,
+            changes,
+            players.clone(),
+            cmd_rx,
+            on_update,
+        ));
+
+        Ok(Self {
+            players,
+            commands: cmd_tx,
+        })
+    }
+
+    /// Snapshot of all players, sorted by bus name
+    pub fn players(&self) -> Vec<MprisData> {
+        let players = self.players.read().unwrap_or_else(PoisonError::into_inner);
+        let mut players: Vec<MprisData> = players.values().cloned().collect();
+        players.sort_by(|a, b| a.source_bus_name.cmp(&b.source_bus_name));
+        players
+    }
+
+    /// State of the player with this bus name
+    pub fn player(&self, bus_name: &str) -> Option<MprisData> {
+        let players = self.players.read().unwrap_or_else(PoisonError::into_inner);
+        players.get(bus_name).cloned()
+    }
+
+    /// Run a command on the player with this bus name, resolves once it was carried out.
+    /// Source commands only apply to [`MprisClient`](crate::MprisClient) and are ignored.
+    pub async fn send(&self, bus_name: &str, command: PlayerCommand) -> Result<(), MprisError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.commands
+            .send((bus_name.to_string(), command, reply_tx))
+            .map_err(|_| MprisError::Disconnected)?;
+        reply_rx.await.map_err(|_| MprisError::Disconnected)?
+    }
+}
+
+/// A connected player with the streams that fire when its state changes
+struct WatchedPlayer {
+    session: PlayerSession,
+    properties: PropertiesChangedStream<'static>,
+    seeked: SeekedStream<'static>,
+}
+
+impl WatchedPlayer {
+    /// Connect to a player and fetch its state, failures are logged
+    async fn connect(connection: &Connection, bus_name: &str) -> Option<(Self, MprisData)> {
+        let session = match PlayerSession::connect(connection, bus_name).await {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("Failed to connect to player {}: {}", bus_name, e);
+                return None;
+            }
+        };
+        let (properties, seeked) = match session.subscribe().await {
+            Ok(streams) => streams,
+            Err(e) => {
+                warn!("Failed to subscribe to player {}: {}", bus_name, e);
+                return None;
+            }
+        };
+
+        let data = session.fetch_state().await;
+        Some((
+            Self {
+                session,
+                properties,
+                seeked,
+            },
+            data,
+        ))
+    }
+}
+
+/// Watch the bus for players and keep the shared state current
+async fn run_tracker<F>(
+    connection: Connection,
+    initial: Vec<(WatchedPlayer, MprisData)>,
+    mut changes: BusChanges,
+    players: Arc<RwLock<HashMap<String, MprisData>>>,
+    mut cmd_rx: UnboundedReceiver<(String, PlayerCommand, CommandReply)>,
+    on_update: F,
+) where
+    F: Fn(PlayerUpdate) + Send + Sync + 'static,
+{
+    let (update_tx, mut update_rx) = mpsc::unbounded_channel::<MprisData>();
+    // Command senders of the player tasks, dropping one stops its task
+    let mut watchers: HashMap<String, UnboundedSender<(PlayerCommand, CommandReply)>> =
+        HashMap::new();
+
+    // Players found at startup are already connected and in the shared state
+    for (player, data) in initial {
+        let bus_name = data.source_bus_name.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        watchers.insert(bus_name, tx);
+        tokio::spawn(watch_player(player, rx, update_tx.clone()));
+        on_update(PlayerUpdate::Updated(Box::new(data)));
+    }
+
+    let spawn_watcher = |watchers: &mut HashMap<_, _>, bus_name: String| {
+        info!("Player appeared: {}", bus_name);
+        let (tx, rx) = mpsc::unbounded_channel();
+        watchers.insert(bus_name.clone(), tx);
+        let (connection, updates) = (connection.clone(), update_tx.clone());
+        tokio::spawn(async move {
+            if let Some((player, data)) = WatchedPlayer::connect(&connection, &bus_name).await {
+                let _ = updates.send(data);
+                watch_player(player, rx, updates).await;
+            }
+        });
+    };
+
+    loop {
+        tokio::select! {
+            change = changes.next() => match change {
+                Some(BusChange::Appeared(bus_name)) => {
+                    if !watchers.contains_key(&bus_name) {
+                        spawn_watcher(&mut watchers, bus_name);
+                    }
+                }
+                Some(BusChange::Left(bus_name)) => {
+                    if watchers.remove(&bus_name).is_none() {
+                        continue;
+                    }
+                    info!("Player left: {}", bus_name);
+                    players
+                        .write()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&bus_name);
+                    on_update(PlayerUpdate::Removed(bus_name));
+                }
+                None => {
+                    warn!("D-Bus connection closed, no longer tracking players");
+                    break;
+                }
+            },
+
+            Some(data) = update_rx.recv() => {
+                // Late updates from players that already left are dropped
+                if !watchers.contains_key(&data.source_bus_name) {
+                    continue;
+                }
+                players
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(data.source_bus_name.clone(), data.clone());
+                on_update(PlayerUpdate::Updated(Box::new(data)));
+            }
+
+            command = cmd_rx.recv() => {
+                // Every handle was dropped, stop tracking
+                let Some((bus_name, command, reply)) = command else {
+                    break;
+                };
+                match watchers.get(&bus_name) {
+                    Some(watcher) => {
+                        // A watcher that already stopped drops the reply, failing the command
+                        let _ = watcher.send((command, reply));
+                    }
+                    None => {
+                        debug!("No player {} for command {:?}", bus_name, command);
+                        let _ = reply.send(Err(MprisError::NoPlayer));
+                    }
+                }
+            }
+        }
+    }
+
+    debug!("Player tracker stopped");
+}
+
+/// Send the state of one player whenever it changes, and run its commands
+async fn watch_player(
+    player: WatchedPlayer,
+    mut cmd_rx: UnboundedReceiver<(PlayerCommand, CommandReply)>,
+    updates: UnboundedSender<MprisData>,
+) {
+    let WatchedPlayer {
+        session,
+        mut properties,
+        mut seeked,
+    } = player;
+
+    loop {
+        tokio::select! {
+            Some(_) = properties.next() => {
+                let _ = updates.send(session.fetch_state().await);
+            }
+
+            Some(_) = seeked.next() => {
+                let _ = updates.send(session.fetch_state().await);
+            }
+
+            command = cmd_rx.recv() => {
+                // The tracker dropped the sender, the player left
+                let Some((command, reply)) = command else {
+                    break;
+                };
+                let result = session.run_command(&command).await;
+                let poll_delay = result.as_ref().ok().copied().flatten();
+                let _ = reply.send(result.map(|_| ()));
+                if let Some(poll_delay) = poll_delay {
+                    tokio::time::sleep(poll_delay).await;
+                    let _ = updates.send(session.fetch_state().await);
+                }
+            }
+        }
+    }
+
+    // Drop streams explicitly to remove the match rules before the task ends
+    drop(properties);
+    drop(seeked);
+    tokio::task::yield_now().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::{TestBus, next_event};
+    use crate::types::PlaybackStatus;
+
+    async fn next_update(rx: &mut UnboundedReceiver<PlayerUpdate>) -> PlayerUpdate {
+        next_event(rx).await
+    }
+
+    #[tokio::test]
+    async fn test_players_appear_and_leave() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+        let _first = bus.add_player("first").await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let tracker = PlayerTracker::start_with_connection(bus.connect().await, move |update| {
+            let _ = tx.send(update);
+        })
+        .await
+        .unwrap();
+
+        let PlayerUpdate::Updated(data) = next_update(&mut rx).await else {
+            panic!("expected the first player");
+        };
+        assert_eq!(data.source_bus_name, "org.mpris.MediaPlayer2.first");
+        assert_eq!(data.source_identity, "Fake first");
+        assert_eq!(data.title, "first track");
+
+        let second = bus.add_player("second").await;
+        let PlayerUpdate::Updated(data) = next_update(&mut rx).await else {
+            panic!("expected the second player");
+        };
+        assert_eq!(data.source_bus_name, "org.mpris.MediaPlayer2.second");
+        assert_eq!(tracker.players().len(), 2);
+
+        drop(second);
+        let PlayerUpdate::Removed(bus_name) = next_update(&mut rx).await else {
+            panic!("expected the second player to leave");
+        };
+        assert_eq!(bus_name, "org.mpris.MediaPlayer2.second");
+        assert!(tracker.player(&bus_name).is_none());
+        assert_eq!(tracker.players().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_commands_routed_by_bus_name() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+        let _first = bus.add_player("first").await;
+        let _second = bus.add_player("second").await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let tracker = PlayerTracker::start_with_connection(bus.connect().await, move |update| {
+            let _ = tx.send(update);
+        })
+        .await
+        .unwrap();
+        next_update(&mut rx).await;
+        next_update(&mut rx).await;
+
+        tracker
+            .send("org.mpris.MediaPlayer2.second", PlayerCommand::PlayPause)
+            .await
+            .unwrap();
+        let PlayerUpdate::Updated(data) = next_update(&mut rx).await else {
+            panic!("expected a state update");
+        };
+        assert_eq!(data.source_bus_name, "org.mpris.MediaPlayer2.second");
+        assert_eq!(data.status, PlaybackStatus::Playing);
+
+        let first = tracker.player("org.mpris.MediaPlayer2.first").unwrap();
+        assert_eq!(first.status, PlaybackStatus::Paused);
+    }
+}
@@ -1,5 +1,6 @@
 //! Core types for capy-mpris
 
+use crate::sources::SourcePreference;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Playback status from MPRIS player
@@ -12,7 +13,8 @@ pub enum PlaybackStatus {
 }
 
 impl PlaybackStatus {
-    pub fn from_str(s: &str) -> Self {
+    /// Parse the MPRIS `PlaybackStatus` property, unknown values count as stopped
+    pub fn from_mpris(s: &str) -> Self {
         match s {
             "Playing" => PlaybackStatus::Playing,
             "Paused" => PlaybackStatus::Paused,
@@ -25,17 +27,64 @@ impl PlaybackStatus {
     }
 }
 
+/// Loop mode from MPRIS player
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopStatus {
+    #[default]
+    None,
+    /// Repeat the current track
+    Track,
+    /// Repeat the whole playlist
+    Playlist,
+}
+
+impl LoopStatus {
+    /// Parse the MPRIS `LoopStatus` property, unknown values count as no loop
+    pub fn from_mpris(s: &str) -> Self {
+        match s {
+            "Track" => LoopStatus::Track,
+            "Playlist" => LoopStatus::Playlist,
+            _ => LoopStatus::None,
+        }
+    }
+
+    /// Value of the MPRIS `LoopStatus` property
+    pub fn as_mpris(&self) -> &'static str {
+        match self {
+            LoopStatus::None => "None",
+            LoopStatus::Track => "Track",
+            LoopStatus::Playlist => "Playlist",
+        }
+    }
+
+    /// Next mode when cycling through them: None -> Playlist -> Track -> None
+    pub fn cycle(&self) -> Self {
+        match self {
+            LoopStatus::None => LoopStatus::Playlist,
+            LoopStatus::Playlist => LoopStatus::Track,
+            LoopStatus::Track => LoopStatus::None,
+        }
+    }
+}
+
 /// Commands that can be sent to the media player
 #[derive(Clone, Debug)]
 pub enum PlayerCommand {
     PlayPause,
+    Play,
+    Pause,
+    Stop,
     Next,
     Previous,
-    Seek(i64),            // Offset in microseconds
-    SetPosition(i64),     // Absolute position in microseconds
-    SwitchSource(String), // Switch to named source (short name)
-    SetFavorite(String),  // Set favorite source (short name)
-    ClearFavorite,        // Clear favorite
+    Seek(i64),                       // Offset in microseconds
+    SetPosition(i64),                // Absolute position in microseconds
+    OpenUri(String),                 // Open a track by URI, e.g. "file:///..."
+    SetShuffle(bool),                // Enable or disable shuffle
+    SetLoopStatus(LoopStatus),       // Set the loop mode
+    SetVolume(f64),                  // Volume between 0.0 and 1.0
+    SetRate(f64),                    // Playback rate, 1.0 is normal speed
+    SwitchSource(String),            // Switch to named source (short name)
+    SetPreference(SourcePreference), // Replace and save the source policy
 }
 
 /// Media player state snapshot with interpolation info
@@ -49,6 +98,23 @@ pub struct MprisData {
     pub status: PlaybackStatus,
     pub track_id: Option<String>,
 
+    // Playback options
+    pub shuffle: bool,
+    pub loop_status: LoopStatus,
+    /// Volume between 0.0 and 1.0
+    pub volume: f64,
+    /// Playback rate, 1.0 is normal speed
+    pub rate: f64,
+
+    // Capabilities - controls for unsupported actions should be disabled
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_seek: bool,
+    /// False if the player cannot be controlled at all
+    pub can_control: bool,
+
     // For client-side interpolation
     /// Last known position from D-Bus (microseconds)
     pub position_us: i64,
@@ -60,9 +126,21 @@ pub struct MprisData {
     pub source_name: String,
     /// Full D-Bus name, e.g. "org.mpris.MediaPlayer2.spotify"
     pub source_bus_name: String,
+    /// Identity from MPRIS, e.g. "Spotify"
+    pub source_identity: String,
 }
 
 impl MprisData {
+    /// Whether play/pause does anything in the current state
+    pub fn can_play_pause(&self) -> bool {
+        self.can_control
+            && if self.status.is_playing() {
+                self.can_pause
+            } else {
+                self.can_play
+            }
+    }
+
     /// Get current interpolated position in microseconds
     pub fn interpolated_position_us(&self) -> i64 {
         if !self.status.is_playing() {
@@ -74,8 +152,10 @@ impl MprisData {
             .map(|d| d.as_millis() as u64)
             .unwrap_or(self.position_timestamp_ms);
 
+        // Rate is 0 when the player did not report it
+        let rate = if self.rate > 0.0 { self.rate } else { 1.0 };
         let elapsed_ms = now_ms.saturating_sub(self.position_timestamp_ms);
-        let elapsed_us = (elapsed_ms * 1000) as i64;
+        let elapsed_us = (elapsed_ms as f64 * 1000.0 * rate) as i64;
 
         (self.position_us + elapsed_us).min(self.length_us).max(0)
     }
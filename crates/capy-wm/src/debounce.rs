@@ -0,0 +1,214 @@
+//! Coalescing of event bursts.
+//!
+//! Compositors often emit several events for a single user action (a window
+//! opening fires open, focus, workspace and title events). Backends trigger a
+//! [`Debouncer`] for each event and the refresh runs once per burst.
+
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+type Action = Box<dyn Fn() + Send + Sync>;
+
+struct Inner {
+    pending: Mutex<bool>,
+    wake: Condvar,
+    action: Action,
+}
+
+impl Inner {
+    /// Run the action if it was triggered since the last run.
+    fn flush(&self) -> bool {
+        let pending =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        if pending {
+            (self.action)();
+        }
+        pending
+    }
+}
+
+/// Runs an action on a worker thread at most once per `delay`, no matter how often it is triggered.
+pub(crate) struct Debouncer {
+    inner: Arc<Inner>,
+}
+
+impl Debouncer {
+    /// Spawn the worker thread running `action` after each burst of triggers.
+    pub(crate) fn new<F>(delay: Duration, action: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let debouncer = Self::manual(action);
+
+        let worker = debouncer.inner.clone();
+        thread::spawn(move || {
+            loop {
+                {
+                    let mut pending = worker
+                        .pending
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner);
+                    while !*pending {
+                        pending = worker
+                            .wake
+                            .wait(pending)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                }
+
+                // Let the rest of the burst arrive, triggers after this point schedule another run
+                thread::sleep(delay);
+                worker.flush();
+            }
+        });
+
+        debouncer
+    }
+
+    /// Debouncer without a worker, the action only runs on [`flush`](Self::flush).
+    fn manual<F>(action: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(Inner {
+                pending: Mutex::new(false),
+                wake: Condvar::new(),
+                action: Box::new(action),
+            }),
+        }
+    }
+
+    /// Schedule a run of the action.
+    pub(crate) fn trigger(&self) {
+        *self
+            .inner
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = true;
+        self.inner.wake.notify_one();
+    }
+
+    /// Run the action now if it was triggered since the last run, returns whether it ran.
+    #[cfg(test)]
+    pub(crate) fn flush(&self) -> bool {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{self, LayoutSnapshot, MonitorSnapshot};
+    use crate::types::WmEventKind;
+    use crate::{EventFilter, WmEvent, subscribe_with};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn monitor(name: &str, active_workspace: i32) -> MonitorSnapshot {
+        MonitorSnapshot {
+            name: name.to_string(),
+            active_workspace: Some(active_workspace),
+            active_special: None,
+            focused: false,
+        }
+    }
+
+    fn counting_debouncer() -> (Debouncer, Arc<AtomicUsize>) {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let debouncer = Debouncer::manual(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        (debouncer, runs)
+    }
+
+    #[test]
+    fn test_burst_runs_once() {
+        let (debouncer, runs) = counting_debouncer();
+
+        for _ in 0..50 {
+            debouncer.trigger();
+        }
+        assert!(debouncer.flush());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // Nothing was triggered since
+        assert!(!debouncer.flush());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        debouncer.trigger();
+        assert!(debouncer.flush());
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_worker_runs_after_trigger() {
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let done_tx = Mutex::new(done_tx);
+        let debouncer = Debouncer::new(Duration::from_millis(1), move || {
+            let _ = done_tx.lock().unwrap().send(());
+        });
+
+        debouncer.trigger();
+        done_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("worker never ran the action");
+    }
+
+    /// One snapshot per burst, events only for changed monitors.
+    #[test]
+    fn test_snapshot_fetches_per_event_burst() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let active = Arc::new(AtomicUsize::new(1));
+        let subscription =
+            subscribe_with(EventFilter::new().kinds([WmEventKind::WorkspacesChanged]));
+
+        let (fetch_count, active_ws) = (fetches.clone(), active.clone());
+        let debouncer = Debouncer::manual(move || {
+            fetch_count.fetch_add(1, Ordering::SeqCst);
+            let snapshot = LayoutSnapshot {
+                monitors: vec![
+                    monitor("BURST-1", active_ws.load(Ordering::SeqCst) as i32),
+                    monitor("BURST-2", 11),
+                    monitor("BURST-3", 21),
+                ],
+                ..Default::default()
+            };
+            layout::publish_changes(&snapshot);
+        });
+
+        let changed_monitors = || -> Vec<String> {
+            subscription
+                .try_iter()
+                .filter_map(|event| match event {
+                    WmEvent::WorkspacesChanged(s) if s.monitor_name.starts_with("BURST-") => {
+                        Some(s.monitor_name)
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // e.g. a title event burst
+        for _ in 0..20 {
+            debouncer.trigger();
+        }
+        assert!(debouncer.flush());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(changed_monitors(), ["BURST-1", "BURST-2", "BURST-3"]);
+
+        // Nothing changed, so nothing is sent
+        debouncer.trigger();
+        assert!(debouncer.flush());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+        assert!(changed_monitors().is_empty());
+
+        // Only the monitor that switched workspace is updated
+        active.store(2, Ordering::SeqCst);
+        debouncer.trigger();
+        assert!(debouncer.flush());
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+        assert_eq!(changed_monitors(), ["BURST-1"]);
+    }
+}
@@ -0,0 +1,32 @@
+//! Error types for capy-wm
+
+/// Window manager errors
+#[derive(Debug, thiserror::Error)]
+pub enum WmError {
+    #[error("{0} is not supported by this backend")]
+    Unsupported(&'static str),
+
+    #[error("No supported window manager detected")]
+    NoBackend,
+
+    #[error("Window manager rejected the request: {0}")]
+    Dispatch(String),
+
+    #[error("Window manager query failed: {0}")]
+    Query(String),
+
+    #[error("IPC error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unknown monitor: {0}")]
+    UnknownMonitor(String),
+
+    #[error("Invalid monitor profile: {0}")]
+    InvalidProfile(String),
+
+    #[error("Invalid event recording: {0}")]
+    InvalidRecording(String),
+}
+
+/// Result type for window manager operations.
+pub type WmResult<T> = Result<T, WmError>;
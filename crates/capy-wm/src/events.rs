@@ -0,0 +1,202 @@
+//! Event subscriptions.
+//!
+//! Every consumer (panels, launcher, IPC server, ...) gets its own bounded
+//! channel through [`subscribe`]. Events are delivered to all subscribers whose
+//! filter matches, and a subscriber is removed when its [`Subscription`] is dropped.
+
+use crate::types::{WmEvent, WmEventKind};
+use log::warn;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Number of events buffered per subscriber before new events are dropped.
+pub const SUBSCRIPTION_CAPACITY: usize = 256;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+
+struct Subscriber {
+    id: u64,
+    filter: EventFilter,
+    sender: SyncSender<WmEvent>,
+}
+
+/// Selects which events a subscription receives. The default filter accepts everything.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    monitor: Option<String>,
+    kinds: Option<Vec<WmEventKind>>,
+}
+
+impl EventFilter {
+    /// Filter accepting all events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only receive events for this monitor. Global events (focus, closed windows) still pass.
+    pub fn monitor(mut self, monitor: impl Into<String>) -> Self {
+        self.monitor = Some(monitor.into());
+        self
+    }
+
+    /// Only receive events of these kinds.
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = WmEventKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// Whether an event passes this filter.
+    pub fn matches(&self, event: &WmEvent) -> bool {
+        let kind_matches = self
+            .kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(&event.kind()));
+        let monitor_matches = match (&self.monitor, event.monitor()) {
+            (Some(wanted), Some(monitor)) => wanted == monitor,
+            _ => true,
+        };
+        kind_matches && monitor_matches
+    }
+}
+
+/// Handle to a stream of WM events. Unsubscribes when dropped.
+pub struct Subscription {
+    id: u64,
+    receiver: Receiver<WmEvent>,
+}
+
+impl Subscription {
+    /// Block until the next event arrives.
+    pub fn recv(&self) -> Option<WmEvent> {
+        self.receiver.recv().ok()
+    }
+
+    /// Next event if one is pending.
+    pub fn try_recv(&self) -> Option<WmEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Wait up to `timeout` for the next event.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<WmEvent> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Blocking iterator over incoming events.
+    pub fn iter(&self) -> impl Iterator<Item = WmEvent> + '_ {
+        self.receiver.iter()
+    }
+
+    /// Iterator over the events pending right now.
+    pub fn try_iter(&self) -> impl Iterator<Item = WmEvent> + '_ {
+        self.receiver.try_iter()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut subscribers = SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner);
+        subscribers.retain(|s| s.id != self.id);
+    }
+}
+
+/// Subscribe to all WM events.
+pub fn subscribe() -> Subscription {
+    subscribe_with(EventFilter::new())
+}
+
+/// Subscribe to the WM events matching `filter`.
+pub fn subscribe_with(filter: EventFilter) -> Subscription {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = mpsc::sync_channel(SUBSCRIPTION_CAPACITY);
+
+    SUBSCRIBERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Subscriber { id, filter, sender });
+
+    Subscription { id, receiver }
+}
+
+/// Deliver an event to every matching subscriber.
+/// A full subscriber misses the event rather than blocking the backend.
+pub(crate) fn publish(event: &WmEvent) {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner);
+    subscribers.retain(|subscriber| {
+        if !subscriber.filter.matches(event) {
+            return true;
+        }
+        match subscriber.sender.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    "Subscriber {} is full, dropping {:?} event",
+                    subscriber.id,
+                    event.kind()
+                );
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WorkspacesStatus;
+
+    fn workspaces(monitor: &str) -> WmEvent {
+        WmEvent::WorkspacesChanged(WorkspacesStatus {
+            monitor_name: monitor.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn subscriber_exists(id: u64) -> bool {
+        SUBSCRIBERS.lock().unwrap().iter().any(|s| s.id == id)
+    }
+
+    #[test]
+    fn test_filtered_subscribers_receive_independently() {
+        let all = subscribe_with(EventFilter::new().monitor("SUB-1"));
+        let added = subscribe_with(
+            EventFilter::new()
+                .monitor("SUB-1")
+                .kinds([WmEventKind::MonitorAdded]),
+        );
+
+        publish(&workspaces("SUB-1"));
+        publish(&workspaces("SUB-2"));
+        publish(&WmEvent::MonitorAdded("SUB-1".to_string()));
+
+        let kinds: Vec<_> = all.try_iter().map(|e| e.kind()).collect();
+        assert_eq!(
+            kinds,
+            [WmEventKind::WorkspacesChanged, WmEventKind::MonitorAdded]
+        );
+        let kinds: Vec<_> = added.try_iter().map(|e| e.kind()).collect();
+        assert_eq!(kinds, [WmEventKind::MonitorAdded]);
+    }
+
+    #[test]
+    fn test_drop_unsubscribes() {
+        let subscription = subscribe_with(EventFilter::new().monitor("SUB-DROP"));
+        let id = subscription.id;
+        assert!(subscriber_exists(id));
+
+        drop(subscription);
+        assert!(!subscriber_exists(id));
+    }
+
+    #[test]
+    fn test_full_subscriber_does_not_block() {
+        let subscription = subscribe_with(EventFilter::new().monitor("SUB-FULL"));
+        for _ in 0..SUBSCRIPTION_CAPACITY + 10 {
+            publish(&workspaces("SUB-FULL"));
+        }
+        assert_eq!(subscription.try_iter().count(), SUBSCRIPTION_CAPACITY);
+    }
+}
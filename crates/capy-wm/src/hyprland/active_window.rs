@@ -1,41 +1,51 @@
 //! Active window tracking for Hyprland.
 
 use crate::types::ActiveWindowInfo;
-use crate::{WmEvent, get_state, resolve_icon, send_event};
+use crate::{WmEvent, WmResult, get_state, read, resolve_icon, send_event};
 use hyprland::data::{Client, Monitors};
 use hyprland::event_listener::{WindowEventData, WindowTitleEventData};
 use hyprland::shared::{HyprData, HyprDataActiveOptional};
 use log::warn;
 
-fn get_active_monitor_name() -> String {
-    Monitors::get()
-        .ok()
-        .and_then(|monitors| monitors.iter().find(|m| m.focused).map(|m| m.name.clone()))
-        .unwrap_or_default()
+use super::query_error;
+
+/// Name of the focused monitor, empty if no monitor has focus.
+pub(crate) fn get_active_monitor_name() -> WmResult<String> {
+    let monitors = Monitors::get().map_err(query_error)?;
+    Ok(monitors
+        .iter()
+        .find(|m| m.focused)
+        .map(|m| m.name.clone())
+        .unwrap_or_default())
+}
+
+/// Focused monitor for event handlers, where a failed query can only be logged.
+fn focused_monitor() -> String {
+    get_active_monitor_name().unwrap_or_else(|e| {
+        warn!("Failed to get the focused Hyprland monitor: {}", e);
+        String::new()
+    })
 }
 
 /// Initialize active window state from current Hyprland state.
-pub(crate) fn init() {
-    if let Ok(Some(active)) = Client::get_active() {
-        let info = ActiveWindowInfo {
+pub(crate) fn init() -> WmResult<()> {
+    let info = match Client::get_active().map_err(query_error)? {
+        Some(active) => ActiveWindowInfo {
             address: active.address.to_string(),
             app: active.class.clone(),
             window_title: active.title,
             icon_path: resolve_icon(&active.class),
-            focused_monitor: get_active_monitor_name(),
-        };
-        send_event(WmEvent::ActiveWindowChanged(info));
-    } else {
-        warn!("Failed to initialize active window");
-    }
+            focused_monitor: get_active_monitor_name()?,
+        },
+        None => ActiveWindowInfo::default(),
+    };
+    send_event(WmEvent::ActiveWindowChanged(info));
+    Ok(())
 }
 
 /// Set the active window from a Hyprland event.
 pub(crate) fn set(window: Option<WindowEventData>) {
-    let current_address = match get_state().active_window.read() {
-        Ok(guard) => guard.address.clone(),
-        Err(_) => String::new(),
-    };
+    let current_address = read(&get_state().active_window).address.clone();
 
     match window {
         Some(w) if w.address.to_string() != current_address => {
@@ -44,7 +54,7 @@ pub(crate) fn set(window: Option<WindowEventData>) {
                 app: w.class.clone(),
                 window_title: w.title,
                 icon_path: resolve_icon(&w.class),
-                focused_monitor: get_active_monitor_name(),
+                focused_monitor: focused_monitor(),
             };
             send_event(WmEvent::ActiveWindowChanged(new_info));
         }
@@ -57,15 +67,10 @@ pub(crate) fn set(window: Option<WindowEventData>) {
 
 /// Update the active window title.
 pub(crate) fn update_title(title_info: WindowTitleEventData) {
-    let mut info = match get_state().active_window.read() {
-        Ok(guard) => {
-            if title_info.address.to_string() != guard.address {
-                return;
-            }
-            guard.clone()
-        }
-        Err(_) => return,
-    };
+    let mut info = read(&get_state().active_window).clone();
+    if title_info.address.to_string() != info.address {
+        return;
+    }
 
     info.window_title = title_info.title;
     send_event(WmEvent::ActiveWindowChanged(info));
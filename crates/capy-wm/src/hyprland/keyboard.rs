@@ -0,0 +1,43 @@
+//! Keyboard layout and submap tracking for Hyprland.
+
+use crate::{WmError, WmEvent, WmResult, send_event};
+use hyprland::ctl::switch_xkb_layout::{self, SwitchXKBLayoutCmdTypes};
+use hyprland::data::Devices;
+use hyprland::event_listener::LayoutEvent;
+use hyprland::shared::HyprData;
+use log::debug;
+
+use super::query_error;
+
+/// Publish the active layout of the main keyboard.
+pub(crate) fn init() -> WmResult<()> {
+    let devices = Devices::get().map_err(query_error)?;
+    let keyboard = devices
+        .keyboards
+        .iter()
+        .find(|k| k.main)
+        .or_else(|| devices.keyboards.first());
+
+    match keyboard {
+        Some(keyboard) => send_event(WmEvent::KeyboardLayoutChanged {
+            keyboard: keyboard.name.clone(),
+            layout: keyboard.active_keymap.clone(),
+        }),
+        None => debug!("No keyboards found from Hyprland"),
+    }
+    Ok(())
+}
+
+/// Handle an `activelayout` event.
+pub(crate) fn set_layout(event: LayoutEvent) {
+    send_event(WmEvent::KeyboardLayoutChanged {
+        keyboard: event.keyboard_name,
+        layout: event.layout_name,
+    });
+}
+
+/// Switch every keyboard to its next layout.
+pub(crate) fn cycle() -> WmResult<()> {
+    switch_xkb_layout::call("all", SwitchXKBLayoutCmdTypes::Next)
+        .map_err(|e| WmError::Dispatch(e.to_string()))
+}
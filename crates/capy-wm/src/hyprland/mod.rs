@@ -3,46 +3,153 @@
 //! Implements the WindowBackend trait for the Hyprland compositor.
 
 mod active_window;
+mod keyboard;
+mod monitors;
+mod refresh;
+mod rules;
+mod windows;
 mod workspaces;
 
-use crate::{WindowBackend, WmEvent, send_event};
-use hyprland::data::Monitors;
-use hyprland::dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial};
+use crate::monitors::MonitorConfig;
+use crate::reconnect::{self, Backoff};
+use crate::types::{MonitorInfo, MonitorTransform};
+use crate::{WindowBackend, WmError, WmEvent, WmResult, send_event, set_urgent, update_window};
+use hyprland::data::{Client, Monitors};
+use hyprland::dispatch::{
+    Dispatch, DispatchType, WindowIdentifier, WorkspaceIdentifierWithSpecial,
+};
 use hyprland::event_listener::EventListener;
-use hyprland::shared::HyprData;
-use log::{debug, error, info};
-use std::collections::HashSet;
+use hyprland::shared::{Address, HyprData, HyprDataActiveOptional, HyprError};
+use log::{debug, info};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{LazyLock, RwLock};
 use std::thread;
 
-// Urgent window handling, stored here as it is not stored in the client data
-static URGENT_WINDOWS: LazyLock<RwLock<HashSet<String>>> =
-    LazyLock::new(|| RwLock::new(HashSet::new()));
+static RUNNING: AtomicBool = AtomicBool::new(false);
 
-pub(crate) fn is_urgent(address: &str) -> bool {
-    URGENT_WINDOWS
-        .read()
-        .map(|set| set.contains(address))
-        .unwrap_or(false)
+/// Run a dispatcher, mapping Hyprland errors to WmError.
+fn dispatch(dispatch: DispatchType) -> WmResult<()> {
+    Dispatch::call(dispatch).map_err(|e| WmError::Dispatch(e.to_string()))
 }
 
-fn add_urgent(address: String) {
-    if let Ok(mut set) = URGENT_WINDOWS.write() {
-        set.insert(address);
-    }
+/// Map a failed Hyprland query to WmError.
+pub(crate) fn query_error(error: HyprError) -> WmError {
+    WmError::Query(error.to_string())
 }
 
-fn clear_urgent(address: &str) {
-    if let Ok(mut set) = URGENT_WINDOWS.write() {
-        set.remove(address);
+/// Normalize an address to the `0x`-prefixed form Hyprland expects.
+fn hypr_address(address: &str) -> String {
+    if address.starts_with("0x") {
+        address.to_string()
+    } else {
+        format!("0x{}", address)
     }
 }
 
-/// Number of workspaces per monitor.
-pub const WORKSPACES_PER_MONITOR: i32 = 10;
+/// Window identifier for a window address.
+fn window(address: &str) -> WindowIdentifier<'static> {
+    WindowIdentifier::Address(Address::new(hypr_address(address)))
+}
 
-static RUNNING: AtomicBool = AtomicBool::new(false);
+/// Register the event handlers and block while Hyprland is sending events.
+fn listen() -> WmResult<()> {
+    let mut listener = EventListener::new();
+
+    // Monitor hotplug handlers
+    listener.add_monitor_added_handler(|event_data| {
+        debug!("Monitor added: {}", event_data.name);
+        thread::sleep(std::time::Duration::from_millis(200));
+        send_event(WmEvent::MonitorAdded(event_data.name));
+    });
+
+    listener.add_monitor_removed_handler(|name| {
+        debug!("Monitor removed: {}", name);
+        thread::sleep(std::time::Duration::from_millis(200));
+        send_event(WmEvent::MonitorRemoved(name));
+    });
+
+    // Workspace event handlers
+    listener.add_workspace_changed_handler(|ws| {
+        debug!("Workspace changed event: {:?}", ws);
+        refresh::schedule();
+    });
+
+    listener.add_changed_special_handler(|special| {
+        debug!("Special workspace changed: {:?}", special);
+        refresh::schedule();
+    });
+
+    listener.add_active_window_changed_handler(|win| {
+        debug!("Active window changed: {:?}", win);
+        if let Some(ref w) = win {
+            set_urgent(&w.address.to_string(), false);
+        }
+        active_window::set(win);
+        refresh::schedule();
+    });
+
+    listener.add_window_opened_handler(|win| {
+        debug!("Window opened: {:?}", win);
+        refresh::schedule();
+    });
+
+    listener.add_window_closed_handler(|addr| {
+        debug!("Window closed: {:?}", addr);
+        refresh::schedule();
+    });
+
+    listener.add_window_moved_handler(|win| {
+        debug!("Window moved: {:?}", win);
+        refresh::schedule();
+    });
+
+    listener.add_float_state_changed_handler(|state| {
+        debug!("Float state changed: {:?}", state);
+        send_event(WmEvent::FloatingChanged {
+            address: state.address.to_string(),
+            floating: state.floating,
+        });
+        refresh::schedule();
+    });
+
+    // The event does not say which window, FullscreenChanged is derived from the refreshed clients
+    listener.add_fullscreen_state_changed_handler(|fullscreen| {
+        debug!("Fullscreen state changed: {:?}", fullscreen);
+        refresh::schedule();
+    });
+
+    listener.add_urgent_state_changed_handler(|addr| {
+        debug!("Urgent state changed: {:?}", addr);
+        set_urgent(&addr.to_string(), true);
+        refresh::schedule();
+    });
+
+    listener.add_window_title_changed_handler(|addr| {
+        debug!("Window title changed: {:?}", addr.title);
+        let title = addr.title.clone();
+        update_window(&addr.address.to_string(), |w| w.title = title);
+        active_window::update_title(addr);
+    });
+
+    listener.add_layout_changed_handler(|layout| {
+        debug!("Keyboard layout changed: {:?}", layout);
+        keyboard::set_layout(layout);
+    });
+
+    // Hyprland sends an empty name when leaving a submap
+    listener.add_sub_map_changed_handler(|name| {
+        debug!("Submap changed: {:?}", name);
+        send_event(WmEvent::SubmapChanged(name));
+    });
+
+    listener.add_config_reloaded_handler(|| {
+        debug!("Config reloaded");
+        rules::invalidate();
+        refresh::schedule();
+    });
+
+    info!("Hyprland event listener active");
+    listener.start_listener().map_err(query_error)
+}
 
 /// Hyprland window manager backend.
 pub struct HyprlandBackend;
@@ -61,108 +168,159 @@ impl Default for HyprlandBackend {
 }
 
 impl WindowBackend for HyprlandBackend {
-    fn get_active_monitor(&self) -> String {
-        Monitors::get()
-            .ok()
-            .and_then(|monitors| monitors.iter().find(|m| m.focused).map(|m| m.name.clone()))
-            .unwrap_or_default()
+    fn get_active_monitor(&self) -> WmResult<String> {
+        active_window::get_active_monitor_name()
+    }
+
+    fn get_monitors(&self) -> WmResult<Vec<String>> {
+        let monitors = Monitors::get().map_err(query_error)?;
+        Ok(monitors.iter().map(|m| m.name.clone()).collect())
+    }
+
+    fn get_monitor_info(&self) -> WmResult<Vec<MonitorInfo>> {
+        monitors::info()
+    }
+
+    fn set_monitor_mode(
+        &self,
+        name: &str,
+        width: i32,
+        height: i32,
+        refresh_rate: f32,
+    ) -> WmResult<()> {
+        monitors::update(name, |config| {
+            config.width = width;
+            config.height = height;
+            config.refresh_rate = refresh_rate;
+        })
+    }
+
+    fn set_monitor_position(&self, name: &str, x: i32, y: i32) -> WmResult<()> {
+        monitors::update(name, |config| {
+            config.x = x;
+            config.y = y;
+        })
+    }
+
+    fn set_monitor_scale(&self, name: &str, scale: f32) -> WmResult<()> {
+        monitors::update(name, |config| config.scale = scale)
+    }
+
+    fn set_monitor_transform(&self, name: &str, transform: MonitorTransform) -> WmResult<()> {
+        monitors::update(name, |config| config.transform = transform)
+    }
+
+    fn set_monitor_enabled(&self, name: &str, enabled: bool) -> WmResult<()> {
+        monitors::set_enabled(name, enabled)
     }
 
-    fn get_monitors(&self) -> Vec<String> {
-        Monitors::get()
-            .map(|m| m.iter().map(|m| m.name.clone()).collect())
-            .unwrap_or_default()
+    fn apply_monitor_config(&self, config: &MonitorConfig) -> WmResult<()> {
+        monitors::apply(config)
     }
 
-    fn switch_workspace(&self, workspace_id: i32) {
-        let _ = Dispatch::call(DispatchType::Workspace(WorkspaceIdentifierWithSpecial::Id(
+    fn switch_workspace(&self, workspace_id: i32) -> WmResult<()> {
+        dispatch(DispatchType::Workspace(WorkspaceIdentifierWithSpecial::Id(
             workspace_id,
-        )));
+        )))
+    }
+
+    fn close_window(&self, address: &str) -> WmResult<()> {
+        dispatch(DispatchType::CloseWindow(window(address)))
+    }
+
+    fn focus_window(&self, address: &str) -> WmResult<()> {
+        dispatch(DispatchType::FocusWindow(window(address)))
+    }
+
+    fn move_window_to_workspace(&self, address: &str, workspace_id: i32) -> WmResult<()> {
+        dispatch(DispatchType::MoveToWorkspaceSilent(
+            WorkspaceIdentifierWithSpecial::Id(workspace_id),
+            Some(window(address)),
+        ))
+    }
+
+    fn toggle_floating(&self, address: &str) -> WmResult<()> {
+        dispatch(DispatchType::ToggleFloating(Some(window(address))))
+    }
+
+    fn toggle_fullscreen(&self, address: &str) -> WmResult<()> {
+        // fullscreen only acts on the focused window. Focus is not given back,
+        // another window on the workspace would be focused over the fullscreen one.
+        self.focus_window(address)?;
+        dispatch(DispatchType::Custom("fullscreen", "0"))
+    }
+
+    fn toggle_pin(&self, address: &str) -> WmResult<()> {
+        let target = format!("address:{}", hypr_address(address));
+        dispatch(DispatchType::Custom("pin", &target))
+    }
+
+    fn swap_windows(&self, address: &str, other: &str) -> WmResult<()> {
+        // swapwindow swaps the focused window with the target, focus is given back afterwards
+        let previous = Client::get_active().map_err(query_error)?;
+        self.focus_window(address)?;
+        let target = format!("address:{}", hypr_address(other));
+        let result = dispatch(DispatchType::Custom("swapwindow", &target));
+
+        if let Some(previous) = previous {
+            let previous = previous.address.to_string();
+            if hypr_address(&previous) != hypr_address(address) {
+                self.focus_window(&previous)?;
+            }
+        }
+        result
+    }
+
+    fn clear_urgency(&self, address: &str) -> WmResult<()> {
+        set_urgent(address, false);
+        refresh::schedule();
+        Ok(())
+    }
+
+    fn toggle_special_workspace(&self, name: &str) -> WmResult<()> {
+        // Hyprland names them "special:<name>", the plain "special" workspace has no name
+        let name = name.strip_prefix("special:").unwrap_or(name);
+        let name = (!name.is_empty() && name != "special").then(|| name.to_string());
+        dispatch(DispatchType::ToggleSpecialWorkspace(name))
+    }
+
+    fn cycle_keyboard_layout(&self) -> WmResult<()> {
+        keyboard::cycle()
     }
 
-    fn start_listener(&self) {
+    fn start_listener(&self) -> WmResult<()> {
         if RUNNING.swap(true, Ordering::SeqCst) {
             info!("Hyprland listener already running");
-            return;
+            return Ok(());
         }
 
         info!("Starting Hyprland event listener...");
 
-        // Initialize active window state
-        self.init_active_window();
-        workspaces::send_updates_to_all_monitors();
-
-        thread::spawn(move || {
-            let mut listener = EventListener::new();
-
-            // Monitor hotplug handlers
-            listener.add_monitor_added_handler(|event_data| {
-                debug!("Monitor added: {}", event_data.name);
-                thread::sleep(std::time::Duration::from_millis(200));
-                send_event(WmEvent::MonitorAdded(event_data.name));
-            });
-
-            listener.add_monitor_removed_handler(|name| {
-                debug!("Monitor removed: {}", name);
-                thread::sleep(std::time::Duration::from_millis(200));
-                send_event(WmEvent::MonitorRemoved(name));
-            });
-
-            // Workspace event handlers
-            listener.add_workspace_changed_handler(|ws| {
-                debug!("Workspace changed event: {:?}", ws);
-                workspaces::send_updates_to_all_monitors();
-            });
-
-            listener.add_active_window_changed_handler(|win| {
-                debug!("Active window changed: {:?}", win);
-                if let Some(ref w) = win {
-                    clear_urgent(&w.address.to_string());
-                }
-                active_window::set(win);
-                workspaces::send_updates_to_all_monitors();
-            });
-
-            listener.add_window_opened_handler(|win| {
-                debug!("Window opened: {:?}", win);
-                workspaces::send_updates_to_all_monitors();
-            });
-
-            listener.add_window_closed_handler(|addr| {
-                debug!("Window closed: {:?}", addr);
-                clear_urgent(&addr.to_string());
-                workspaces::send_updates_to_all_monitors();
-            });
-
-            listener.add_urgent_state_changed_handler(|addr| {
-                debug!("Urgent state changed: {:?}", addr);
-                add_urgent(addr.to_string());
-                workspaces::send_updates_to_all_monitors();
-            });
-
-            listener.add_window_title_changed_handler(|addr| {
-                debug!("Window title changed: {:?}", addr.title);
-                active_window::update_title(addr);
-            });
-
-            info!("Hyprland event listener active");
-            if let Err(e) = listener.start_listener() {
-                error!("Hyprland listener failed: {}", e);
-                RUNNING.store(false, Ordering::SeqCst);
-            }
+        // Initialize active window, workspace and keyboard state
+        if let Err(e) = self.trigger_refresh() {
+            RUNNING.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+
+        thread::spawn(|| {
+            reconnect::supervise("Hyprland", Backoff::default(), listen, || {
+                // The config may have changed while Hyprland was gone
+                rules::invalidate();
+                HyprlandBackend::new().trigger_refresh()
+            })
         });
+        Ok(())
     }
 
-    fn trigger_refresh(&self) {
+    fn trigger_refresh(&self) -> WmResult<()> {
         info!("Triggering Hyprland state refresh...");
-        workspaces::send_updates_to_all_monitors();
-        self.init_active_window();
+        refresh::run()?;
+        keyboard::init()?;
+        self.init_active_window()
     }
 
-    fn init_active_window(&self) {
-        active_window::init();
-        let active = active_window::get();
-        send_event(WmEvent::ActiveWindowChanged(active));
+    fn init_active_window(&self) -> WmResult<()> {
+        active_window::init()
     }
 }
 
@@ -171,17 +329,17 @@ pub use active_window::get as get_active_window;
 pub use workspaces::get_status as get_workspaces_status;
 
 /// Get the active monitor name.
-pub fn get_active_monitor() -> String {
+pub fn get_active_monitor() -> WmResult<String> {
     HyprlandBackend::new().get_active_monitor()
 }
 
 /// Trigger a refresh.
-pub fn trigger_refresh() {
+pub fn trigger_refresh() -> WmResult<()> {
     HyprlandBackend::new().trigger_refresh()
 }
 
 /// Start the listener.
-pub fn start_listener() {
+pub fn start_listener() -> WmResult<()> {
     HyprlandBackend::new().start_listener()
 }
 
@@ -189,10 +347,3 @@ pub fn start_listener() {
 pub fn set_icon_resolver(resolver: crate::IconResolver) {
     crate::set_icon_resolver(resolver);
 }
-
-pub fn set_event_callback<F>(callback: F)
-where
-    F: Fn(WmEvent) + Send + Sync + 'static,
-{
-    crate::set_event_callback(callback);
-}
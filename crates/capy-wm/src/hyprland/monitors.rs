@@ -0,0 +1,131 @@
+//! Monitor information and configuration for Hyprland.
+//!
+//! Changes are applied with `keyword monitor`, so they last until the config is reloaded.
+
+use crate::monitors::MonitorConfig;
+use crate::types::{MonitorInfo, MonitorTransform};
+use crate::{WmError, WmResult};
+use hyprland::data::{Monitor, Monitors, Transforms};
+use hyprland::keyword::Keyword;
+use hyprland::shared::{HyprData, HyprDataVec};
+
+use super::query_error;
+
+/// Details of all enabled monitors. Hyprland does not list disabled ones.
+pub(crate) fn info() -> WmResult<Vec<MonitorInfo>> {
+    let monitors = Monitors::get().map_err(query_error)?;
+    Ok(monitors.to_vec().into_iter().map(to_info).collect())
+}
+
+fn to_info(monitor: Monitor) -> MonitorInfo {
+    MonitorInfo {
+        name: monitor.name,
+        description: monitor.description,
+        make: monitor.make,
+        model: monitor.model,
+        width: monitor.width as i32,
+        height: monitor.height as i32,
+        refresh_rate: monitor.refresh_rate,
+        scale: monitor.scale,
+        transform: transform(monitor.transform),
+        x: monitor.x,
+        y: monitor.y,
+        focused: monitor.focused,
+        disabled: false,
+    }
+}
+
+fn transform(transform: Transforms) -> MonitorTransform {
+    match transform {
+        Transforms::Normal => MonitorTransform::Normal,
+        Transforms::Normal90 => MonitorTransform::Rotate90,
+        Transforms::Normal180 => MonitorTransform::Rotate180,
+        Transforms::Normal270 => MonitorTransform::Rotate270,
+        Transforms::Flipped => MonitorTransform::Flipped,
+        Transforms::Flipped90 => MonitorTransform::Flipped90,
+        Transforms::Flipped180 => MonitorTransform::Flipped180,
+        Transforms::Flipped270 => MonitorTransform::Flipped270,
+    }
+}
+
+/// Change part of a monitor's current configuration.
+pub(crate) fn update<F>(name: &str, change: F) -> WmResult<()>
+where
+    F: FnOnce(&mut MonitorConfig),
+{
+    let mut config = info()?
+        .iter()
+        .find(|m| m.name == name)
+        .map(MonitorConfig::from)
+        .ok_or_else(|| WmError::UnknownMonitor(name.to_string()))?;
+    change(&mut config);
+    apply(&config)
+}
+
+/// Enable a monitor with its preferred mode, or disable it.
+pub(crate) fn set_enabled(name: &str, enabled: bool) -> WmResult<()> {
+    // Enabled monitors keep their current configuration
+    if enabled && info()?.iter().any(|m| m.name == name) {
+        return Ok(());
+    }
+    let rule = if enabled {
+        format!("{},preferred,auto,1", name)
+    } else {
+        format!("{},disable", name)
+    };
+    keyword(rule)
+}
+
+/// Apply a full monitor configuration in one `keyword monitor` call.
+pub(crate) fn apply(config: &MonitorConfig) -> WmResult<()> {
+    keyword(rule(config))
+}
+
+fn keyword(rule: String) -> WmResult<()> {
+    Keyword::set("monitor", rule).map_err(|e| WmError::Dispatch(e.to_string()))
+}
+
+/// Monitor rule, e.g. `DP-1,2560x1440@144,0x0,1,transform,0`.
+fn rule(config: &MonitorConfig) -> String {
+    if !config.enabled {
+        return format!("{},disable", config.name);
+    }
+    format!(
+        "{},{}x{}@{},{}x{},{},transform,{}",
+        config.name,
+        config.width,
+        config.height,
+        config.refresh_rate,
+        config.x,
+        config.y,
+        config.scale,
+        config.transform.to_wayland()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule() {
+        let mut config = MonitorConfig {
+            name: "DP-1".to_string(),
+            enabled: true,
+            width: 2560,
+            height: 1440,
+            refresh_rate: 143.998,
+            x: 1920,
+            y: 0,
+            scale: 1.25,
+            transform: MonitorTransform::Rotate90,
+        };
+        assert_eq!(
+            rule(&config),
+            "DP-1,2560x1440@143.998,1920x0,1.25,transform,1"
+        );
+
+        config.enabled = false;
+        assert_eq!(rule(&config), "DP-1,disable");
+    }
+}
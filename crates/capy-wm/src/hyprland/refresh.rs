@@ -0,0 +1,197 @@
+//! Batched state refresh for Hyprland.
+//!
+//! A refresh fetches monitors, workspaces and clients once (3 IPC round trips,
+//! workspace rules are cached) and derives both the workspace status of every
+//! monitor and the window list from them. Listener events only schedule a
+//! refresh, so a burst of events costs a single round of IPC, and only monitors
+//! whose status changed get a `WorkspacesChanged` event.
+
+use crate::debounce::Debouncer;
+use crate::{WmResult, layout, sync_windows};
+use hyprland::data::{Client, Clients, Monitor, Monitors, Workspace, Workspaces};
+use hyprland::shared::{HyprData, HyprDataVec};
+use log::{debug, warn};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use super::{query_error, windows, workspaces};
+
+/// How long to wait for the rest of an event burst before refreshing.
+const DEBOUNCE: Duration = Duration::from_millis(30);
+
+static DEBOUNCER: LazyLock<Debouncer> = LazyLock::new(|| {
+    Debouncer::new(DEBOUNCE, || {
+        if let Err(e) = run() {
+            warn!("Failed to refresh Hyprland state: {}", e);
+        }
+    })
+});
+
+/// The queries a refresh is made of, replaced by a fake in tests.
+trait Fetch {
+    fn monitors(&self) -> WmResult<Vec<Monitor>>;
+    fn workspaces(&self) -> WmResult<Vec<Workspace>>;
+    fn clients(&self) -> WmResult<Vec<Client>>;
+}
+
+/// Queries the running Hyprland instance.
+struct Ipc;
+
+impl Fetch for Ipc {
+    fn monitors(&self) -> WmResult<Vec<Monitor>> {
+        Ok(Monitors::get().map_err(query_error)?.to_vec())
+    }
+
+    fn workspaces(&self) -> WmResult<Vec<Workspace>> {
+        Ok(Workspaces::get().map_err(query_error)?.to_vec())
+    }
+
+    fn clients(&self) -> WmResult<Vec<Client>> {
+        Ok(Clients::get().map_err(query_error)?.to_vec())
+    }
+}
+
+/// Schedule a refresh after the current burst of events.
+pub(crate) fn schedule() {
+    DEBOUNCER.trigger();
+}
+
+/// Refresh workspaces and windows right away.
+pub(crate) fn run() -> WmResult<()> {
+    run_with(&Ipc)
+}
+
+fn run_with(fetch: &impl Fetch) -> WmResult<()> {
+    let monitors = fetch.monitors()?;
+    if monitors.is_empty() {
+        debug!("No monitors found from Hyprland");
+        return Ok(());
+    }
+    let hypr_workspaces = fetch.workspaces()?;
+    let clients = fetch.clients()?;
+
+    let snapshot = workspaces::snapshot(&monitors, &hypr_workspaces, &clients);
+    let updated = layout::publish_changes(&snapshot);
+    debug!("Refreshed Hyprland state, {} monitor(s) changed", updated);
+
+    sync_windows(windows::build(clients, &hypr_workspaces));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WmEventKind;
+    use crate::{EventFilter, WmEvent, subscribe_with};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, PoisonError};
+
+    // Replies in the shape of `hyprctl -j monitors/workspaces/clients`
+    const MONITORS: &str = r#"[{
+        "id": 0, "name": "REFRESH-1", "description": "Fake monitor", "make": "Fake", "model": "M1",
+        "serial": "1", "width": 1920, "height": 1080, "refreshRate": 60.0, "x": 0, "y": 0,
+        "activeWorkspace": {"id": 3, "name": "3"}, "specialWorkspace": {"id": 0, "name": ""},
+        "reserved": [0, 0, 0, 0], "scale": 1.0, "transform": 0, "focused": true,
+        "dpmsStatus": true, "vrr": false, "solitary": "0", "activelyTearing": false,
+        "disabled": false, "currentFormat": "XRGB8888", "mirrorOf": "none", "availableModes": []
+    }]"#;
+
+    const WORKSPACES: &str = r#"[{
+        "id": 3, "name": "3", "monitor": "REFRESH-1", "monitorID": 0, "windows": 1,
+        "hasfullscreen": false, "lastwindow": "0x5a1", "lastwindowtitle": "~", "ispersistent": false
+    }]"#;
+
+    const CLIENTS: &str = r#"[{
+        "address": "0x5a1", "mapped": true, "hidden": false, "at": [0, 0], "size": [1920, 1080],
+        "workspace": {"id": 3, "name": "3"}, "floating": false, "pseudo": false, "monitor": 0,
+        "class": "foot", "title": "~", "initialClass": "foot", "initialTitle": "foot",
+        "pid": 4242, "xwayland": false, "pinned": false, "fullscreen": 0, "fullscreenClient": 0,
+        "grouped": [], "tags": [], "swallowing": "0x0", "focusHistoryID": 0, "inhibitingIdle": false
+    }]"#;
+
+    /// Serves canned replies and counts every query.
+    #[derive(Clone, Default)]
+    struct FakeHyprland {
+        monitors: Arc<AtomicUsize>,
+        workspaces: Arc<AtomicUsize>,
+        clients: Arc<AtomicUsize>,
+    }
+
+    impl FakeHyprland {
+        fn counts(&self) -> [usize; 3] {
+            [&self.monitors, &self.workspaces, &self.clients].map(|c| c.load(Ordering::SeqCst))
+        }
+    }
+
+    impl Fetch for FakeHyprland {
+        fn monitors(&self) -> WmResult<Vec<Monitor>> {
+            self.monitors.fetch_add(1, Ordering::SeqCst);
+            Ok(serde_json::from_str(MONITORS).unwrap())
+        }
+
+        fn workspaces(&self) -> WmResult<Vec<Workspace>> {
+            self.workspaces.fetch_add(1, Ordering::SeqCst);
+            Ok(serde_json::from_str(WORKSPACES).unwrap())
+        }
+
+        fn clients(&self) -> WmResult<Vec<Client>> {
+            self.clients.fetch_add(1, Ordering::SeqCst);
+            Ok(serde_json::from_str(CLIENTS).unwrap())
+        }
+    }
+
+    /// A burst of events costs one Monitors, Workspaces and Clients query each.
+    #[test]
+    fn test_one_fetch_per_event_burst() {
+        let _guard = crate::tests::SYNC_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let subscription = subscribe_with(
+            EventFilter::new().kinds([WmEventKind::WorkspacesChanged, WmEventKind::WindowOpened]),
+        );
+
+        let fake = FakeHyprland::default();
+        let fetch = fake.clone();
+        let debouncer = Debouncer::manual(move || run_with(&fetch).unwrap());
+
+        let events = || -> Vec<String> {
+            subscription
+                .try_iter()
+                .filter_map(|event| match event {
+                    WmEvent::WorkspacesChanged(s) if s.monitor_name == "REFRESH-1" => {
+                        Some("workspaces REFRESH-1".to_string())
+                    }
+                    WmEvent::WindowOpened(w) if w.address == "0x5a1" => {
+                        Some(format!("opened {} on {}", w.class, w.monitor))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // e.g. the open, focus, workspace and title events of a new window
+        for _ in 0..20 {
+            debouncer.trigger();
+        }
+        assert!(debouncer.flush());
+        assert_eq!(fake.counts(), [1, 1, 1]);
+        assert_eq!(
+            events(),
+            ["workspaces REFRESH-1", "opened foot on REFRESH-1"]
+        );
+
+        // Nothing changed, so the next burst fetches again but sends nothing
+        for _ in 0..5 {
+            debouncer.trigger();
+        }
+        assert!(debouncer.flush());
+        assert_eq!(fake.counts(), [2, 2, 2]);
+        assert!(events().is_empty());
+
+        // No event, no fetch
+        assert!(!debouncer.flush());
+        assert_eq!(fake.counts(), [2, 2, 2]);
+
+        sync_windows(Vec::new());
+    }
+}
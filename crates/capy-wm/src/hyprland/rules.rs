@@ -0,0 +1,108 @@
+//! Workspace rules (`workspace = 3, monitor:DP-1`) from Hyprland.
+//!
+//! hyprland-rs has no wrapper for `workspacerules`, so this asks the socket directly.
+
+use crate::layout::WorkspaceRule;
+use log::debug;
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+// Rules only change when the config is reloaded, so they are fetched once
+static CACHE: RwLock<Option<Vec<WorkspaceRule>>> = RwLock::new(None);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRule {
+    workspace_string: String,
+    #[serde(default)]
+    monitor: Option<String>,
+}
+
+/// Path of Hyprland's request socket.
+fn socket_path() -> Option<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".to_string());
+    Some(
+        PathBuf::from(runtime_dir)
+            .join("hypr")
+            .join(signature)
+            .join(".socket.sock"),
+    )
+}
+
+/// Get the workspace rules that bind a numeric workspace to a monitor.
+pub(crate) fn get() -> Vec<WorkspaceRule> {
+    if let Ok(cache) = CACHE.read()
+        && let Some(rules) = cache.as_ref()
+    {
+        return rules.clone();
+    }
+
+    let rules = fetch();
+    if let Ok(mut cache) = CACHE.write() {
+        *cache = Some(rules.clone());
+    }
+    rules
+}
+
+/// Drop the cached rules, e.g. after a config reload.
+pub(crate) fn invalidate() {
+    if let Ok(mut cache) = CACHE.write() {
+        *cache = None;
+    }
+}
+
+fn fetch() -> Vec<WorkspaceRule> {
+    let reply = socket_path().and_then(|path| {
+        let mut stream = UnixStream::connect(path).ok()?;
+        stream.write_all(b"j/workspacerules").ok()?;
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).ok()?;
+        Some(reply)
+    });
+
+    match reply {
+        Some(reply) => parse(&reply),
+        None => {
+            debug!("Failed to read Hyprland workspace rules");
+            Vec::new()
+        }
+    }
+}
+
+/// Parse a `workspacerules` reply, skipping named, special and monitor-less rules.
+fn parse(reply: &str) -> Vec<WorkspaceRule> {
+    let rules: Vec<RawRule> = serde_json::from_str(reply).unwrap_or_default();
+    rules
+        .into_iter()
+        .filter_map(|rule| {
+            Some(WorkspaceRule {
+                workspace_id: rule.workspace_string.parse().ok()?,
+                monitor: rule.monitor?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keeps_numeric_rules_with_monitor() {
+        let rules = parse(
+            r#"[
+                {"workspaceString": "3", "monitor": "DP-1", "default": true},
+                {"workspaceString": "special:scratch", "monitor": "DP-1"},
+                {"workspaceString": "4"}
+            ]"#,
+        );
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].workspace_id, 3);
+        assert_eq!(rules[0].monitor, "DP-1");
+    }
+}
@@ -0,0 +1,42 @@
+//! Window list tracking for Hyprland.
+
+use crate::resolve_icon;
+use crate::types::{WindowGeometry, WindowInfo};
+use hyprland::data::{Client, FullscreenMode, Workspace};
+
+/// Build the window list from already fetched clients.
+pub(crate) fn build(clients: Vec<Client>, workspaces: &[Workspace]) -> Vec<WindowInfo> {
+    clients
+        .into_iter()
+        .filter(|c| c.mapped)
+        .map(|c| to_info(c, workspaces))
+        .collect()
+}
+
+fn to_info(client: Client, workspaces: &[Workspace]) -> WindowInfo {
+    // Clients only carry a monitor ID, the workspace knows the monitor name
+    let monitor = workspaces
+        .iter()
+        .find(|ws| ws.id == client.workspace.id)
+        .map(|ws| ws.monitor.clone())
+        .unwrap_or_default();
+
+    WindowInfo {
+        address: client.address.to_string(),
+        icon_path: resolve_icon(&client.class),
+        class: client.class,
+        title: client.title,
+        initial_class: client.initial_class,
+        workspace_id: client.workspace.id,
+        monitor,
+        floating: client.floating,
+        fullscreen: !matches!(client.fullscreen, FullscreenMode::None),
+        pid: Some(client.pid),
+        geometry: WindowGeometry {
+            x: client.at.0 as i32,
+            y: client.at.1 as i32,
+            width: client.size.0 as i32,
+            height: client.size.1 as i32,
+        },
+    }
+}
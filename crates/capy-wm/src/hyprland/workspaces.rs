@@ -1,99 +1,65 @@
 //! Workspace management for Hyprland.
 
-use crate::types::{WorkspaceInfo, WorkspaceState, WorkspacesStatus};
-use crate::{WmEvent, resolve_icon, send_event};
-use hyprland::data::{Clients, Monitors, Workspaces};
+use crate::layout::{self, ClientSnapshot, LayoutSnapshot, MonitorSnapshot, WorkspaceSnapshot};
+use crate::types::WorkspacesStatus;
+use crate::{WmResult, get_state};
+use hyprland::data::{Client, Clients, Monitor, Monitors, Workspace, Workspaces};
 use hyprland::shared::{HyprData, HyprDataVec};
-use log::debug;
 
-use super::WORKSPACES_PER_MONITOR;
+use super::{query_error, rules};
 
-/// Get workspace status for a specific monitor.
-pub fn get_status(monitor_name: &str) -> WorkspacesStatus {
-    let all_monitors: Vec<_> = Monitors::get().map(|m| m.to_vec()).unwrap_or_default();
-    let all_workspaces: Vec<_> = Workspaces::get().map(|ws| ws.to_vec()).unwrap_or_default();
-    let all_clients: Vec<_> = Clients::get().map(|c| c.to_vec()).unwrap_or_default();
-
-    let monitor_idx = all_monitors
+/// Build a layout snapshot from already fetched Hyprland data and the cached workspace rules.
+pub(crate) fn snapshot(
+    monitors: &[Monitor],
+    workspaces: &[Workspace],
+    clients: &[Client],
+) -> LayoutSnapshot {
+    let monitors = monitors
         .iter()
-        .position(|m| m.name == monitor_name)
-        .unwrap_or(0) as i32;
-
-    // workspace range for this monitor
-    let ws_start = monitor_idx * WORKSPACES_PER_MONITOR + 1;
-    let ws_end = ws_start + WORKSPACES_PER_MONITOR - 1;
-
-    // active workspace for this monitor
-    let this_monitor = all_monitors.iter().find(|m| m.name == monitor_name);
-    let visible_ws_on_monitor = this_monitor.map(|m| m.active_workspace.id);
-
-    let is_focused_monitor = this_monitor.map(|m| m.focused).unwrap_or(false);
-
-    let mut workspaces = Vec::with_capacity(WORKSPACES_PER_MONITOR as usize);
-
-    for ws_id in ws_start..=ws_end {
-        let relative_id = ws_id - ws_start + 1;
-
-        let ws_data = all_workspaces.iter().find(|ws| ws.id == ws_id);
-        let has_windows = ws_data.map(|ws| ws.windows > 0).unwrap_or(false);
-
-        let clients_on_ws: Vec<_> = all_clients
-            .iter()
-            .filter(|c| c.workspace.id == ws_id)
-            .collect();
-        let has_urgent = clients_on_ws
-            .iter()
-            .any(|c| super::is_urgent(&c.address.to_string()));
-
-        let (app_class, icon_path) = if let Some(client) = clients_on_ws.first() {
-            let class = client.class.clone();
-            let icon = resolve_icon(&class);
-            (Some(class), icon)
-        } else {
-            (None, None)
-        };
-
-        let state = if Some(ws_id) == visible_ws_on_monitor {
-            if is_focused_monitor {
-                WorkspaceState::Active
-            } else {
-                WorkspaceState::Visible
-            }
-        } else if has_urgent {
-            WorkspaceState::Attention
-        } else {
-            WorkspaceState::Empty
-        };
-
-        workspaces.push(WorkspaceInfo {
-            id: relative_id,
-            absolute_id: ws_id,
-            state,
-            icon_path,
-            occupied: has_windows,
-            app_class,
-        });
-    }
-
-    WorkspacesStatus {
-        monitor_name: monitor_name.to_string(),
+        .map(|m| MonitorSnapshot {
+            name: m.name.clone(),
+            active_workspace: Some(m.active_workspace.id),
+            // Hyprland reports id 0 when no special workspace is shown
+            active_special: (m.special_workspace.id != 0).then_some(m.special_workspace.id),
+            focused: m.focused,
+        })
+        .collect();
+
+    let workspaces = workspaces
+        .iter()
+        .map(|ws| WorkspaceSnapshot {
+            id: ws.id,
+            name: ws.name.clone(),
+            monitor: ws.monitor.clone(),
+            urgent: false,
+        })
+        .collect();
+
+    let clients = clients
+        .iter()
+        .map(|c| ClientSnapshot {
+            urgent: get_state().is_urgent(&c.address.to_string()),
+            class: c.class.clone(),
+            workspace_id: c.workspace.id,
+        })
+        .collect();
+
+    LayoutSnapshot {
+        monitors,
         workspaces,
+        clients,
+        rules: rules::get(),
     }
 }
 
-/// Send workspace updates to all monitors.
-pub(crate) fn send_updates_to_all_monitors() {
-    let monitor_names: Vec<String> = Monitors::get()
-        .map(|m| m.iter().map(|m| m.name.clone()).collect())
-        .unwrap_or_default();
-
-    if monitor_names.is_empty() {
-        debug!("No monitors found from Hyprland");
-        return;
-    }
-
-    for monitor_name in monitor_names {
-        let status = get_status(&monitor_name);
-        send_event(WmEvent::WorkspacesChanged(status));
-    }
+/// Get workspace status for a specific monitor.
+pub fn get_status(monitor_name: &str) -> WmResult<WorkspacesStatus> {
+    let monitors = Monitors::get().map_err(query_error)?.to_vec();
+    let workspaces = Workspaces::get().map_err(query_error)?.to_vec();
+    let clients = Clients::get().map_err(query_error)?.to_vec();
+
+    Ok(layout::build_status(
+        &snapshot(&monitors, &workspaces, &clients),
+        monitor_name,
+    ))
 }
@@ -0,0 +1,405 @@
+//! Workspace layout strategies.
+//!
+//! Decides which workspaces each monitor shows in the taskbar. Backends fill a
+//! [`LayoutSnapshot`] from their IPC replies and [`build_status`] turns it into
+//! a [`WorkspacesStatus`] using the active [`WorkspaceLayout`].
+
+use crate::types::{WorkspaceInfo, WorkspaceKind, WorkspaceState, WorkspacesStatus};
+use crate::{read, resolve_icon, send_workspaces_if_changed, write};
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+
+/// Default number of workspaces per monitor for [`WorkspaceLayout::FixedPerMonitor`].
+pub const DEFAULT_WORKSPACES_PER_MONITOR: i32 = 10;
+
+static LAYOUT: RwLock<WorkspaceLayout> = RwLock::new(WorkspaceLayout::FixedPerMonitor(
+    DEFAULT_WORKSPACES_PER_MONITOR,
+));
+
+/// Strategy for which workspaces are shown on each monitor.
+/// Used by the Hyprland and Sway backends, niri and ext-workspace report their
+/// dynamic workspaces as they are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorkspaceLayout {
+    /// Monitor N owns IDs `N*n+1..=N*n+n`, shown as 1..=n.
+    FixedPerMonitor(i32),
+    /// Every monitor shows the shared pool 1..=n.
+    SharedGlobal(i32),
+    /// Only workspaces that currently exist on the monitor.
+    OnlyExisting,
+    /// Workspaces 1..=n on every monitor, plus any other workspace existing on it.
+    PersistentPlusExisting(i32),
+    /// Workspaces bound to the monitor by WM rules, plus any other workspace existing on it.
+    Rules,
+}
+
+impl Default for WorkspaceLayout {
+    fn default() -> Self {
+        WorkspaceLayout::FixedPerMonitor(DEFAULT_WORKSPACES_PER_MONITOR)
+    }
+}
+
+/// Set the workspace layout. Call `trigger_refresh` on the backend afterwards to apply it.
+pub fn set_workspace_layout(layout: WorkspaceLayout) {
+    *write(&LAYOUT) = layout;
+}
+
+/// Get the current workspace layout.
+pub fn get_workspace_layout() -> WorkspaceLayout {
+    read(&LAYOUT).clone()
+}
+
+/// A monitor as seen by the layout.
+#[derive(Clone, Debug, Default)]
+pub struct MonitorSnapshot {
+    pub name: String,
+    /// Workspace currently shown on this monitor.
+    pub active_workspace: Option<i32>,
+    /// Special workspace currently shown on top, if any.
+    pub active_special: Option<i32>,
+    pub focused: bool,
+}
+
+/// An existing workspace.
+#[derive(Clone, Debug, Default)]
+pub struct WorkspaceSnapshot {
+    pub id: i32,
+    pub name: String,
+    /// Monitor the workspace currently lives on.
+    pub monitor: String,
+    pub urgent: bool,
+}
+
+/// A window, only what the taskbar needs.
+#[derive(Clone, Debug, Default)]
+pub struct ClientSnapshot {
+    pub class: String,
+    pub workspace_id: i32,
+    pub urgent: bool,
+}
+
+/// A WM rule binding a workspace to a monitor.
+#[derive(Clone, Debug, Default)]
+pub struct WorkspaceRule {
+    pub workspace_id: i32,
+    pub monitor: String,
+}
+
+/// Everything a layout needs to build the workspace status, in WM order.
+#[derive(Clone, Debug, Default)]
+pub struct LayoutSnapshot {
+    pub monitors: Vec<MonitorSnapshot>,
+    pub workspaces: Vec<WorkspaceSnapshot>,
+    pub clients: Vec<ClientSnapshot>,
+    pub rules: Vec<WorkspaceRule>,
+}
+
+impl WorkspaceSnapshot {
+    fn kind(&self) -> WorkspaceKind {
+        WorkspaceKind::from_id_and_name(self.id, &self.name)
+    }
+}
+
+impl WorkspaceLayout {
+    /// `(relative, absolute)` IDs of the numbered workspaces shown on a monitor, in display order.
+    fn workspace_ids(&self, snapshot: &LayoutSnapshot, monitor_name: &str) -> Vec<(i32, i32)> {
+        let existing = snapshot
+            .workspaces
+            .iter()
+            .filter(|ws| ws.monitor == monitor_name && ws.kind() != WorkspaceKind::Special)
+            .map(|ws| ws.id);
+
+        let ids: BTreeSet<i32> = match self {
+            WorkspaceLayout::FixedPerMonitor(per_monitor) => {
+                let monitor_idx = snapshot
+                    .monitors
+                    .iter()
+                    .position(|m| m.name == monitor_name)
+                    .unwrap_or(0) as i32;
+                let start = monitor_idx * per_monitor + 1;
+                return (start..start + per_monitor)
+                    .map(|id| (id - start + 1, id))
+                    .collect();
+            }
+            WorkspaceLayout::SharedGlobal(count) => (1..=*count).collect(),
+            WorkspaceLayout::OnlyExisting => existing.collect(),
+            WorkspaceLayout::PersistentPlusExisting(count) => {
+                (1..=*count).chain(existing).collect()
+            }
+            WorkspaceLayout::Rules => snapshot
+                .rules
+                .iter()
+                .filter(|rule| rule.monitor == monitor_name)
+                .map(|rule| rule.workspace_id)
+                .chain(existing)
+                .collect(),
+        };
+
+        // Named workspaces with negative IDs are appended by build_status_with
+        ids.into_iter()
+            .filter(|id| *id > 0)
+            .map(|id| (id, id))
+            .collect()
+    }
+}
+
+/// Build the workspace status for one monitor with the current layout.
+pub fn build_status(snapshot: &LayoutSnapshot, monitor_name: &str) -> WorkspacesStatus {
+    build_status_with(&get_workspace_layout(), snapshot, monitor_name)
+}
+
+/// Build the status of every monitor in the snapshot and send the ones that changed.
+/// Returns the number of monitors that were updated.
+pub(crate) fn publish_changes(snapshot: &LayoutSnapshot) -> usize {
+    let layout = get_workspace_layout();
+    snapshot
+        .monitors
+        .iter()
+        .filter(|m| send_workspaces_if_changed(build_status_with(&layout, snapshot, &m.name)))
+        .count()
+}
+
+/// Build the workspace status for one monitor with a specific layout.
+/// Named workspaces with negative IDs follow the numbered ones, special workspaces are listed separately.
+pub fn build_status_with(
+    layout: &WorkspaceLayout,
+    snapshot: &LayoutSnapshot,
+    monitor_name: &str,
+) -> WorkspacesStatus {
+    let monitor = snapshot.monitors.iter().find(|m| m.name == monitor_name);
+
+    let named = snapshot
+        .workspaces
+        .iter()
+        .filter(|ws| ws.monitor == monitor_name && ws.id < 0)
+        .filter(|ws| ws.kind() == WorkspaceKind::Named)
+        .map(|ws| (ws.id, ws.id));
+
+    let workspaces = layout
+        .workspace_ids(snapshot, monitor_name)
+        .into_iter()
+        .chain(named)
+        .map(|(relative_id, ws_id)| {
+            workspace_info(snapshot, monitor, relative_id, ws_id, |m| {
+                m.active_workspace
+            })
+        })
+        .collect();
+
+    let special = snapshot
+        .workspaces
+        .iter()
+        .filter(|ws| ws.monitor == monitor_name && ws.kind() == WorkspaceKind::Special)
+        .map(|ws| workspace_info(snapshot, monitor, ws.id, ws.id, |m| m.active_special))
+        .collect();
+
+    WorkspacesStatus {
+        monitor_name: monitor_name.to_string(),
+        workspaces,
+        special,
+    }
+}
+
+/// Build the info for one workspace. `shown` picks the workspace the monitor currently displays.
+fn workspace_info(
+    snapshot: &LayoutSnapshot,
+    monitor: Option<&MonitorSnapshot>,
+    relative_id: i32,
+    ws_id: i32,
+    shown: fn(&MonitorSnapshot) -> Option<i32>,
+) -> WorkspaceInfo {
+    let existing = snapshot.workspaces.iter().find(|ws| ws.id == ws_id);
+    let clients: Vec<_> = snapshot
+        .clients
+        .iter()
+        .filter(|c| c.workspace_id == ws_id)
+        .collect();
+    let has_urgent = clients.iter().any(|c| c.urgent) || existing.is_some_and(|ws| ws.urgent);
+
+    let (app_class, icon_path) = match clients.first() {
+        Some(client) => (Some(client.class.clone()), resolve_icon(&client.class)),
+        None => (None, None),
+    };
+
+    let state = match monitor {
+        Some(m) if shown(m) == Some(ws_id) => {
+            if m.focused {
+                WorkspaceState::Active
+            } else {
+                WorkspaceState::Visible
+            }
+        }
+        _ if has_urgent => WorkspaceState::Attention,
+        _ => WorkspaceState::Empty,
+    };
+
+    let name = existing
+        .map(|ws| ws.name.clone())
+        .unwrap_or_else(|| ws_id.to_string());
+
+    WorkspaceInfo {
+        id: relative_id,
+        absolute_id: ws_id,
+        kind: WorkspaceKind::from_id_and_name(ws_id, &name),
+        name,
+        state,
+        icon_path,
+        occupied: !clients.is_empty(),
+        app_class,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, active_workspace: i32, focused: bool) -> MonitorSnapshot {
+        MonitorSnapshot {
+            name: name.to_string(),
+            active_workspace: Some(active_workspace),
+            active_special: None,
+            focused,
+        }
+    }
+
+    fn workspace(id: i32, name: &str, monitor: &str) -> WorkspaceSnapshot {
+        WorkspaceSnapshot {
+            id,
+            name: name.to_string(),
+            monitor: monitor.to_string(),
+            urgent: false,
+        }
+    }
+
+    fn client(class: &str, workspace_id: i32, urgent: bool) -> ClientSnapshot {
+        ClientSnapshot {
+            class: class.to_string(),
+            workspace_id,
+            urgent,
+        }
+    }
+
+    /// Two monitors on Hyprland's default shared pool: DP-1 shows 1, HDMI-A-1 shows 2,
+    /// workspace 5 lives on HDMI-A-1 and 7 is bound to DP-1 by a rule.
+    fn snapshot() -> LayoutSnapshot {
+        LayoutSnapshot {
+            monitors: vec![monitor("DP-1", 1, true), monitor("HDMI-A-1", 2, false)],
+            workspaces: vec![
+                workspace(1, "1", "DP-1"),
+                workspace(2, "2", "HDMI-A-1"),
+                workspace(5, "5", "HDMI-A-1"),
+                workspace(-98, "special:scratch", "DP-1"),
+            ],
+            clients: vec![
+                client("foot", 1, false),
+                client("firefox", 2, false),
+                client("vesktop", 5, true),
+                client("spotify", -98, false),
+            ],
+            rules: vec![WorkspaceRule {
+                workspace_id: 7,
+                monitor: "DP-1".to_string(),
+            }],
+        }
+    }
+
+    fn ids(status: &WorkspacesStatus) -> Vec<(i32, i32)> {
+        status
+            .workspaces
+            .iter()
+            .map(|ws| (ws.id, ws.absolute_id))
+            .collect()
+    }
+
+    #[test]
+    fn test_fixed_per_monitor() {
+        let layout = WorkspaceLayout::FixedPerMonitor(3);
+        let snapshot = snapshot();
+
+        let dp = build_status_with(&layout, &snapshot, "DP-1");
+        assert_eq!(ids(&dp), vec![(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(dp.workspaces[0].state, WorkspaceState::Active);
+        assert_eq!(dp.workspaces[0].app_class.as_deref(), Some("foot"));
+
+        let hdmi = build_status_with(&layout, &snapshot, "HDMI-A-1");
+        assert_eq!(ids(&hdmi), vec![(1, 4), (2, 5), (3, 6)]);
+        assert_eq!(hdmi.workspaces[1].state, WorkspaceState::Attention);
+    }
+
+    #[test]
+    fn test_shared_global() {
+        let layout = WorkspaceLayout::SharedGlobal(3);
+        let snapshot = snapshot();
+
+        let hdmi = build_status_with(&layout, &snapshot, "HDMI-A-1");
+        assert_eq!(ids(&hdmi), vec![(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(hdmi.workspaces[0].state, WorkspaceState::Empty);
+        assert!(hdmi.workspaces[0].occupied);
+        assert_eq!(hdmi.workspaces[1].state, WorkspaceState::Visible);
+    }
+
+    #[test]
+    fn test_only_existing_skips_special() {
+        let snapshot = snapshot();
+
+        let dp = build_status_with(&WorkspaceLayout::OnlyExisting, &snapshot, "DP-1");
+        assert_eq!(ids(&dp), vec![(1, 1)]);
+
+        let hdmi = build_status_with(&WorkspaceLayout::OnlyExisting, &snapshot, "HDMI-A-1");
+        assert_eq!(ids(&hdmi), vec![(2, 2), (5, 5)]);
+    }
+
+    #[test]
+    fn test_persistent_plus_existing() {
+        let layout = WorkspaceLayout::PersistentPlusExisting(3);
+        let hdmi = build_status_with(&layout, &snapshot(), "HDMI-A-1");
+
+        assert_eq!(ids(&hdmi), vec![(1, 1), (2, 2), (3, 3), (5, 5)]);
+        assert_eq!(hdmi.workspaces[3].app_class.as_deref(), Some("vesktop"));
+    }
+
+    #[test]
+    fn test_named_and_special_workspaces() {
+        let mut snapshot = snapshot();
+        snapshot.workspaces.push(workspace(-1337, "music", "DP-1"));
+        snapshot.monitors[0].active_special = Some(-98);
+
+        let dp = build_status_with(&WorkspaceLayout::OnlyExisting, &snapshot, "DP-1");
+        assert_eq!(ids(&dp), vec![(1, 1), (-1337, -1337)]);
+        assert_eq!(dp.workspaces[1].kind, WorkspaceKind::Named);
+        assert_eq!(dp.workspaces[1].name, "music");
+
+        assert_eq!(dp.special.len(), 1);
+        assert_eq!(dp.special[0].kind, WorkspaceKind::Special);
+        assert_eq!(dp.special[0].app_class.as_deref(), Some("spotify"));
+        assert_eq!(dp.active_special(), Some("special:scratch"));
+
+        let hdmi = build_status_with(&WorkspaceLayout::OnlyExisting, &snapshot, "HDMI-A-1");
+        assert!(hdmi.special.is_empty());
+        assert_eq!(hdmi.active_special(), None);
+    }
+
+    #[test]
+    fn test_workspace_kind_from_name() {
+        let kind = WorkspaceKind::from_id_and_name;
+        assert_eq!(kind(3, "3"), WorkspaceKind::Normal);
+        assert_eq!(kind(3, ""), WorkspaceKind::Normal);
+        assert_eq!(kind(-98, "special"), WorkspaceKind::Special);
+        assert_eq!(kind(-98, "special:scratch"), WorkspaceKind::Special);
+        // Ordinary names that merely start with "special"
+        assert_eq!(kind(-1337, "specialty"), WorkspaceKind::Named);
+        assert_eq!(kind(4, "special-ops"), WorkspaceKind::Named);
+    }
+
+    #[test]
+    fn test_rules_bind_workspaces() {
+        let snapshot = snapshot();
+
+        let dp = build_status_with(&WorkspaceLayout::Rules, &snapshot, "DP-1");
+        assert_eq!(ids(&dp), vec![(1, 1), (7, 7)]);
+        assert!(!dp.workspaces[1].occupied);
+
+        let hdmi = build_status_with(&WorkspaceLayout::Rules, &snapshot, "HDMI-A-1");
+        assert_eq!(ids(&hdmi), vec![(2, 2), (5, 5)]);
+    }
+}
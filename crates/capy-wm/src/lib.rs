@@ -1,32 +1,54 @@
 //! capy-wm: Window manager abstraction for tiling window managers
 //!
 //! Provides a unified interface for interacting with tiling window managers.
-//! Currently supports Hyprland with architecture ready for Sway, Niri, etc.
+//! Currently supports Hyprland, Sway and Niri, with a generic Wayland protocol fallback.
 
+#[cfg(any(feature = "hyprland", test))]
+mod debounce;
+pub mod error;
+pub mod events;
+pub mod layout;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod monitors;
+#[cfg(any(feature = "hyprland", test))]
+mod reconnect;
+#[cfg(feature = "serde")]
+pub mod replay;
 pub mod types;
 pub mod window_backend;
 
 #[cfg(feature = "hyprland")]
 pub mod hyprland;
 
+#[cfg(feature = "sway")]
+pub mod sway;
+
+#[cfg(feature = "niri")]
+pub mod niri;
+
+#[cfg(feature = "wayland")]
+pub mod wayland;
+
+pub use error::*;
+pub use events::{EventFilter, Subscription, subscribe, subscribe_with};
+pub use layout::{WorkspaceLayout, get_workspace_layout, set_workspace_layout};
+pub use monitors::{MonitorConfig, MonitorProfile};
+#[cfg(feature = "serde")]
+pub use replay::{Recorder, Recording};
 pub use types::*;
 pub use window_backend::*;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::{Arc, OnceLock, RwLock};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 /// Icon resolver callback type.
 /// The WM backend uses this to resolve app class names to icon paths.
 pub type IconResolver = Box<dyn Fn(&str) -> Option<PathBuf> + Send + Sync>;
 
-/// Event callback type.
-/// Called when WM events occur (workspace changes, active window changes, etc.).
-pub type EventCallback = Box<dyn Fn(WmEvent) + Send + Sync>;
-
 // Global callbacks shared across the backend
 static ICON_RESOLVER: OnceLock<Arc<RwLock<Option<IconResolver>>>> = OnceLock::new();
-static EVENT_CALLBACK: OnceLock<Arc<RwLock<Option<EventCallback>>>> = OnceLock::new();
 
 // Global state cache
 static STATE: OnceLock<WmState> = OnceLock::new();
@@ -37,57 +59,69 @@ pub struct WmState {
     pub active_window: RwLock<ActiveWindowInfo>,
     /// Workspace status keyed by monitor name.
     pub workspaces: RwLock<HashMap<String, WorkspacesStatus>>,
+    /// All open windows keyed by address.
+    pub windows: RwLock<HashMap<String, WindowInfo>>,
+    /// Addresses of windows demanding attention.
+    pub urgent: RwLock<HashSet<String>>,
+    /// Addresses of focused windows, most recently focused first.
+    pub focus_history: RwLock<Vec<String>>,
+    /// Monitors currently showing a fullscreen window.
+    pub fullscreen: RwLock<HashSet<String>>,
+    /// Active layout keyed by keyboard name.
+    pub keyboard_layouts: RwLock<HashMap<String, String>>,
+    /// Layout most recently activated on any keyboard.
+    pub keyboard_layout: RwLock<String>,
+    /// Current submap, empty for the default one.
+    pub submap: RwLock<String>,
 }
 
 /// Returns the active window info.
 pub fn get_active_window() -> ActiveWindowInfo {
-    let state = get_state();
-    match state.active_window.read() {
-        Ok(w) => w.clone(),
-        Err(err) => {
-            eprintln!(
-                "capy-wm: failed to acquire read lock on active_window in get_active_window: {:?}",
-                err
-            );
-            ActiveWindowInfo::default()
-        }
-    }
+    read(&get_state().active_window).clone()
 }
+
+/// Returns the workspace status of a monitor, empty if the monitor is unknown.
 pub fn get_workspaces_status(monitor_name: &str) -> WorkspacesStatus {
-    let state = get_state();
-    match state.workspaces.read() {
-        Ok(map) => map
-            .get(monitor_name)
-            .cloned()
-            .unwrap_or_else(|| WorkspacesStatus {
-                monitor_name: monitor_name.to_string(),
-                workspaces: Vec::new(),
-            }),
-        Err(err) => {
-            eprintln!(
-                "capy-wm: failed to acquire read lock on workspaces in get_workspaces_status for monitor '{}': {:?}",
-                monitor_name, err
-            );
-            WorkspacesStatus {
-                monitor_name: monitor_name.to_string(),
-                workspaces: Vec::new(),
-            }
-        }
-    }
+    read(&get_state().workspaces)
+        .get(monitor_name)
+        .cloned()
+        .unwrap_or_else(|| WorkspacesStatus {
+            monitor_name: monitor_name.to_string(),
+            ..Default::default()
+        })
 }
+
+/// Returns the workspace status of every known monitor, sorted by monitor name.
+pub fn get_all_workspaces_status() -> Vec<WorkspacesStatus> {
+    let mut statuses: Vec<WorkspacesStatus> =
+        read(&get_state().workspaces).values().cloned().collect();
+    statuses.sort_by(|a, b| a.monitor_name.cmp(&b.monitor_name));
+    statuses
+}
+
 /// Get the active monitor name.
 pub fn get_active_monitor() -> String {
-    let state = get_state();
-    match state.active_window.read() {
-        Ok(w) => w.focused_monitor.clone(),
-        Err(err) => {
-            eprintln!(
-                "capy-wm: failed to acquire read lock on active_window in get_active_monitor: {:?}",
-                err
-            );
-            String::new()
-        }
-    }
+    read(&get_state().active_window).focused_monitor.clone()
+}
+
+/// Returns the layout most recently activated on any keyboard, empty if unknown.
+pub fn get_keyboard_layout() -> String {
+    read(&get_state().keyboard_layout).clone()
+}
+
+/// Returns the current submap, empty for the default one.
+pub fn get_submap() -> String {
+    read(&get_state().submap).clone()
+}
+
+/// Returns all open windows, ordered by workspace.
+pub fn get_windows() -> Vec<WindowInfo> {
+    get_state().find_windows(|_| true)
+}
+
+/// Returns all open windows, most recently focused first.
+pub fn get_windows_by_recency() -> Vec<WindowInfo> {
+    get_state().windows_by_recency()
 }
 
 impl WmState {
@@ -95,8 +129,94 @@ impl WmState {
         Self {
             active_window: RwLock::new(ActiveWindowInfo::default()),
             workspaces: RwLock::new(HashMap::new()),
+            windows: RwLock::new(HashMap::new()),
+            urgent: RwLock::new(HashSet::new()),
+            focus_history: RwLock::new(Vec::new()),
+            fullscreen: RwLock::new(HashSet::new()),
+            keyboard_layouts: RwLock::new(HashMap::new()),
+            keyboard_layout: RwLock::new(String::new()),
+            submap: RwLock::new(String::new()),
         }
     }
+
+    /// Get a window by address.
+    pub fn window(&self, address: &str) -> Option<WindowInfo> {
+        self.find_window(|w| w.address == address)
+    }
+
+    /// Windows on a workspace by absolute ID.
+    pub fn windows_on_workspace(&self, workspace_id: i32) -> Vec<WindowInfo> {
+        self.find_windows(|w| w.workspace_id == workspace_id)
+    }
+
+    /// Windows with the given class, compared case-insensitively.
+    pub fn windows_by_class(&self, class: &str) -> Vec<WindowInfo> {
+        self.find_windows(|w| w.class.eq_ignore_ascii_case(class))
+    }
+
+    /// Whether the window with the given address demands attention.
+    pub fn is_urgent(&self, address: &str) -> bool {
+        read(&self.urgent).contains(address)
+    }
+
+    /// Addresses of all windows demanding attention, sorted.
+    pub fn urgent_windows(&self) -> Vec<String> {
+        let mut addresses: Vec<String> = read(&self.urgent).iter().cloned().collect();
+        addresses.sort();
+        addresses
+    }
+
+    /// Whether the monitor is showing a fullscreen window.
+    pub fn is_fullscreen(&self, monitor: &str) -> bool {
+        read(&self.fullscreen).contains(monitor)
+    }
+
+    /// Addresses of focused windows that are still open, most recently focused first.
+    pub fn focus_history(&self) -> Vec<String> {
+        read(&self.focus_history).clone()
+    }
+
+    /// All windows, most recently focused first.
+    /// Windows that were never focused follow in workspace order.
+    pub fn windows_by_recency(&self) -> Vec<WindowInfo> {
+        let mut windows = self.find_windows(|_| true);
+        let history = read(&self.focus_history);
+        let rank = |w: &WindowInfo| {
+            history
+                .iter()
+                .position(|a| *a == w.address)
+                .unwrap_or(usize::MAX)
+        };
+        // Stable sort keeps the workspace order among windows without history
+        windows.sort_by_key(rank);
+        windows
+    }
+
+    /// First window matching the predicate.
+    pub fn find_window<P>(&self, predicate: P) -> Option<WindowInfo>
+    where
+        P: Fn(&WindowInfo) -> bool,
+    {
+        self.find_windows(predicate).into_iter().next()
+    }
+
+    /// All windows matching the predicate, ordered by workspace and address.
+    pub fn find_windows<P>(&self, predicate: P) -> Vec<WindowInfo>
+    where
+        P: Fn(&WindowInfo) -> bool,
+    {
+        let mut windows: Vec<WindowInfo> = read(&self.windows)
+            .values()
+            .filter(|w| predicate(w))
+            .cloned()
+            .collect();
+        windows.sort_by(|a, b| {
+            a.workspace_id
+                .cmp(&b.workspace_id)
+                .then_with(|| a.address.cmp(&b.address))
+        });
+        windows
+    }
 }
 
 /// Get the global state instance.
@@ -104,111 +224,488 @@ pub fn get_state() -> &'static WmState {
     STATE.get_or_init(WmState::new)
 }
 
-fn get_icon_resolver_store() -> Arc<RwLock<Option<IconResolver>>> {
-    ICON_RESOLVER
-        .get_or_init(|| Arc::new(RwLock::new(None)))
-        .clone()
+/// Lock for reading. Writers only ever replace whole entries, so the data behind a
+/// poisoned lock is still consistent and is used as is.
+pub(crate) fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Lock for writing, see [`read`] for why poisoning is ignored.
+pub(crate) fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
 }
 
-fn get_event_callback_store() -> Arc<RwLock<Option<EventCallback>>> {
-    EVENT_CALLBACK
+fn get_icon_resolver_store() -> Arc<RwLock<Option<IconResolver>>> {
+    ICON_RESOLVER
         .get_or_init(|| Arc::new(RwLock::new(None)))
         .clone()
 }
 
 /// Set the icon resolver callback.
 pub fn set_icon_resolver(resolver: IconResolver) {
-    match get_icon_resolver_store().write() {
-        Ok(mut guard) => {
-            *guard = Some(resolver);
+    *write(&get_icon_resolver_store()) = Some(resolver);
+}
+
+/// Resolve an icon path using the configured resolver.
+pub fn resolve_icon(class: &str) -> Option<PathBuf> {
+    read(&get_icon_resolver_store())
+        .as_ref()
+        .and_then(|r| r(class))
+}
+
+/// Replace the window list with a full snapshot from the backend.
+/// Emits WindowOpened/WindowClosed/WindowMoved/FloatingChanged for the differences,
+/// other changes (title, ...) are applied silently.
+pub(crate) fn sync_windows(windows: Vec<WindowInfo>) {
+    let mut events = Vec::new();
+    {
+        let mut map = write(&get_state().windows);
+        let seen: HashSet<String> = windows.iter().map(|w| w.address.clone()).collect();
+
+        for address in map.keys().filter(|a| !seen.contains(*a)) {
+            events.push(WmEvent::WindowClosed(address.clone()));
         }
-        Err(err) => {
-            eprintln!(
-                "capy-wm: failed to acquire write lock on icon resolver: {:?}",
-                err
-            );
+        map.retain(|address, _| seen.contains(address));
+
+        for window in windows {
+            match map.get(&window.address) {
+                None => events.push(WmEvent::WindowOpened(window.clone())),
+                Some(old) => {
+                    // A window can move and change floating state between two snapshots
+                    if old.workspace_id != window.workspace_id || old.monitor != window.monitor {
+                        events.push(WmEvent::WindowMoved(window.clone()));
+                    }
+                    if old.floating != window.floating {
+                        events.push(WmEvent::FloatingChanged {
+                            address: window.address.clone(),
+                            floating: window.floating,
+                        });
+                    }
+                }
+            }
+            map.insert(window.address.clone(), window);
         }
     }
+
+    for event in events {
+        send_event(event);
+    }
+    // Fullscreen flags are applied silently above
+    update_fullscreen();
 }
 
-/// Set the event callback.
-pub fn set_event_callback<F>(callback: F)
-where
-    F: Fn(WmEvent) + Send + Sync + 'static,
-{
-    match get_event_callback_store().write() {
-        Ok(mut guard) => {
-            *guard = Some(Box::new(callback));
-        }
-        Err(err) => {
-            eprintln!(
-                "capy-wm: failed to acquire write lock on event callback: {:?}",
-                err
-            );
-        }
+/// Recompute which monitors show a fullscreen window, sending FullscreenChanged for changes.
+/// A fullscreen window only counts while its workspace is shown on its monitor.
+fn update_fullscreen() {
+    let state = get_state();
+    let current: HashSet<String> = {
+        let workspaces = read(&state.workspaces);
+        let shown = |window: &WindowInfo| {
+            workspaces.get(&window.monitor).is_none_or(|status| {
+                status.workspaces.iter().chain(&status.special).any(|ws| {
+                    ws.absolute_id == window.workspace_id
+                        && matches!(ws.state, WorkspaceState::Active | WorkspaceState::Visible)
+                })
+            })
+        };
+        read(&state.windows)
+            .values()
+            .filter(|w| w.fullscreen && shown(w))
+            .map(|w| w.monitor.clone())
+            .collect()
+    };
+
+    let previous = read(&state.fullscreen).clone();
+    for monitor in previous.symmetric_difference(&current) {
+        send_event(WmEvent::FullscreenChanged {
+            monitor: monitor.clone(),
+            fullscreen: current.contains(monitor),
+        });
     }
 }
 
-/// Resolve an icon path using the configured resolver.
-pub fn resolve_icon(class: &str) -> Option<PathBuf> {
-    match get_icon_resolver_store().read() {
-        Ok(guard) => guard.as_ref().and_then(|r| r(class)),
-        Err(err) => {
-            eprintln!(
-                "capy-wm: failed to acquire read lock on icon resolver in resolve_icon: {:?}",
-                err
-            );
-            None
-        }
+/// Send a WorkspacesChanged event unless the cached status for the monitor is identical.
+/// Returns whether the event was sent.
+pub(crate) fn send_workspaces_if_changed(status: WorkspacesStatus) -> bool {
+    let unchanged = read(&get_state().workspaces).get(&status.monitor_name) == Some(&status);
+    if !unchanged {
+        send_event(WmEvent::WorkspacesChanged(status));
+    }
+    !unchanged
+}
+
+/// Mark a window as urgent or not, sending UrgencyChanged if that changes anything.
+pub(crate) fn set_urgent(address: &str, urgent: bool) {
+    if get_state().is_urgent(address) != urgent {
+        send_event(WmEvent::UrgencyChanged {
+            address: address.to_string(),
+            urgent,
+        });
+    }
+}
+
+/// Update a cached window in place without emitting an event.
+pub(crate) fn update_window<F>(address: &str, update: F)
+where
+    F: FnOnce(&mut WindowInfo),
+{
+    if let Some(window) = write(&get_state().windows).get_mut(address) {
+        update(window);
     }
 }
 
-/// Updates internal cache and sends a WM event to the configured callback.
+/// Updates internal cache and sends a WM event to all subscribers.
 pub fn send_event(event: WmEvent) {
     let state = get_state();
-    let mut update_successful = true;
+    let mut special_toggled = None;
 
     match &event {
-        WmEvent::WorkspacesChanged(status) => match state.workspaces.write() {
-            Ok(mut guard) => {
-                guard.insert(status.monitor_name.clone(), status.clone());
+        WmEvent::WorkspacesChanged(status) => {
+            let previous =
+                write(&state.workspaces).insert(status.monitor_name.clone(), status.clone());
+            let was_shown = previous.as_ref().and_then(|p| p.active_special());
+            if was_shown != status.active_special() {
+                special_toggled = Some(WmEvent::SpecialWorkspaceToggled {
+                    monitor: status.monitor_name.clone(),
+                    name: status.active_special().map(str::to_string),
+                });
             }
-            Err(err) => {
-                eprintln!(
-                    "capy-wm: failed to acquire write lock on workspaces in send_event: {:?}",
-                    err
-                );
-                update_successful = false;
+        }
+        WmEvent::ActiveWindowChanged(info) => {
+            *write(&state.active_window) = info.clone();
+            if !info.address.is_empty() {
+                let mut history = write(&state.focus_history);
+                history.retain(|a| *a != info.address);
+                history.insert(0, info.address.clone());
+            }
+        }
+        WmEvent::WindowOpened(info) | WmEvent::WindowMoved(info) => {
+            write(&state.windows).insert(info.address.clone(), info.clone());
+        }
+        WmEvent::WindowClosed(address) => {
+            write(&state.windows).remove(address);
+            // A closed window no longer needs attention
+            write(&state.urgent).remove(address);
+            write(&state.focus_history).retain(|a| a != address);
+        }
+        WmEvent::UrgencyChanged { address, urgent } => {
+            let mut set = write(&state.urgent);
+            if *urgent {
+                set.insert(address.clone());
+            } else {
+                set.remove(address);
             }
-        },
-        WmEvent::ActiveWindowChanged(info) => match state.active_window.write() {
-            Ok(mut guard) => {
-                *guard = info.clone();
+        }
+        WmEvent::FullscreenChanged {
+            monitor,
+            fullscreen,
+        } => {
+            let mut set = write(&state.fullscreen);
+            if *fullscreen {
+                set.insert(monitor.clone());
+            } else {
+                set.remove(monitor);
             }
-            Err(err) => {
-                eprintln!(
-                    "capy-wm: failed to acquire write lock on active_window in send_event: {:?}",
-                    err
-                );
-                update_successful = false;
+        }
+        WmEvent::FloatingChanged { address, floating } => {
+            if let Some(window) = write(&state.windows).get_mut(address) {
+                window.floating = *floating;
             }
-        },
+        }
+        WmEvent::KeyboardLayoutChanged { keyboard, layout } => {
+            write(&state.keyboard_layouts).insert(keyboard.clone(), layout.clone());
+            *write(&state.keyboard_layout) = layout.clone();
+        }
+        WmEvent::SubmapChanged(name) => {
+            *write(&state.submap) = name.clone();
+        }
         _ => {}
     }
 
-    if update_successful {
-        match get_event_callback_store().read() {
-            Ok(guard) => {
-                if let Some(ref callback) = *guard {
-                    callback(event);
+    events::publish(&event);
+
+    // Window and workspace changes can reveal or hide a fullscreen window
+    if matches!(
+        event.kind(),
+        WmEventKind::WorkspacesChanged
+            | WmEventKind::WindowOpened
+            | WmEventKind::WindowMoved
+            | WmEventKind::WindowClosed
+    ) {
+        update_fullscreen();
+    }
+
+    if let Some(toggled) = special_toggled {
+        send_event(toggled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // sync_windows replaces the whole window list, tests that keep windows in it must not overlap
+    pub(crate) static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+    fn window(address: &str, class: &str, workspace_id: i32) -> WindowInfo {
+        WindowInfo {
+            address: address.to_string(),
+            class: class.to_string(),
+            workspace_id,
+            monitor: "DP-1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sync_windows_emits_differences() {
+        let _guard = SYNC_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let subscription = subscribe_with(EventFilter::new().kinds([
+            WmEventKind::WindowOpened,
+            WmEventKind::WindowMoved,
+            WmEventKind::WindowClosed,
+        ]));
+
+        sync_windows(vec![
+            window("sync-a", "foot", 1),
+            window("sync-b", "Firefox", 2),
+        ]);
+
+        let mut renamed = window("sync-a", "foot", 1);
+        renamed.title = "htop".to_string();
+        sync_windows(vec![renamed, window("sync-b", "Firefox", 3)]);
+        sync_windows(vec![window("sync-b", "Firefox", 3)]);
+
+        let events: Vec<String> = subscription
+            .try_iter()
+            .filter_map(|event| match event {
+                WmEvent::WindowOpened(w) if w.address.starts_with("sync-") => {
+                    Some(format!("opened {}", w.address))
+                }
+                WmEvent::WindowMoved(w) if w.address.starts_with("sync-") => {
+                    Some(format!("moved {}", w.address))
+                }
+                WmEvent::WindowClosed(a) if a.starts_with("sync-") => Some(format!("closed {}", a)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "opened sync-a",
+                "opened sync-b",
+                "moved sync-b",
+                "closed sync-a"
+            ]
+        );
+
+        let state = get_state();
+        assert_eq!(state.windows_on_workspace(3).len(), 1);
+        assert_eq!(
+            state.windows_by_class("firefox")[0].address,
+            "sync-b".to_string()
+        );
+        assert!(state.window("sync-a").is_none());
+        assert!(state.find_window(|w| w.workspace_id == 1).is_none());
+    }
+
+    #[test]
+    fn test_sync_windows_reports_move_and_floating_together() {
+        let _guard = SYNC_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let subscription = subscribe_with(
+            EventFilter::new().kinds([WmEventKind::WindowMoved, WmEventKind::FloatingChanged]),
+        );
+
+        sync_windows(vec![window("sync-float", "mpv", 1)]);
+        let mut moved = window("sync-float", "mpv", 2);
+        moved.floating = true;
+        sync_windows(vec![moved]);
+
+        let events: Vec<String> = subscription
+            .try_iter()
+            .filter_map(|event| match event {
+                WmEvent::WindowMoved(w) if w.address == "sync-float" => {
+                    Some(format!("moved to {}", w.workspace_id))
                 }
-            }
-            Err(err) => {
-                eprintln!(
-                    "capy-wm: failed to acquire read lock on event callback store in send_event: {:?}",
-                    err
-                );
-            }
+                WmEvent::FloatingChanged { address, floating } if address == "sync-float" => {
+                    Some(format!("floating {}", floating))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(events, ["moved to 2", "floating true"]);
+
+        sync_windows(Vec::new());
+    }
+
+    #[test]
+    fn test_special_workspace_toggle_is_emitted() {
+        let subscription = subscribe_with(
+            EventFilter::new()
+                .monitor("TOGGLE-1")
+                .kinds([WmEventKind::SpecialWorkspaceToggled]),
+        );
+        let status = |state| WorkspacesStatus {
+            monitor_name: "TOGGLE-1".to_string(),
+            special: vec![WorkspaceInfo {
+                name: "special:term".to_string(),
+                kind: WorkspaceKind::Special,
+                state,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        send_event(WmEvent::WorkspacesChanged(status(WorkspaceState::Active)));
+        send_event(WmEvent::WorkspacesChanged(status(WorkspaceState::Active)));
+        send_event(WmEvent::WorkspacesChanged(status(WorkspaceState::Empty)));
+
+        let shown: Vec<_> = subscription
+            .try_iter()
+            .map(|event| match event {
+                WmEvent::SpecialWorkspaceToggled { name, .. } => name,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(shown, [Some("special:term".to_string()), None]);
+    }
+
+    #[test]
+    fn test_urgency_is_tracked_centrally() {
+        let subscription = subscribe_with(EventFilter::new().kinds([WmEventKind::UrgencyChanged]));
+
+        set_urgent("urgent-a", true);
+        set_urgent("urgent-a", true);
+        set_urgent("urgent-b", true);
+        assert!(get_state().is_urgent("urgent-a"));
+
+        set_urgent("urgent-a", false);
+        send_event(WmEvent::WindowClosed("urgent-b".to_string()));
+        assert!(
+            !get_state()
+                .urgent_windows()
+                .iter()
+                .any(|a| a.starts_with("urgent-"))
+        );
+
+        let changes: Vec<_> = subscription
+            .try_iter()
+            .filter_map(|event| match event {
+                WmEvent::UrgencyChanged { address, urgent } if address.starts_with("urgent-") => {
+                    Some((address, urgent))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("urgent-a".to_string(), true),
+                ("urgent-b".to_string(), true),
+                ("urgent-a".to_string(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fullscreen_follows_shown_workspace() {
+        let _guard = SYNC_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let subscription = subscribe_with(
+            EventFilter::new()
+                .monitor("FS-1")
+                .kinds([WmEventKind::FullscreenChanged]),
+        );
+        let status = |active: i32| WorkspacesStatus {
+            monitor_name: "FS-1".to_string(),
+            workspaces: (1..=2)
+                .map(|id| WorkspaceInfo {
+                    id,
+                    absolute_id: id,
+                    state: if id == active {
+                        WorkspaceState::Active
+                    } else {
+                        WorkspaceState::Empty
+                    },
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut video = window("fs-video", "mpv", 1);
+        video.monitor = "FS-1".to_string();
+
+        send_event(WmEvent::WorkspacesChanged(status(1)));
+        sync_windows(vec![video.clone()]);
+        video.fullscreen = true;
+        sync_windows(vec![video.clone()]);
+        assert!(get_state().is_fullscreen("FS-1"));
+
+        // Switching away hides the fullscreen window, closing it on the other workspace changes nothing
+        send_event(WmEvent::WorkspacesChanged(status(2)));
+        send_event(WmEvent::WindowClosed("fs-video".to_string()));
+        assert!(!get_state().is_fullscreen("FS-1"));
+
+        let changes: Vec<_> = subscription
+            .try_iter()
+            .map(|event| match event {
+                WmEvent::FullscreenChanged { fullscreen, .. } => fullscreen,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(changes, [true, false]);
+    }
+
+    #[test]
+    fn test_focus_history_is_most_recent_first() {
+        let _guard = SYNC_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let focus = |address: &str| {
+            send_event(WmEvent::ActiveWindowChanged(ActiveWindowInfo {
+                address: address.to_string(),
+                ..Default::default()
+            }))
+        };
+        for address in ["mru-a", "mru-b", "mru-c"] {
+            send_event(WmEvent::WindowOpened(window(address, "foot", 40)));
         }
+        send_event(WmEvent::WindowOpened(window("mru-d", "foot", 40)));
+
+        focus("mru-a");
+        focus("mru-b");
+        focus("mru-c");
+        focus("mru-a");
+        send_event(WmEvent::WindowClosed("mru-c".to_string()));
+
+        let history: Vec<String> = get_state()
+            .focus_history()
+            .into_iter()
+            .filter(|a| a.starts_with("mru-"))
+            .collect();
+        assert_eq!(history, ["mru-a", "mru-b"]);
+
+        // Never focused windows come last
+        let order: Vec<String> = get_windows_by_recency()
+            .into_iter()
+            .map(|w| w.address)
+            .filter(|a| a.starts_with("mru-"))
+            .collect();
+        assert_eq!(order, ["mru-a", "mru-b", "mru-d"]);
+    }
+
+    #[test]
+    fn test_all_workspaces_sorted_by_monitor() {
+        for monitor in ["all-b", "all-a"] {
+            send_event(WmEvent::WorkspacesChanged(WorkspacesStatus {
+                monitor_name: monitor.to_string(),
+                ..Default::default()
+            }));
+        }
+
+        let monitors: Vec<String> = get_all_workspaces_status()
+            .into_iter()
+            .map(|status| status.monitor_name)
+            .filter(|name| name.starts_with("all-"))
+            .collect();
+        assert_eq!(monitors, ["all-a", "all-b"]);
     }
 }
@@ -0,0 +1,429 @@
+//! Scriptable backend for deterministic tests.
+//!
+//! [`MockBackend`] keeps its monitors in memory and feeds everything else through
+//! [`send_event`], so the shared [`WmState`](crate::WmState) and all subscribers see
+//! exactly what they would see with a live compositor. Actions requested through
+//! [`WindowBackend`] are recorded and can be asserted on with [`MockBackend::calls`].
+
+use crate::replay::Recording;
+use crate::types::{
+    ActiveWindowInfo, MonitorInfo, MonitorTransform, WindowInfo, WmEvent, WorkspacesStatus,
+};
+use crate::{WindowBackend, WmError, WmResult, get_state, send_event, send_workspaces_if_changed};
+use std::sync::{Mutex, PoisonError};
+
+/// In-memory backend driven by the test instead of a compositor.
+#[derive(Default)]
+pub struct MockBackend {
+    monitors: Mutex<Vec<MonitorInfo>>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a backend with the monitors of a recording and replay its events.
+    pub fn from_recording(recording: &Recording) -> Self {
+        let backend = Self::new();
+        *backend.lock_monitors() = recording.monitors.clone();
+        recording.replay();
+        backend
+    }
+
+    /// Plug in a monitor, replacing one with the same name.
+    pub fn add_monitor(&self, monitor: MonitorInfo) {
+        let name = monitor.name.clone();
+        {
+            let mut monitors = self.lock_monitors();
+            monitors.retain(|m| m.name != name);
+            monitors.push(monitor);
+        }
+        send_event(WmEvent::MonitorAdded(name));
+    }
+
+    /// Unplug a monitor.
+    pub fn remove_monitor(&self, name: &str) {
+        self.lock_monitors().retain(|m| m.name != name);
+        send_event(WmEvent::MonitorRemoved(name.to_string()));
+    }
+
+    /// Set the workspace status of a monitor. Nothing is sent if it did not change.
+    pub fn set_workspaces(&self, status: WorkspacesStatus) {
+        send_workspaces_if_changed(status);
+    }
+
+    /// Open a window, or move it if a window with the same address exists.
+    pub fn open_window(&self, window: WindowInfo) {
+        if get_state().window(&window.address).is_some() {
+            send_event(WmEvent::WindowMoved(window));
+        } else {
+            send_event(WmEvent::WindowOpened(window));
+        }
+    }
+
+    /// Close a window as if the client went away.
+    pub fn remove_window(&self, address: &str) {
+        send_event(WmEvent::WindowClosed(address.to_string()));
+    }
+
+    /// Change the active window.
+    pub fn set_active_window(&self, info: ActiveWindowInfo) {
+        send_event(WmEvent::ActiveWindowChanged(info));
+    }
+
+    /// Send an arbitrary event.
+    pub fn emit(&self, event: WmEvent) {
+        send_event(event);
+    }
+
+    /// Actions requested through [`WindowBackend`] so far, e.g. `"switch_workspace 3"`.
+    pub fn calls(&self) -> Vec<String> {
+        self.lock_calls().clone()
+    }
+
+    /// Forget the recorded actions.
+    pub fn clear_calls(&self) {
+        self.lock_calls().clear();
+    }
+
+    fn record(&self, call: String) {
+        self.lock_calls().push(call);
+    }
+
+    fn lock_monitors(&self) -> std::sync::MutexGuard<'_, Vec<MonitorInfo>> {
+        self.monitors.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_calls(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.calls.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record a monitor change and apply it to the stored monitor.
+    fn update_monitor<F>(&self, name: &str, call: String, update: F) -> WmResult<()>
+    where
+        F: FnOnce(&mut MonitorInfo),
+    {
+        self.record(call);
+        let mut monitors = self.lock_monitors();
+        let monitor = monitors
+            .iter_mut()
+            .find(|m| m.name == name)
+            .ok_or_else(|| WmError::UnknownMonitor(name.to_string()))?;
+        update(monitor);
+        Ok(())
+    }
+}
+
+impl WindowBackend for MockBackend {
+    fn get_active_monitor(&self) -> WmResult<String> {
+        let monitors = self.lock_monitors();
+        monitors
+            .iter()
+            .find(|m| m.focused)
+            .or_else(|| monitors.first())
+            .map(|m| m.name.clone())
+            .ok_or_else(|| WmError::Query("no monitors".to_string()))
+    }
+
+    fn get_monitors(&self) -> WmResult<Vec<String>> {
+        Ok(self
+            .lock_monitors()
+            .iter()
+            .map(|m| m.name.clone())
+            .collect())
+    }
+
+    fn get_monitor_info(&self) -> WmResult<Vec<MonitorInfo>> {
+        Ok(self.lock_monitors().clone())
+    }
+
+    fn set_monitor_mode(
+        &self,
+        name: &str,
+        width: i32,
+        height: i32,
+        refresh_rate: f32,
+    ) -> WmResult<()> {
+        let call = format!(
+            "set_monitor_mode {} {}x{}@{}",
+            name, width, height, refresh_rate
+        );
+        self.update_monitor(name, call, |m| {
+            m.width = width;
+            m.height = height;
+            m.refresh_rate = refresh_rate;
+        })
+    }
+
+    fn set_monitor_position(&self, name: &str, x: i32, y: i32) -> WmResult<()> {
+        let call = format!("set_monitor_position {} {},{}", name, x, y);
+        self.update_monitor(name, call, |m| {
+            m.x = x;
+            m.y = y;
+        })
+    }
+
+    fn set_monitor_scale(&self, name: &str, scale: f32) -> WmResult<()> {
+        let call = format!("set_monitor_scale {} {}", name, scale);
+        self.update_monitor(name, call, |m| m.scale = scale)
+    }
+
+    fn set_monitor_transform(&self, name: &str, transform: MonitorTransform) -> WmResult<()> {
+        let call = format!("set_monitor_transform {} {}", name, transform.name());
+        self.update_monitor(name, call, |m| m.transform = transform)
+    }
+
+    fn set_monitor_enabled(&self, name: &str, enabled: bool) -> WmResult<()> {
+        let call = format!("set_monitor_enabled {} {}", name, enabled);
+        self.update_monitor(name, call, |m| m.disabled = !enabled)
+    }
+
+    fn switch_workspace(&self, workspace_id: i32) -> WmResult<()> {
+        self.record(format!("switch_workspace {}", workspace_id));
+        Ok(())
+    }
+
+    fn close_window(&self, address: &str) -> WmResult<()> {
+        self.record(format!("close_window {}", address));
+        self.remove_window(address);
+        Ok(())
+    }
+
+    fn focus_window(&self, address: &str) -> WmResult<()> {
+        self.record(format!("focus_window {}", address));
+        if let Some(window) = get_state().window(address) {
+            self.set_active_window(ActiveWindowInfo {
+                address: window.address,
+                app: window.class,
+                window_title: window.title,
+                icon_path: window.icon_path,
+                focused_monitor: window.monitor,
+            });
+        }
+        Ok(())
+    }
+
+    fn move_window_to_workspace(&self, address: &str, workspace_id: i32) -> WmResult<()> {
+        self.record(format!(
+            "move_window_to_workspace {} {}",
+            address, workspace_id
+        ));
+        if let Some(mut window) = get_state().window(address) {
+            window.workspace_id = workspace_id;
+            send_event(WmEvent::WindowMoved(window));
+        }
+        Ok(())
+    }
+
+    fn toggle_floating(&self, address: &str) -> WmResult<()> {
+        self.record(format!("toggle_floating {}", address));
+        Ok(())
+    }
+
+    fn toggle_fullscreen(&self, address: &str) -> WmResult<()> {
+        self.record(format!("toggle_fullscreen {}", address));
+        Ok(())
+    }
+
+    fn toggle_pin(&self, address: &str) -> WmResult<()> {
+        self.record(format!("toggle_pin {}", address));
+        Ok(())
+    }
+
+    fn swap_windows(&self, address: &str, other: &str) -> WmResult<()> {
+        self.record(format!("swap_windows {} {}", address, other));
+        Ok(())
+    }
+
+    fn toggle_special_workspace(&self, name: &str) -> WmResult<()> {
+        self.record(format!("toggle_special_workspace {}", name));
+        Ok(())
+    }
+
+    fn cycle_keyboard_layout(&self) -> WmResult<()> {
+        self.record("cycle_keyboard_layout".to_string());
+        Ok(())
+    }
+
+    fn start_listener(&self) -> WmResult<()> {
+        self.record("start_listener".to_string());
+        Ok(())
+    }
+
+    fn trigger_refresh(&self) -> WmResult<()> {
+        self.record("trigger_refresh".to_string());
+        Ok(())
+    }
+
+    fn init_active_window(&self) -> WmResult<()> {
+        self.record("init_active_window".to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::Recorder;
+    use crate::types::{WmEventKind, WorkspaceInfo, WorkspaceState};
+    use crate::{EventFilter, get_active_window, get_workspaces_status, subscribe_with};
+
+    fn monitor(name: &str) -> MonitorInfo {
+        MonitorInfo {
+            name: name.to_string(),
+            width: 1920,
+            height: 1080,
+            scale: 1.0,
+            ..Default::default()
+        }
+    }
+
+    fn window(address: &str, monitor: &str, workspace_id: i32) -> WindowInfo {
+        WindowInfo {
+            address: address.to_string(),
+            class: "foot".to_string(),
+            title: "~".to_string(),
+            workspace_id,
+            monitor: monitor.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn status(monitor: &str, active: i32) -> WorkspacesStatus {
+        WorkspacesStatus {
+            monitor_name: monitor.to_string(),
+            workspaces: (1..=3)
+                .map(|id| WorkspaceInfo {
+                    id,
+                    absolute_id: id,
+                    name: id.to_string(),
+                    state: if id == active {
+                        WorkspaceState::Active
+                    } else {
+                        WorkspaceState::Empty
+                    },
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scripted_state_reaches_getters() {
+        let backend = MockBackend::new();
+        let subscription = subscribe_with(EventFilter::new().monitor("MOCK-1").kinds([
+            WmEventKind::MonitorAdded,
+            WmEventKind::WorkspacesChanged,
+            WmEventKind::WindowOpened,
+        ]));
+
+        backend.add_monitor(monitor("MOCK-1"));
+        backend.set_workspaces(status("MOCK-1", 1));
+        backend.set_workspaces(status("MOCK-1", 1));
+        backend.set_workspaces(status("MOCK-1", 2));
+        backend.open_window(window("mock-a", "MOCK-1", 2));
+        backend.focus_window("mock-a").unwrap();
+
+        let kinds: Vec<_> = subscription.try_iter().map(|e| e.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                WmEventKind::MonitorAdded,
+                WmEventKind::WorkspacesChanged,
+                WmEventKind::WorkspacesChanged,
+                WmEventKind::WindowOpened,
+            ]
+        );
+
+        let workspaces = get_workspaces_status("MOCK-1");
+        assert_eq!(workspaces.workspaces[1].state, WorkspaceState::Active);
+        assert_eq!(backend.get_active_monitor().unwrap(), "MOCK-1");
+
+        let active = get_active_window();
+        assert_eq!(active.address, "mock-a");
+        assert_eq!(active.focused_monitor, "MOCK-1");
+    }
+
+    #[test]
+    fn test_actions_are_recorded() {
+        let backend = MockBackend::new();
+        backend.add_monitor(monitor("MOCK-2"));
+        backend.open_window(window("mock-b", "MOCK-2", 1));
+
+        backend.switch_workspace(4).unwrap();
+        backend.move_window_to_workspace("mock-b", 4).unwrap();
+        assert_eq!(get_state().window("mock-b").unwrap().workspace_id, 4);
+
+        backend.close_window("mock-b").unwrap();
+        assert!(get_state().window("mock-b").is_none());
+
+        backend.set_monitor_scale("MOCK-2", 1.5).unwrap();
+        assert_eq!(backend.get_monitor_info().unwrap()[0].scale, 1.5);
+        assert!(matches!(
+            backend.set_monitor_scale("MOCK-404", 2.0),
+            Err(WmError::UnknownMonitor(_))
+        ));
+
+        assert_eq!(
+            backend.calls(),
+            [
+                "switch_workspace 4",
+                "move_window_to_workspace mock-b 4",
+                "close_window mock-b",
+                "set_monitor_scale MOCK-2 1.5",
+                "set_monitor_scale MOCK-404 2",
+            ]
+        );
+    }
+
+    // Monitor events only, global events from other tests pass any monitor filter
+    fn monitor_events(monitor: &str) -> EventFilter {
+        EventFilter::new().monitor(monitor).kinds([
+            WmEventKind::MonitorAdded,
+            WmEventKind::MonitorRemoved,
+            WmEventKind::WorkspacesChanged,
+        ])
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let live = MockBackend::new();
+        live.add_monitor(monitor("MOCK-3"));
+
+        let recorder = Recorder::start_with(&live, monitor_events("MOCK-3")).unwrap();
+        live.set_workspaces(status("MOCK-3", 1));
+        live.set_workspaces(status("MOCK-3", 3));
+        live.remove_monitor("MOCK-3");
+
+        let recording = recorder.stop();
+        assert_eq!(recording.monitors.len(), 1);
+        assert_eq!(recording.events.len(), 3);
+
+        let path =
+            std::env::temp_dir().join(format!("capy-wm-recording-{}.json", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let subscription = subscribe_with(monitor_events("MOCK-3"));
+        let replayed = MockBackend::from_recording(&loaded);
+        assert_eq!(replayed.get_monitors().unwrap(), ["MOCK-3"]);
+
+        let kinds: Vec<_> = subscription.try_iter().map(|e| e.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                WmEventKind::WorkspacesChanged,
+                WmEventKind::WorkspacesChanged,
+                WmEventKind::MonitorRemoved,
+            ]
+        );
+        assert_eq!(
+            get_workspaces_status("MOCK-3").workspaces[2].state,
+            WorkspaceState::Active
+        );
+    }
+}
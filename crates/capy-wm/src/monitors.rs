@@ -0,0 +1,221 @@
+//! Monitor arrangement profiles.
+//!
+//! A [`MonitorProfile`] captures the mode, position, scale and transform of every
+//! monitor so an arrangement can be saved to disk and restored later through any
+//! backend that supports monitor configuration.
+
+#[cfg(feature = "serde")]
+use crate::WmError;
+use crate::types::{MonitorInfo, MonitorTransform};
+use crate::{WindowBackend, WmResult};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+/// Desired configuration of a single monitor.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorConfig {
+    /// Connector name (e.g. "DP-1").
+    pub name: String,
+    pub enabled: bool,
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in Hz.
+    pub refresh_rate: f32,
+    pub x: i32,
+    pub y: i32,
+    pub scale: f32,
+    pub transform: MonitorTransform,
+}
+
+impl From<&MonitorInfo> for MonitorConfig {
+    fn from(info: &MonitorInfo) -> Self {
+        Self {
+            name: info.name.clone(),
+            enabled: !info.disabled,
+            width: info.width,
+            height: info.height,
+            refresh_rate: info.refresh_rate,
+            x: info.x,
+            y: info.y,
+            scale: info.scale,
+            transform: info.transform,
+        }
+    }
+}
+
+/// A saved arrangement of monitors.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorProfile {
+    pub name: String,
+    pub monitors: Vec<MonitorConfig>,
+}
+
+impl MonitorProfile {
+    /// Capture the current arrangement from a backend.
+    pub fn capture(name: impl Into<String>, backend: &dyn WindowBackend) -> WmResult<Self> {
+        Ok(Self {
+            name: name.into(),
+            monitors: backend
+                .get_monitor_info()?
+                .iter()
+                .map(MonitorConfig::from)
+                .collect(),
+        })
+    }
+
+    /// Apply this arrangement. Monitors not in the profile are left untouched.
+    /// Enabled monitors are configured first so disabling never leaves no output active.
+    pub fn apply(&self, backend: &dyn WindowBackend) -> WmResult<()> {
+        let (enabled, disabled): (Vec<_>, Vec<_>) =
+            self.monitors.iter().partition(|config| config.enabled);
+        for config in enabled.into_iter().chain(disabled) {
+            backend.apply_monitor_config(config)?;
+        }
+        Ok(())
+    }
+
+    /// Load a profile from a JSON file.
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> WmResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| WmError::InvalidProfile(e.to_string()))
+    }
+
+    /// Save this profile as a JSON file.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> WmResult<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| WmError::InvalidProfile(e.to_string()))?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Backend with a fixed monitor list that records configuration calls.
+    #[derive(Default)]
+    struct FakeBackend {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl FakeBackend {
+        fn record(&self, call: String) -> WmResult<()> {
+            self.calls.lock().unwrap().push(call);
+            Ok(())
+        }
+    }
+
+    impl WindowBackend for FakeBackend {
+        fn get_active_monitor(&self) -> WmResult<String> {
+            Ok("DP-1".to_string())
+        }
+
+        fn get_monitors(&self) -> WmResult<Vec<String>> {
+            Ok(vec!["DP-1".to_string(), "HDMI-A-1".to_string()])
+        }
+
+        fn get_monitor_info(&self) -> WmResult<Vec<MonitorInfo>> {
+            Ok(vec![
+                MonitorInfo {
+                    name: "DP-1".to_string(),
+                    width: 2560,
+                    height: 1440,
+                    refresh_rate: 144.0,
+                    scale: 1.0,
+                    ..Default::default()
+                },
+                MonitorInfo {
+                    name: "HDMI-A-1".to_string(),
+                    disabled: true,
+                    ..Default::default()
+                },
+            ])
+        }
+
+        fn switch_workspace(&self, _workspace_id: i32) -> WmResult<()> {
+            Ok(())
+        }
+
+        fn set_monitor_enabled(&self, name: &str, enabled: bool) -> WmResult<()> {
+            self.record(format!("{} enabled {}", name, enabled))
+        }
+
+        fn set_monitor_mode(
+            &self,
+            name: &str,
+            width: i32,
+            height: i32,
+            refresh: f32,
+        ) -> WmResult<()> {
+            self.record(format!("{} mode {}x{}@{}", name, width, height, refresh))
+        }
+
+        fn set_monitor_position(&self, name: &str, x: i32, y: i32) -> WmResult<()> {
+            self.record(format!("{} position {} {}", name, x, y))
+        }
+
+        fn set_monitor_scale(&self, name: &str, scale: f32) -> WmResult<()> {
+            self.record(format!("{} scale {}", name, scale))
+        }
+
+        fn set_monitor_transform(&self, name: &str, transform: MonitorTransform) -> WmResult<()> {
+            self.record(format!("{} transform {}", name, transform.name()))
+        }
+
+        fn start_listener(&self) -> WmResult<()> {
+            Ok(())
+        }
+
+        fn trigger_refresh(&self) -> WmResult<()> {
+            Ok(())
+        }
+
+        fn init_active_window(&self) -> WmResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_capture_and_apply() {
+        let backend = FakeBackend::default();
+        let mut profile = MonitorProfile::capture("desk", &backend).unwrap();
+        assert_eq!(profile.monitors.len(), 2);
+
+        // Disabled monitors are applied last
+        profile.monitors.swap(0, 1);
+        profile.apply(&backend).unwrap();
+
+        assert_eq!(
+            backend.calls.lock().unwrap().as_slice(),
+            [
+                "DP-1 enabled true",
+                "DP-1 mode 2560x1440@144",
+                "DP-1 scale 1",
+                "DP-1 transform normal",
+                "DP-1 position 0 0",
+                "HDMI-A-1 enabled false",
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_and_load() {
+        let backend = FakeBackend::default();
+        let profile = MonitorProfile::capture("desk", &backend).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("capy-wm-profile-{}.json", std::process::id()));
+
+        profile.save(&path).unwrap();
+        let loaded = MonitorProfile::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded, profile);
+    }
+}
@@ -0,0 +1,362 @@
+//! Minimal client for the niri IPC socket.
+//!
+//! Niri speaks newline-delimited JSON: one request per line, answered by one
+//! `{"Ok": ...}` or `{"Err": "..."}` reply line. After an `EventStream` request
+//! the connection keeps streaming one event object per line.
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+/// Resolve the niri IPC socket path from `NIRI_SOCKET`.
+pub(crate) fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("NIRI_SOCKET").map(PathBuf::from)
+}
+
+/// A single connection to the niri IPC socket.
+pub(crate) struct NiriConnection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl NiriConnection {
+    /// Connect to the socket at `path`.
+    pub(crate) fn connect(path: &Path) -> io::Result<Self> {
+        let writer = UnixStream::connect(path)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+
+    /// Send a request and return the `Ok` payload of the reply.
+    pub(crate) fn request(&mut self, request: &Value) -> io::Result<Value> {
+        let mut line = serde_json::to_string(request).map_err(io::Error::from)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+
+        match self.read_line()? {
+            Some(reply) => parse_reply(reply),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "niri closed the connection",
+            )),
+        }
+    }
+
+    /// Send a request and deserialize the response wrapped in `{"<kind>": ...}`,
+    /// e.g. `{"Workspaces": [...]}` for the `"Workspaces"` request.
+    pub(crate) fn query<T: DeserializeOwned>(&mut self, kind: &str) -> io::Result<T> {
+        let mut reply = self.request(&Value::String(kind.to_string()))?;
+        let inner = reply
+            .get_mut(kind)
+            .map(Value::take)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unexpected niri reply"))?;
+        serde_json::from_value(inner).map_err(io::Error::from)
+    }
+
+    /// Run a niri action, e.g. `{"FocusWorkspace": {"reference": {"Id": 3}}}`.
+    pub(crate) fn action(&mut self, action: Value) -> io::Result<()> {
+        self.request(&serde_json::json!({ "Action": action }))
+            .map(|_| ())
+    }
+
+    /// Change an output's configuration, e.g. `{"Scale": {"scale": {"Specific": 1.5}}}`.
+    pub(crate) fn output_action(&mut self, output: &str, action: Value) -> io::Result<()> {
+        self.request(&serde_json::json!({ "Output": { "output": output, "action": action } }))
+            .map(|_| ())
+    }
+
+    /// Switch this connection into event stream mode.
+    pub(crate) fn event_stream(&mut self) -> io::Result<()> {
+        self.request(&Value::String("EventStream".to_string()))
+            .map(|_| ())
+    }
+
+    /// Read the next event from an event stream connection.
+    /// Returns `None` when niri closes the socket.
+    pub(crate) fn read_event(&mut self) -> io::Result<Option<Event>> {
+        loop {
+            let Some(value) = self.read_line()? else {
+                return Ok(None);
+            };
+            if let Some(event) = Event::from_value(value) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<Value>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        serde_json::from_str(&line)
+            .map(Some)
+            .map_err(io::Error::from)
+    }
+}
+
+/// Unwrap a `{"Ok": ...}` / `{"Err": "..."}` reply.
+fn parse_reply(mut reply: Value) -> io::Result<Value> {
+    if let Some(ok) = reply.get_mut("Ok") {
+        return Ok(ok.take());
+    }
+    let message = reply
+        .get("Err")
+        .and_then(Value::as_str)
+        .unwrap_or("malformed niri reply")
+        .to_string();
+    Err(io::Error::other(message))
+}
+
+// ============ Reply and event types ============
+
+/// A niri workspace. Workspaces are dynamic and ordered per output by `idx`.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NiriWorkspace {
+    pub id: u64,
+    pub idx: u8,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub is_urgent: bool,
+    pub is_active: bool,
+    pub is_focused: bool,
+    #[serde(default)]
+    pub active_window_id: Option<u64>,
+}
+
+/// A niri window.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NiriWindow {
+    pub id: u64,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub workspace_id: Option<u64>,
+    pub is_focused: bool,
+    #[serde(default)]
+    pub is_floating: bool,
+    #[serde(default)]
+    pub is_urgent: bool,
+    #[serde(default)]
+    pub pid: Option<i32>,
+    /// Only reported by niri 25.08 and newer.
+    #[serde(default)]
+    pub layout: Option<NiriWindowLayout>,
+}
+
+/// Size and position of a niri window.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NiriWindowLayout {
+    pub window_size: (i32, i32),
+    #[serde(default)]
+    pub tile_pos_in_workspace_view: Option<(f64, f64)>,
+}
+
+/// A niri output as returned by the `Outputs` request.
+/// `logical` is `None` while the output is disabled.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NiriOutput {
+    pub name: String,
+    #[serde(default)]
+    pub make: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub modes: Vec<NiriMode>,
+    /// Index into `modes`.
+    #[serde(default)]
+    pub current_mode: Option<usize>,
+    #[serde(default)]
+    pub logical: Option<NiriLogicalOutput>,
+}
+
+/// Output mode, `refresh_rate` is in mHz.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(crate) struct NiriMode {
+    pub width: i32,
+    pub height: i32,
+    pub refresh_rate: i32,
+}
+
+/// Placement of an enabled output in the global layout.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NiriLogicalOutput {
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub transform: Option<String>,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// Reply to the `Outputs` request, keyed by connector name.
+pub(crate) type NiriOutputs = HashMap<String, NiriOutput>;
+
+/// Events from the niri event stream that capy-wm cares about.
+#[derive(Clone, Debug)]
+pub(crate) enum Event {
+    WorkspacesChanged(Vec<NiriWorkspace>),
+    WorkspaceActivated {
+        id: u64,
+        focused: bool,
+    },
+    WorkspaceUrgencyChanged {
+        id: u64,
+        urgent: bool,
+    },
+    WorkspaceActiveWindowChanged {
+        workspace_id: u64,
+        active_window_id: Option<u64>,
+    },
+    WindowsChanged(Vec<NiriWindow>),
+    WindowOpenedOrChanged(NiriWindow),
+    WindowClosed(u64),
+    WindowFocusChanged(Option<u64>),
+    WindowUrgencyChanged {
+        id: u64,
+        urgent: bool,
+    },
+}
+
+#[derive(Deserialize)]
+struct WorkspacesBody {
+    workspaces: Vec<NiriWorkspace>,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceActivatedBody {
+    id: u64,
+    focused: bool,
+}
+
+#[derive(Deserialize)]
+struct UrgencyBody {
+    id: u64,
+    urgent: bool,
+}
+
+#[derive(Deserialize)]
+struct ActiveWindowBody {
+    workspace_id: u64,
+    active_window_id: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct WindowsBody {
+    windows: Vec<NiriWindow>,
+}
+
+#[derive(Deserialize)]
+struct WindowBody {
+    window: NiriWindow,
+}
+
+#[derive(Deserialize)]
+struct IdBody {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct FocusBody {
+    id: Option<u64>,
+}
+
+impl Event {
+    /// Decode an externally tagged event object such as `{"WindowClosed": {"id": 3}}`.
+    /// Unknown or malformed events return `None` so newer niri versions don't break us.
+    pub(crate) fn from_value(value: Value) -> Option<Self> {
+        let Value::Object(map) = value else {
+            return None;
+        };
+        let (name, body) = map.into_iter().next()?;
+
+        fn body_of<T: DeserializeOwned>(body: Value) -> Option<T> {
+            serde_json::from_value(body).ok()
+        }
+
+        Some(match name.as_str() {
+            "WorkspacesChanged" => {
+                Event::WorkspacesChanged(body_of::<WorkspacesBody>(body)?.workspaces)
+            }
+            "WorkspaceActivated" => {
+                let b: WorkspaceActivatedBody = body_of(body)?;
+                Event::WorkspaceActivated {
+                    id: b.id,
+                    focused: b.focused,
+                }
+            }
+            "WorkspaceUrgencyChanged" => {
+                let b: UrgencyBody = body_of(body)?;
+                Event::WorkspaceUrgencyChanged {
+                    id: b.id,
+                    urgent: b.urgent,
+                }
+            }
+            "WorkspaceActiveWindowChanged" => {
+                let b: ActiveWindowBody = body_of(body)?;
+                Event::WorkspaceActiveWindowChanged {
+                    workspace_id: b.workspace_id,
+                    active_window_id: b.active_window_id,
+                }
+            }
+            "WindowsChanged" => Event::WindowsChanged(body_of::<WindowsBody>(body)?.windows),
+            "WindowOpenedOrChanged" => {
+                Event::WindowOpenedOrChanged(body_of::<WindowBody>(body)?.window)
+            }
+            "WindowClosed" => Event::WindowClosed(body_of::<IdBody>(body)?.id),
+            "WindowFocusChanged" => Event::WindowFocusChanged(body_of::<FocusBody>(body)?.id),
+            "WindowUrgencyChanged" => {
+                let b: UrgencyBody = body_of(body)?;
+                Event::WindowUrgencyChanged {
+                    id: b.id,
+                    urgent: b.urgent,
+                }
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply_ok_and_err() {
+        let ok = parse_reply(serde_json::json!({"Ok": "Handled"})).unwrap();
+        assert_eq!(ok, Value::String("Handled".into()));
+
+        let err = parse_reply(serde_json::json!({"Err": "no such workspace"})).unwrap_err();
+        assert_eq!(err.to_string(), "no such workspace");
+    }
+
+    #[test]
+    fn test_decode_events() {
+        let closed = Event::from_value(serde_json::json!({"WindowClosed": {"id": 7}}));
+        assert!(matches!(closed, Some(Event::WindowClosed(7))));
+
+        let unfocused = Event::from_value(serde_json::json!({"WindowFocusChanged": {"id": null}}));
+        assert!(matches!(unfocused, Some(Event::WindowFocusChanged(None))));
+
+        let unknown =
+            Event::from_value(serde_json::json!({"OverviewOpenedOrClosed": {"is_open": true}}));
+        assert!(unknown.is_none());
+    }
+}
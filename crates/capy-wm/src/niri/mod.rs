@@ -0,0 +1,475 @@
+//! Niri backend for capy-wm.
+//!
+//! Implements the WindowBackend trait over niri's JSON IPC socket (`$NIRI_SOCKET`).
+//! Niri's workspaces are dynamic per output, so workspace IDs come from niri's
+//! per-output index instead of a fixed range per monitor.
+
+mod ipc;
+mod state;
+
+use crate::types::{MonitorInfo, MonitorTransform};
+use crate::{
+    WindowBackend, WmError, WmEvent, WmResult, get_state, read, send_event, set_urgent,
+    sync_windows, write,
+};
+use ipc::{Event, NiriConnection, NiriOutputs, NiriWindow, NiriWorkspace};
+use log::{debug, error, info};
+use serde_json::json;
+use state::NiriState;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, RwLock};
+use std::thread;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+// Mirror of niri's state, kept up to date by the event stream
+static MODEL: LazyLock<RwLock<NiriState>> = LazyLock::new(|| RwLock::new(NiriState::default()));
+
+/// Niri window manager backend.
+pub struct NiriBackend {
+    socket: PathBuf,
+}
+
+impl NiriBackend {
+    /// Create a new niri backend using the socket from `$NIRI_SOCKET`.
+    pub fn new() -> Self {
+        Self::with_socket(ipc::socket_path().unwrap_or_default())
+    }
+
+    /// Create a niri backend talking to a specific IPC socket.
+    pub fn with_socket(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    fn connect(&self) -> std::io::Result<NiriConnection> {
+        NiriConnection::connect(&self.socket)
+    }
+
+    /// Run a niri action on a fresh connection.
+    fn action(&self, action: serde_json::Value) -> WmResult<()> {
+        Ok(self.connect()?.action(action)?)
+    }
+
+    /// Run an output action on a fresh connection.
+    fn output_action(&self, name: &str, action: serde_json::Value) -> WmResult<()> {
+        Ok(self.connect()?.output_action(name, action)?)
+    }
+
+    /// Re-fetch workspaces and windows and load them into the model.
+    fn reload_model(&self) -> std::io::Result<()> {
+        let mut conn = self.connect()?;
+        let workspaces: Vec<NiriWorkspace> = conn.query("Workspaces")?;
+        let windows: Vec<NiriWindow> = conn.query("Windows")?;
+
+        let mut model = write(&MODEL);
+        model.apply(Event::WorkspacesChanged(workspaces));
+        model.apply(Event::WindowsChanged(windows));
+        Ok(())
+    }
+}
+
+impl Default for NiriBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowBackend for NiriBackend {
+    fn get_active_monitor(&self) -> WmResult<String> {
+        let focused: Option<ipc::NiriOutput> = self.connect()?.query("FocusedOutput")?;
+        Ok(focused.map(|output| output.name).unwrap_or_default())
+    }
+
+    fn get_monitors(&self) -> WmResult<Vec<String>> {
+        let outputs: NiriOutputs = self.connect()?.query("Outputs")?;
+        let mut monitors: Vec<String> = outputs
+            .into_values()
+            .filter(|o| o.logical.is_some())
+            .map(|o| o.name)
+            .collect();
+        monitors.sort();
+        Ok(monitors)
+    }
+
+    fn get_monitor_info(&self) -> WmResult<Vec<MonitorInfo>> {
+        let focused = self.get_active_monitor()?;
+        let outputs: NiriOutputs = self.connect()?.query("Outputs")?;
+        let mut monitors: Vec<MonitorInfo> = outputs.into_values().map(monitor_info).collect();
+        for monitor in &mut monitors {
+            monitor.focused = monitor.name == focused;
+        }
+        monitors.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(monitors)
+    }
+
+    fn set_monitor_mode(
+        &self,
+        name: &str,
+        width: i32,
+        height: i32,
+        refresh_rate: f32,
+    ) -> WmResult<()> {
+        let mode = json!({ "width": width, "height": height, "refresh": refresh_rate });
+        self.output_action(name, json!({ "Mode": { "mode": { "Specific": mode } } }))
+    }
+
+    fn set_monitor_position(&self, name: &str, x: i32, y: i32) -> WmResult<()> {
+        let position = json!({ "x": x, "y": y });
+        self.output_action(
+            name,
+            json!({ "Position": { "position": { "Specific": position } } }),
+        )
+    }
+
+    fn set_monitor_scale(&self, name: &str, scale: f32) -> WmResult<()> {
+        self.output_action(name, json!({ "Scale": { "scale": { "Specific": scale } } }))
+    }
+
+    fn set_monitor_transform(&self, name: &str, transform: MonitorTransform) -> WmResult<()> {
+        self.output_action(
+            name,
+            json!({ "Transform": { "transform": transform.niri_name() } }),
+        )
+    }
+
+    fn set_monitor_enabled(&self, name: &str, enabled: bool) -> WmResult<()> {
+        self.output_action(name, json!(if enabled { "On" } else { "Off" }))
+    }
+
+    fn switch_workspace(&self, workspace_id: i32) -> WmResult<()> {
+        self.action(json!({ "FocusWorkspace": { "reference": { "Id": workspace_id } } }))
+    }
+
+    fn close_window(&self, address: &str) -> WmResult<()> {
+        let id = window_id(address)?;
+        self.action(json!({ "CloseWindow": { "id": id } }))
+    }
+
+    fn focus_window(&self, address: &str) -> WmResult<()> {
+        let id = window_id(address)?;
+        self.action(json!({ "FocusWindow": { "id": id } }))
+    }
+
+    fn move_window_to_workspace(&self, address: &str, workspace_id: i32) -> WmResult<()> {
+        let id = window_id(address)?;
+        self.action(json!({ "MoveWindowToWorkspace": {
+            "window_id": id,
+            "reference": { "Id": workspace_id },
+            "focus": false
+        } }))
+    }
+
+    fn toggle_floating(&self, address: &str) -> WmResult<()> {
+        let id = window_id(address)?;
+        self.action(json!({ "ToggleWindowFloating": { "id": id } }))
+    }
+
+    fn toggle_fullscreen(&self, address: &str) -> WmResult<()> {
+        let id = window_id(address)?;
+        self.action(json!({ "FullscreenWindow": { "id": id } }))
+    }
+
+    fn start_listener(&self) -> WmResult<()> {
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            info!("Niri listener already running");
+            return Ok(());
+        }
+
+        info!("Starting niri event listener...");
+
+        // The event stream initializes the model, only check that niri is reachable
+        let conn = match NiriConnection::connect(&self.socket) {
+            Ok(conn) => conn,
+            Err(e) => {
+                RUNNING.store(false, Ordering::SeqCst);
+                return Err(e.into());
+            }
+        };
+
+        thread::spawn(move || {
+            if let Err(e) = listen(conn) {
+                error!("Niri listener failed: {}", e);
+            }
+            RUNNING.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    fn trigger_refresh(&self) -> WmResult<()> {
+        info!("Triggering niri state refresh...");
+        self.reload_model()?;
+        send_updates_to_all_monitors();
+        let windows = read(&MODEL).windows();
+        sync_windows(windows);
+        self.init_active_window()
+    }
+
+    fn init_active_window(&self) -> WmResult<()> {
+        let active = read(&MODEL).active_window();
+        send_event(WmEvent::ActiveWindowChanged(active));
+        Ok(())
+    }
+}
+
+/// Parse a capy-wm window address into a niri window ID.
+fn window_id(address: &str) -> WmResult<u64> {
+    address
+        .parse()
+        .map_err(|_| WmError::Dispatch(format!("invalid niri window id: {}", address)))
+}
+
+/// Open the event stream and apply events until the socket closes.
+/// Niri sends the full workspace and window lists first, which initializes the model.
+fn listen(mut conn: NiriConnection) -> std::io::Result<()> {
+    conn.event_stream()?;
+    info!("Niri event listener active");
+
+    while let Some(event) = conn.read_event()? {
+        debug!("Niri event: {:?}", event);
+        handle_event(event);
+    }
+    Ok(())
+}
+
+/// Apply an event to the model and emit the resulting capy-wm events.
+fn handle_event(event: Event) {
+    let urgency: Vec<(u64, bool)> = match &event {
+        Event::WindowUrgencyChanged { id, urgent } => vec![(*id, *urgent)],
+        Event::WindowOpenedOrChanged(window) => vec![(window.id, window.is_urgent)],
+        Event::WindowsChanged(windows) => windows.iter().map(|w| (w.id, w.is_urgent)).collect(),
+        _ => Vec::new(),
+    };
+
+    let (before, after, active, windows) = {
+        let mut model = write(&MODEL);
+        let before = model.outputs();
+        model.apply(event);
+        (
+            before,
+            model.outputs(),
+            model.active_window(),
+            model.windows(),
+        )
+    };
+
+    emit_monitor_changes(&before, &after);
+    send_updates_to_all_monitors();
+    sync_windows(windows);
+    for (id, urgent) in urgency {
+        set_urgent(&id.to_string(), urgent);
+    }
+
+    let changed = {
+        let current = read(&get_state().active_window);
+        current.address != active.address
+            || current.window_title != active.window_title
+            || current.focused_monitor != active.focused_monitor
+    };
+    if changed {
+        send_event(WmEvent::ActiveWindowChanged(active));
+    }
+}
+
+/// Convert an `Outputs` entry into monitor info. Focus is filled in by the caller.
+fn monitor_info(output: ipc::NiriOutput) -> MonitorInfo {
+    let mode = output
+        .current_mode
+        .and_then(|index| output.modes.get(index).copied());
+    let description = [
+        Some(&output.make),
+        Some(&output.model),
+        output.serial.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|s| !s.is_empty())
+    .map(|s| s.as_str())
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    MonitorInfo {
+        description,
+        make: output.make,
+        model: output.model,
+        width: mode.map(|m| m.width).unwrap_or_default(),
+        height: mode.map(|m| m.height).unwrap_or_default(),
+        refresh_rate: mode
+            .map(|m| m.refresh_rate as f32 / 1000.0)
+            .unwrap_or_default(),
+        scale: output.logical.as_ref().map(|l| l.scale).unwrap_or(1.0),
+        transform: output
+            .logical
+            .as_ref()
+            .and_then(|l| l.transform.as_deref())
+            .and_then(MonitorTransform::from_name)
+            .unwrap_or_default(),
+        x: output.logical.as_ref().map(|l| l.x).unwrap_or_default(),
+        y: output.logical.as_ref().map(|l| l.y).unwrap_or_default(),
+        focused: false,
+        disabled: output.logical.is_none(),
+        name: output.name,
+    }
+}
+
+/// Niri has no output events, so outputs appearing or disappearing from the
+/// workspace list are reported as hotplug.
+fn emit_monitor_changes(before: &BTreeSet<String>, after: &BTreeSet<String>) {
+    // The first workspace list is the initial state, not a hotplug
+    if before.is_empty() {
+        return;
+    }
+    for name in after.difference(before) {
+        debug!("Monitor added: {}", name);
+        send_event(WmEvent::MonitorAdded(name.clone()));
+    }
+    for name in before.difference(after) {
+        debug!("Monitor removed: {}", name);
+        send_event(WmEvent::MonitorRemoved(name.clone()));
+    }
+}
+
+/// Send workspace updates to all outputs.
+fn send_updates_to_all_monitors() {
+    let statuses: Vec<_> = {
+        let model = read(&MODEL);
+        model
+            .outputs()
+            .iter()
+            .map(|output| model.workspaces_status(output))
+            .collect()
+    };
+
+    for status in statuses {
+        send_event(WmEvent::WorkspacesChanged(status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+    use std::sync::{Arc, Mutex};
+
+    fn ok_reply(value: Value) -> String {
+        format!("{}\n", json!({ "Ok": value }))
+    }
+
+    /// A fake niri socket answering requests with canned replies.
+    fn fake_niri(name: &str, requests: Arc<Mutex<Vec<Value>>>) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("capy-wm-niri-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    let request: Value = serde_json::from_str(&line).unwrap();
+                    line.clear();
+
+                    let reply = match request.as_str() {
+                        Some("Outputs") => ok_reply(json!({"Outputs": {
+                            "DP-1": {"name": "DP-1", "make": "LG", "model": "27GL850",
+                                     "modes": [{"width": 2560, "height": 1440, "refresh_rate": 143998}],
+                                     "current_mode": 0,
+                                     "logical": {"x": 0, "y": 0, "scale": 1.25, "transform": "Flipped90"}},
+                            "HDMI-A-1": {"name": "HDMI-A-1", "logical": null}
+                        }})),
+                        Some("FocusedOutput") => {
+                            ok_reply(json!({"FocusedOutput": {"name": "DP-1", "logical": {}}}))
+                        }
+                        _ => ok_reply(json!("Handled")),
+                    };
+                    requests.lock().unwrap().push(request);
+                    if stream.write_all(reply.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        path
+    }
+
+    #[test]
+    fn test_monitors_skip_disabled_outputs() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let backend = NiriBackend::with_socket(fake_niri("monitors", requests));
+
+        assert_eq!(backend.get_monitors().unwrap(), vec!["DP-1"]);
+        assert_eq!(backend.get_active_monitor().unwrap(), "DP-1");
+    }
+
+    #[test]
+    fn test_monitor_info_and_configuration() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let backend = NiriBackend::with_socket(fake_niri("outputs", requests.clone()));
+
+        let monitors = backend.get_monitor_info().unwrap();
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(monitors[0].description, "LG 27GL850");
+        assert_eq!((monitors[0].width, monitors[0].height), (2560, 1440));
+        assert_eq!(monitors[0].scale, 1.25);
+        assert_eq!(monitors[0].transform, MonitorTransform::Flipped90);
+        assert!(monitors[0].focused);
+        assert!(monitors[1].disabled);
+
+        requests.lock().unwrap().clear();
+        backend.set_monitor_scale("DP-1", 2.0).unwrap();
+        backend.set_monitor_enabled("HDMI-A-1", false).unwrap();
+
+        assert_eq!(
+            requests.lock().unwrap().as_slice(),
+            [
+                json!({"Output": {"output": "DP-1", "action": {"Scale": {"scale": {"Specific": 2.0}}}}}),
+                json!({"Output": {"output": "HDMI-A-1", "action": "Off"}})
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transform_uses_niri_names() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let backend = NiriBackend::with_socket(fake_niri("transform", requests.clone()));
+
+        for transform in [
+            MonitorTransform::Normal,
+            MonitorTransform::Rotate90,
+            MonitorTransform::Flipped270,
+        ] {
+            backend.set_monitor_transform("DP-1", transform).unwrap();
+        }
+
+        let transform = |name: &str| json!({"Output": {"output": "DP-1", "action": {"Transform": {"transform": name}}}});
+        assert_eq!(
+            requests.lock().unwrap().as_slice(),
+            [
+                transform("Normal"),
+                transform("90"),
+                transform("Flipped270")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_switch_workspace_uses_niri_id() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let backend = NiriBackend::with_socket(fake_niri("switch", requests.clone()));
+
+        backend.switch_workspace(9).unwrap();
+
+        assert_eq!(
+            requests.lock().unwrap().as_slice(),
+            [json!({"Action": {"FocusWorkspace": {"reference": {"Id": 9}}}})]
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Local mirror of niri's workspaces and windows, fed by the event stream.
+
+use crate::resolve_icon;
+use crate::types::{
+    ActiveWindowInfo, WindowGeometry, WindowInfo, WorkspaceInfo, WorkspaceKind, WorkspaceState,
+    WorkspacesStatus,
+};
+use std::collections::{BTreeSet, HashMap};
+
+use super::ipc::{Event, NiriWindow, NiriWorkspace};
+
+/// Niri state as seen through the event stream.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NiriState {
+    workspaces: HashMap<u64, NiriWorkspace>,
+    windows: HashMap<u64, NiriWindow>,
+    focused_window: Option<u64>,
+}
+
+impl NiriState {
+    /// Apply a single event from the event stream.
+    pub(crate) fn apply(&mut self, event: Event) {
+        match event {
+            Event::WorkspacesChanged(workspaces) => {
+                self.workspaces = workspaces.into_iter().map(|ws| (ws.id, ws)).collect();
+            }
+            Event::WorkspaceActivated { id, focused } => {
+                let output = self.workspaces.get(&id).and_then(|ws| ws.output.clone());
+                for ws in self.workspaces.values_mut() {
+                    if ws.output == output {
+                        ws.is_active = ws.id == id;
+                    }
+                    if focused {
+                        ws.is_focused = ws.id == id;
+                    }
+                }
+            }
+            Event::WorkspaceUrgencyChanged { id, urgent } => {
+                if let Some(ws) = self.workspaces.get_mut(&id) {
+                    ws.is_urgent = urgent;
+                }
+            }
+            Event::WorkspaceActiveWindowChanged {
+                workspace_id,
+                active_window_id,
+            } => {
+                if let Some(ws) = self.workspaces.get_mut(&workspace_id) {
+                    ws.active_window_id = active_window_id;
+                }
+            }
+            Event::WindowsChanged(windows) => {
+                self.focused_window = windows.iter().find(|w| w.is_focused).map(|w| w.id);
+                self.windows = windows.into_iter().map(|w| (w.id, w)).collect();
+            }
+            Event::WindowOpenedOrChanged(window) => {
+                if window.is_focused {
+                    self.set_focus(Some(window.id));
+                }
+                self.windows.insert(window.id, window);
+            }
+            Event::WindowClosed(id) => {
+                self.windows.remove(&id);
+                if self.focused_window == Some(id) {
+                    self.focused_window = None;
+                }
+            }
+            Event::WindowFocusChanged(id) => self.set_focus(id),
+            Event::WindowUrgencyChanged { id, urgent } => {
+                if let Some(window) = self.windows.get_mut(&id) {
+                    window.is_urgent = urgent;
+                }
+            }
+        }
+    }
+
+    fn set_focus(&mut self, id: Option<u64>) {
+        for window in self.windows.values_mut() {
+            window.is_focused = Some(window.id) == id;
+        }
+        self.focused_window = id;
+    }
+
+    /// Names of all outputs that currently hold workspaces.
+    pub(crate) fn outputs(&self) -> BTreeSet<String> {
+        self.workspaces
+            .values()
+            .filter_map(|ws| ws.output.clone())
+            .collect()
+    }
+
+    /// Name of the output holding the focused workspace.
+    pub(crate) fn focused_output(&self) -> Option<String> {
+        self.workspaces
+            .values()
+            .find(|ws| ws.is_focused)
+            .and_then(|ws| ws.output.clone())
+    }
+
+    /// Build the workspace status for one output.
+    /// Workspace IDs come from niri's per-output `idx`, the absolute ID is niri's workspace ID.
+    pub(crate) fn workspaces_status(&self, monitor_name: &str) -> WorkspacesStatus {
+        let mut on_output: Vec<_> = self
+            .workspaces
+            .values()
+            .filter(|ws| ws.output.as_deref() == Some(monitor_name))
+            .collect();
+        on_output.sort_by_key(|ws| ws.idx);
+
+        let workspaces = on_output
+            .into_iter()
+            .map(|ws| {
+                let mut windows: Vec<_> = self
+                    .windows
+                    .values()
+                    .filter(|w| w.workspace_id == Some(ws.id))
+                    .collect();
+                windows.sort_by_key(|w| w.id);
+
+                let has_urgent = ws.is_urgent || windows.iter().any(|w| w.is_urgent);
+
+                // Prefer the window niri considers active on this workspace
+                let primary = ws
+                    .active_window_id
+                    .and_then(|id| self.windows.get(&id))
+                    .or_else(|| windows.first().copied());
+
+                let (app_class, icon_path) = match primary {
+                    Some(window) => {
+                        let class = window.app_id.clone().unwrap_or_default();
+                        let icon = resolve_icon(&class);
+                        (Some(class), icon)
+                    }
+                    None => (None, None),
+                };
+
+                let state = if ws.is_active {
+                    if ws.is_focused {
+                        WorkspaceState::Active
+                    } else {
+                        WorkspaceState::Visible
+                    }
+                } else if has_urgent {
+                    WorkspaceState::Attention
+                } else {
+                    WorkspaceState::Empty
+                };
+
+                WorkspaceInfo {
+                    id: ws.idx as i32,
+                    absolute_id: ws.id as i32,
+                    name: ws.name.clone().unwrap_or_else(|| ws.idx.to_string()),
+                    kind: if ws.name.is_some() {
+                        WorkspaceKind::Named
+                    } else {
+                        WorkspaceKind::Normal
+                    },
+                    state,
+                    icon_path,
+                    occupied: !windows.is_empty(),
+                    app_class,
+                }
+            })
+            .collect();
+
+        WorkspacesStatus {
+            monitor_name: monitor_name.to_string(),
+            workspaces,
+            special: Vec::new(),
+        }
+    }
+
+    /// Build the window list.
+    pub(crate) fn windows(&self) -> Vec<WindowInfo> {
+        self.windows
+            .values()
+            .map(|window| {
+                let class = window.app_id.clone().unwrap_or_default();
+                let monitor = window
+                    .workspace_id
+                    .and_then(|id| self.workspaces.get(&id))
+                    .and_then(|ws| ws.output.clone())
+                    .unwrap_or_default();
+                let geometry = window
+                    .layout
+                    .as_ref()
+                    .map(|layout| {
+                        let (x, y) = layout.tile_pos_in_workspace_view.unwrap_or_default();
+                        WindowGeometry {
+                            x: x as i32,
+                            y: y as i32,
+                            width: layout.window_size.0,
+                            height: layout.window_size.1,
+                        }
+                    })
+                    .unwrap_or_default();
+
+                WindowInfo {
+                    address: window.id.to_string(),
+                    icon_path: resolve_icon(&class),
+                    initial_class: class.clone(),
+                    class,
+                    title: window.title.clone().unwrap_or_default(),
+                    workspace_id: window.workspace_id.unwrap_or_default() as i32,
+                    monitor,
+                    floating: window.is_floating,
+                    // niri does not report fullscreen state over IPC
+                    fullscreen: false,
+                    pid: window.pid,
+                    geometry,
+                }
+            })
+            .collect()
+    }
+
+    /// Build the active window info from the focused window.
+    pub(crate) fn active_window(&self) -> ActiveWindowInfo {
+        let Some(window) = self.focused_window.and_then(|id| self.windows.get(&id)) else {
+            return ActiveWindowInfo {
+                focused_monitor: self.focused_output().unwrap_or_default(),
+                ..Default::default()
+            };
+        };
+
+        let class = window.app_id.clone().unwrap_or_default();
+        let focused_monitor = window
+            .workspace_id
+            .and_then(|id| self.workspaces.get(&id))
+            .and_then(|ws| ws.output.clone())
+            .or_else(|| self.focused_output())
+            .unwrap_or_default();
+
+        ActiveWindowInfo {
+            address: window.id.to_string(),
+            icon_path: resolve_icon(&class),
+            app: class,
+            window_title: window.title.clone().unwrap_or_default(),
+            focused_monitor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(value: serde_json::Value) -> Event {
+        Event::from_value(value).expect("valid event")
+    }
+
+    fn sample_state() -> NiriState {
+        let mut state = NiriState::default();
+        state.apply(event(json!({"WorkspacesChanged": {"workspaces": [
+            {"id": 5, "idx": 1, "name": null, "output": "DP-1", "is_urgent": false,
+             "is_active": true, "is_focused": true, "active_window_id": 20},
+            {"id": 9, "idx": 2, "name": null, "output": "DP-1", "is_urgent": false,
+             "is_active": false, "is_focused": false, "active_window_id": null},
+            {"id": 3, "idx": 1, "name": "chat", "output": "HDMI-A-1", "is_urgent": false,
+             "is_active": true, "is_focused": false, "active_window_id": null}
+        ]}})));
+        state.apply(event(json!({"WindowsChanged": {"windows": [
+            {"id": 20, "title": "~", "app_id": "foot", "pid": 100, "workspace_id": 5,
+             "is_focused": true, "is_floating": false, "is_urgent": false},
+            {"id": 21, "title": "Chat", "app_id": "vesktop", "pid": 101, "workspace_id": 3,
+             "is_focused": false, "is_floating": false, "is_urgent": false}
+        ]}})));
+        state
+    }
+
+    #[test]
+    fn test_ids_come_from_idx() {
+        let state = sample_state();
+
+        let dp = state.workspaces_status("DP-1");
+        assert_eq!(dp.workspaces.len(), 2);
+        assert_eq!(dp.workspaces[0].id, 1);
+        assert_eq!(dp.workspaces[0].absolute_id, 5);
+        assert_eq!(dp.workspaces[0].state, WorkspaceState::Active);
+        assert_eq!(dp.workspaces[0].app_class.as_deref(), Some("foot"));
+        assert_eq!(dp.workspaces[1].id, 2);
+        assert_eq!(dp.workspaces[1].absolute_id, 9);
+        assert!(!dp.workspaces[1].occupied);
+
+        let hdmi = state.workspaces_status("HDMI-A-1");
+        assert_eq!(hdmi.workspaces.len(), 1);
+        assert_eq!(hdmi.workspaces[0].id, 1);
+        assert_eq!(hdmi.workspaces[0].name, "chat");
+        assert_eq!(hdmi.workspaces[0].kind, WorkspaceKind::Named);
+        assert_eq!(hdmi.workspaces[0].state, WorkspaceState::Visible);
+    }
+
+    #[test]
+    fn test_workspace_activation_moves_focus() {
+        let mut state = sample_state();
+        state.apply(event(
+            json!({"WorkspaceActivated": {"id": 9, "focused": true}}),
+        ));
+
+        let dp = state.workspaces_status("DP-1");
+        assert_eq!(dp.workspaces[0].state, WorkspaceState::Empty);
+        assert_eq!(dp.workspaces[1].state, WorkspaceState::Active);
+
+        // The other output keeps its active workspace
+        let hdmi = state.workspaces_status("HDMI-A-1");
+        assert_eq!(hdmi.workspaces[0].state, WorkspaceState::Visible);
+    }
+
+    #[test]
+    fn test_active_window_follows_focus() {
+        let mut state = sample_state();
+        let active = state.active_window();
+        assert_eq!(active.address, "20");
+        assert_eq!(active.app, "foot");
+        assert_eq!(active.focused_monitor, "DP-1");
+
+        state.apply(event(json!({"WindowFocusChanged": {"id": 21}})));
+        let windows = state.windows();
+        let chat = windows.iter().find(|w| w.address == "21").unwrap();
+        assert_eq!(chat.monitor, "HDMI-A-1");
+        assert_eq!(chat.pid, Some(101));
+
+        let active = state.active_window();
+        assert_eq!(active.window_title, "Chat");
+        assert_eq!(active.focused_monitor, "HDMI-A-1");
+
+        state.apply(event(json!({"WindowClosed": {"id": 21}})));
+        assert!(state.active_window().address.is_empty());
+    }
+
+    #[test]
+    fn test_urgent_window_marks_workspace() {
+        let mut state = sample_state();
+        state.apply(event(json!({"WindowOpenedOrChanged": {"window":
+            {"id": 22, "title": "Ping", "app_id": "vesktop", "pid": 102, "workspace_id": 9,
+             "is_focused": false, "is_floating": true, "is_urgent": true}}})));
+
+        let dp = state.workspaces_status("DP-1");
+        assert_eq!(dp.workspaces[1].state, WorkspaceState::Attention);
+        assert!(dp.workspaces[1].occupied);
+    }
+}
@@ -0,0 +1,160 @@
+//! Supervision of compositor event connections.
+//!
+//! The event socket goes away when the compositor restarts or the connection
+//! drops. Backends run their listener through [`supervise`], which reconnects
+//! with exponential backoff and tells subscribers while the state is stale.
+
+use crate::{WmEvent, WmResult, send_event};
+use log::{debug, info, warn};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A listener that ran this long counts as healthy, the next outage starts over with short delays
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+/// Exponential backoff between reconnect attempts.
+pub(crate) struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub(crate) fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Delay before the next attempt. Doubles on every call up to the maximum.
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Start over with the initial delay.
+    pub(crate) fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+/// Keep an event listener alive. Never returns.
+///
+/// `listen` blocks while connected and returns when the connection is lost.
+/// Afterwards [`WmEvent::BackendDisconnected`] is sent and `reconnect` is retried
+/// with backoff until it succeeds; it should do a full refresh so nothing missed
+/// while disconnected stays stale. [`WmEvent::BackendReconnected`] follows once it did.
+pub(crate) fn supervise<L, R>(
+    name: &str,
+    mut backoff: Backoff,
+    mut listen: L,
+    mut reconnect: R,
+) -> !
+where
+    L: FnMut() -> WmResult<()>,
+    R: FnMut() -> WmResult<()>,
+{
+    loop {
+        let started = Instant::now();
+        match listen() {
+            Ok(()) => warn!("{} event connection closed", name),
+            Err(e) => warn!("{} event listener failed: {}", name, e),
+        }
+        if started.elapsed() >= STABLE_AFTER {
+            backoff.reset();
+        }
+        send_event(WmEvent::BackendDisconnected);
+
+        loop {
+            let delay = backoff.next_delay();
+            debug!("Reconnecting to {} in {:?}", name, delay);
+            thread::sleep(delay);
+            match reconnect() {
+                Ok(()) => break,
+                Err(e) => debug!("Reconnecting to {} failed: {}", name, e),
+            }
+        }
+
+        info!("Reconnected to {}", name);
+        send_event(WmEvent::BackendReconnected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::WmEventKind;
+    use crate::{EventFilter, WmError, subscribe_with};
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<_> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_supervise_reconnects_after_failure() {
+        let subscription = subscribe_with(EventFilter::new().kinds([
+            WmEventKind::BackendDisconnected,
+            WmEventKind::BackendReconnected,
+        ]));
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        thread::spawn(move || {
+            let mut listens = 0;
+            let mut attempts = 0;
+            let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(4));
+            supervise(
+                "test",
+                backoff,
+                || {
+                    listens += 1;
+                    if listens > 1 {
+                        // Connected for good, keep the listener blocked like a live socket
+                        loop {
+                            thread::park();
+                        }
+                    }
+                    Err(WmError::Query("socket closed".to_string()))
+                },
+                || {
+                    attempts += 1;
+                    let _ = tx.send(attempts);
+                    if attempts < 3 {
+                        Err(WmError::Query("not running".to_string()))
+                    } else {
+                        Ok(())
+                    }
+                },
+            )
+        });
+
+        let attempts: Vec<_> = rx.iter().take(3).collect();
+        assert_eq!(attempts, [1, 2, 3]);
+
+        let timeout = Duration::from_secs(1);
+        let kinds: Vec<_> = (0..2)
+            .filter_map(|_| subscription.recv_timeout(timeout).map(|e| e.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                WmEventKind::BackendDisconnected,
+                WmEventKind::BackendReconnected
+            ]
+        );
+    }
+}
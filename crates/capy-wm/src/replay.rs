@@ -0,0 +1,100 @@
+//! Recording and replaying event sequences.
+//!
+//! A [`Recorder`] captures the events of a live backend into a [`Recording`],
+//! which can be saved as JSON and replayed later, e.g. against a
+//! [`MockBackend`](crate::mock::MockBackend) in tests.
+
+use crate::events::{EventFilter, subscribe_with};
+use crate::types::{MonitorInfo, WmEvent};
+use crate::{WindowBackend, WmError, WmResult, send_event};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// How often the recorder thread checks whether it was stopped
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A recorded sequence of events together with the monitors present when recording started.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Recording {
+    pub monitors: Vec<MonitorInfo>,
+    pub events: Vec<WmEvent>,
+}
+
+impl Recording {
+    /// Load a recording from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> WmResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| WmError::InvalidRecording(e.to_string()))
+    }
+
+    /// Save this recording as a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> WmResult<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| WmError::InvalidRecording(e.to_string()))?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Send the recorded events in order, updating the shared state like a live backend would.
+    pub fn replay(&self) {
+        for event in &self.events {
+            send_event(event.clone());
+        }
+    }
+}
+
+/// Records events in the background until stopped.
+/// Events are drained continuously, so long recordings do not overflow the subscription.
+pub struct Recorder {
+    monitors: Vec<MonitorInfo>,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Vec<WmEvent>>,
+}
+
+impl Recorder {
+    /// Start recording all events.
+    pub fn start(backend: &dyn WindowBackend) -> WmResult<Self> {
+        Self::start_with(backend, EventFilter::new())
+    }
+
+    /// Start recording the events matching `filter`.
+    pub fn start_with(backend: &dyn WindowBackend, filter: EventFilter) -> WmResult<Self> {
+        let monitors = backend.get_monitor_info()?;
+        let subscription = subscribe_with(filter);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut events = Vec::new();
+            while !stopped.load(Ordering::Relaxed) {
+                if let Some(event) = subscription.recv_timeout(STOP_POLL_INTERVAL) {
+                    events.push(event);
+                }
+            }
+            events.extend(subscription.try_iter());
+            events
+        });
+
+        Ok(Self {
+            monitors,
+            stop,
+            thread,
+        })
+    }
+
+    /// Stop recording and return everything captured so far.
+    pub fn stop(self) -> Recording {
+        self.stop.store(true, Ordering::Relaxed);
+        let events = self.thread.join().unwrap_or_else(|_| {
+            log::error!("Event recorder thread panicked");
+            Vec::new()
+        });
+        Recording {
+            monitors: self.monitors,
+            events,
+        }
+    }
+}
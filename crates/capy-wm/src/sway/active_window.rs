@@ -0,0 +1,80 @@
+//! Active window tracking for Sway.
+
+use crate::types::ActiveWindowInfo;
+use crate::{WmEvent, WmResult, get_state, read, resolve_icon, send_event};
+use log::warn;
+use std::path::Path;
+
+use super::ipc::{GET_OUTPUTS, GET_TREE, SwayConnection, SwayNode, SwayOutput};
+
+/// Name of the focused output, empty if no output has focus.
+pub(crate) fn get_active_monitor_name(socket: &Path) -> WmResult<String> {
+    let outputs: Vec<SwayOutput> = SwayConnection::connect(socket)?.request(GET_OUTPUTS, "")?;
+    Ok(outputs
+        .into_iter()
+        .find(|o| o.focused)
+        .map(|o| o.name)
+        .unwrap_or_default())
+}
+
+/// Focused output for event handlers, where a failed query can only be logged.
+fn focused_monitor(socket: &Path) -> String {
+    get_active_monitor_name(socket).unwrap_or_else(|e| {
+        warn!("Failed to get the focused Sway output: {}", e);
+        String::new()
+    })
+}
+
+fn to_info(node: &SwayNode, focused_monitor: String) -> ActiveWindowInfo {
+    let class = node.app_class();
+    ActiveWindowInfo {
+        address: node.id.to_string(),
+        icon_path: resolve_icon(&class),
+        app: class,
+        window_title: node.name.clone().unwrap_or_default(),
+        focused_monitor,
+    }
+}
+
+/// Initialize active window state from the current Sway tree.
+pub(crate) fn init(socket: &Path) -> WmResult<()> {
+    let tree: SwayNode = SwayConnection::connect(socket)?.request(GET_TREE, "")?;
+    let info = match tree.focused_window() {
+        Some(node) => to_info(node, get_active_monitor_name(socket)?),
+        None => ActiveWindowInfo::default(),
+    };
+    send_event(WmEvent::ActiveWindowChanged(info));
+    Ok(())
+}
+
+/// Set the active window from a Sway `focus` window event.
+pub(crate) fn set(socket: &Path, container: &SwayNode) {
+    let current_address = read(&get_state().active_window).address.clone();
+
+    if container.id.to_string() != current_address {
+        send_event(WmEvent::ActiveWindowChanged(to_info(
+            container,
+            focused_monitor(socket),
+        )));
+    }
+}
+
+/// Update the active window title from a Sway `title` window event.
+pub(crate) fn update_title(container: &SwayNode) {
+    let mut info = read(&get_state().active_window).clone();
+    if container.id.to_string() != info.address {
+        return;
+    }
+
+    info.window_title = container.name.clone().unwrap_or_default();
+    send_event(WmEvent::ActiveWindowChanged(info));
+}
+
+/// Clear the active window when the focused window closes.
+pub(crate) fn clear_if_active(container: &SwayNode) {
+    let is_active = read(&get_state().active_window).address == container.id.to_string();
+
+    if is_active {
+        send_event(WmEvent::ActiveWindowChanged(ActiveWindowInfo::default()));
+    }
+}
@@ -0,0 +1,396 @@
+//! Minimal client for the i3/Sway IPC protocol.
+//!
+//! Every message is framed as `i3-ipc` magic, a native-endian `u32` payload
+//! length, a native-endian `u32` message type and a JSON payload.
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+// Message types
+pub(crate) const RUN_COMMAND: u32 = 0;
+pub(crate) const GET_WORKSPACES: u32 = 1;
+pub(crate) const SUBSCRIBE: u32 = 2;
+pub(crate) const GET_OUTPUTS: u32 = 3;
+pub(crate) const GET_TREE: u32 = 4;
+pub(crate) const GET_BINDING_STATE: u32 = 12;
+pub(crate) const GET_INPUTS: u32 = 100;
+
+// Event types (high bit set)
+pub(crate) const EVENT_WORKSPACE: u32 = 0x8000_0000;
+pub(crate) const EVENT_OUTPUT: u32 = 0x8000_0001;
+pub(crate) const EVENT_MODE: u32 = 0x8000_0002;
+pub(crate) const EVENT_WINDOW: u32 = 0x8000_0003;
+pub(crate) const EVENT_INPUT: u32 = 0x8000_0015;
+
+// Hidden output and workspace holding scratchpad windows in GET_TREE
+const SCRATCH_OUTPUT: &str = "__i3";
+const SCRATCH_WORKSPACE: &str = "__i3_scratch";
+
+/// Resolve the Sway IPC socket path from `SWAYSOCK`.
+pub(crate) fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("SWAYSOCK").map(PathBuf::from)
+}
+
+/// A single connection to the Sway IPC socket.
+pub(crate) struct SwayConnection {
+    stream: UnixStream,
+}
+
+impl SwayConnection {
+    /// Connect to the socket at `path`.
+    pub(crate) fn connect(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            stream: UnixStream::connect(path)?,
+        })
+    }
+
+    /// Send a raw message.
+    pub(crate) fn send(&mut self, msg_type: u32, payload: &[u8]) -> io::Result<()> {
+        self.stream.write_all(&encode(msg_type, payload))
+    }
+
+    /// Read the next message, returning its type and payload.
+    pub(crate) fn read_message(&mut self) -> io::Result<(u32, Vec<u8>)> {
+        read_message(&mut self.stream)
+    }
+
+    /// Send a request and deserialize the JSON reply.
+    pub(crate) fn request<T: DeserializeOwned>(
+        &mut self,
+        msg_type: u32,
+        payload: &str,
+    ) -> io::Result<T> {
+        self.send(msg_type, payload.as_bytes())?;
+        let (_, reply) = self.read_message()?;
+        serde_json::from_slice(&reply).map_err(io::Error::from)
+    }
+
+    /// Subscribe this connection to the given event names.
+    /// After a successful subscription the connection only receives events.
+    pub(crate) fn subscribe(&mut self, events: &[&str]) -> io::Result<()> {
+        let payload = serde_json::to_string(events).map_err(io::Error::from)?;
+        let reply: CommandResult = self.request(SUBSCRIBE, &payload)?;
+        if reply.success {
+            Ok(())
+        } else {
+            Err(io::Error::other("sway rejected the event subscription"))
+        }
+    }
+}
+
+/// Encode a message into the IPC wire format.
+pub(crate) fn encode(msg_type: u32, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + payload.len());
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    buf.extend_from_slice(&msg_type.to_ne_bytes());
+    buf.extend_from_slice(payload);
+    buf
+}
+
+/// Read one framed message from `reader`.
+pub(crate) fn read_message(reader: &mut impl Read) -> io::Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid i3-ipc magic",
+        ));
+    }
+
+    let len = u32::from_ne_bytes(header[6..10].try_into().unwrap()) as usize;
+    let msg_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok((msg_type, payload))
+}
+
+// ============ Reply types ============
+
+/// Reply to RUN_COMMAND (one entry per command) and SUBSCRIBE.
+#[derive(Debug, Deserialize)]
+pub(crate) struct CommandResult {
+    pub success: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Entry of a GET_WORKSPACES reply.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct SwayWorkspace {
+    pub id: i64,
+    pub num: i32,
+    #[serde(default)]
+    pub name: String,
+    pub output: String,
+    pub visible: bool,
+    pub focused: bool,
+    #[serde(default)]
+    pub urgent: bool,
+}
+
+impl SwayWorkspace {
+    /// ID used by capy-wm, see [`workspace_id`].
+    pub(crate) fn workspace_id(&self) -> i32 {
+        workspace_id(self.num, self.id)
+    }
+}
+
+/// Workspace ID used by capy-wm. Named workspaces without a number (`num` -1)
+/// get the negated container ID so they stay distinct.
+pub(crate) fn workspace_id(num: i32, con_id: i64) -> i32 {
+    if num >= 0 { num } else { -(con_id as i32) }
+}
+
+/// Entry of a GET_OUTPUTS reply.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct SwayOutput {
+    pub name: String,
+    #[serde(default)]
+    pub make: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub serial: String,
+    #[serde(default = "default_true")]
+    pub active: bool,
+    #[serde(default)]
+    pub focused: bool,
+    /// Not reported for inactive outputs.
+    #[serde(default)]
+    pub scale: Option<f32>,
+    #[serde(default)]
+    pub transform: Option<String>,
+    #[serde(default)]
+    pub current_mode: Option<SwayMode>,
+    #[serde(default)]
+    pub rect: Rect,
+}
+
+/// Output mode, `refresh` is in mHz.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct SwayMode {
+    pub width: i32,
+    pub height: i32,
+    pub refresh: i32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// X11 properties of an Xwayland window.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct WindowProperties {
+    #[serde(default)]
+    pub class: Option<String>,
+}
+
+/// Position and size of a node.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// A node of the GET_TREE reply (also used as the `container` of window events).
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct SwayNode {
+    pub id: i64,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "type", default)]
+    pub node_type: String,
+    #[serde(default)]
+    pub num: Option<i32>,
+    #[serde(default)]
+    pub focused: bool,
+    #[serde(default)]
+    pub urgent: bool,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub window_properties: Option<WindowProperties>,
+    #[serde(default)]
+    pub pid: Option<i32>,
+    #[serde(default)]
+    pub rect: Rect,
+    /// 0 = none, 1 = output fullscreen, 2 = global fullscreen
+    #[serde(default)]
+    pub fullscreen_mode: u8,
+    #[serde(default)]
+    pub nodes: Vec<SwayNode>,
+    #[serde(default)]
+    pub floating_nodes: Vec<SwayNode>,
+}
+
+impl SwayNode {
+    /// Whether this node is an actual window rather than a split container.
+    pub(crate) fn is_window(&self) -> bool {
+        matches!(self.node_type.as_str(), "con" | "floating_con")
+            && (self.app_id.is_some() || self.window_properties.is_some())
+    }
+
+    /// App identifier: Wayland app_id, falling back to the X11 class.
+    pub(crate) fn app_class(&self) -> String {
+        self.app_id
+            .clone()
+            .or_else(|| {
+                self.window_properties
+                    .as_ref()
+                    .and_then(|p| p.class.clone())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Collect all descendant nodes (tiling and floating), depth first.
+    pub(crate) fn descendants(&self) -> Vec<&SwayNode> {
+        let mut out = Vec::new();
+        let mut stack: Vec<&SwayNode> = self
+            .floating_nodes
+            .iter()
+            .rev()
+            .chain(self.nodes.iter().rev())
+            .collect();
+        while let Some(node) = stack.pop() {
+            out.push(node);
+            stack.extend(node.floating_nodes.iter().rev());
+            stack.extend(node.nodes.iter().rev());
+        }
+        out
+    }
+
+    /// All windows below this node.
+    pub(crate) fn windows(&self) -> Vec<&SwayNode> {
+        self.descendants()
+            .into_iter()
+            .filter(|n| n.is_window())
+            .collect()
+    }
+
+    /// The focused window in this subtree, if any.
+    pub(crate) fn focused_window(&self) -> Option<&SwayNode> {
+        self.descendants()
+            .into_iter()
+            .find(|n| n.focused && n.is_window())
+    }
+
+    /// Workspace ID of a workspace node, see [`workspace_id`].
+    pub(crate) fn workspace_id(&self) -> i32 {
+        workspace_id(self.num.unwrap_or(-1), self.id)
+    }
+
+    /// Whether this is the hidden `__i3` output or its `__i3_scratch` workspace,
+    /// where Sway keeps scratchpad windows while they are not shown.
+    pub(crate) fn is_scratch(&self) -> bool {
+        matches!(
+            self.name.as_deref(),
+            Some(SCRATCH_OUTPUT | SCRATCH_WORKSPACE)
+        )
+    }
+
+    /// Real output nodes of a GET_TREE root, skipping the scratchpad output.
+    pub(crate) fn outputs(&self) -> impl Iterator<Item = &SwayNode> {
+        self.nodes
+            .iter()
+            .filter(|n| n.node_type == "output" && !n.is_scratch())
+    }
+
+    /// All workspace nodes in this subtree, skipping the scratchpad workspace.
+    pub(crate) fn workspaces(&self) -> Vec<&SwayNode> {
+        self.descendants()
+            .into_iter()
+            .filter(|n| n.node_type == "workspace" && !n.is_scratch())
+            .collect()
+    }
+}
+
+/// Payload of a `window` event.
+#[derive(Debug, Deserialize)]
+pub(crate) struct WindowEvent {
+    pub change: String,
+    pub container: SwayNode,
+}
+
+/// Entry of a GET_INPUTS reply (also used as the `input` of input events).
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct SwayInput {
+    pub identifier: String,
+    #[serde(rename = "type", default)]
+    pub input_type: String,
+    #[serde(default)]
+    pub xkb_active_layout_name: Option<String>,
+}
+
+/// Payload of an `input` event.
+#[derive(Debug, Deserialize)]
+pub(crate) struct InputEvent {
+    pub change: String,
+    pub input: SwayInput,
+}
+
+/// Payload of a `mode` event, also the reply to GET_BINDING_STATE.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BindingMode {
+    #[serde(alias = "change")]
+    pub name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_encode_read_roundtrip() {
+        let payload = br#"["workspace"]"#;
+        let encoded = encode(SUBSCRIBE, payload);
+        assert_eq!(&encoded[..6], MAGIC);
+
+        let (msg_type, decoded) = read_message(&mut Cursor::new(encoded)).unwrap();
+        assert_eq!(msg_type, SUBSCRIBE);
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn test_read_rejects_bad_magic() {
+        let mut encoded = encode(GET_TREE, b"{}");
+        encoded[0] = b'x';
+        let err = read_message(&mut Cursor::new(encoded)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tree_windows_include_floating() {
+        let tree: SwayNode = serde_json::from_str(
+            r#"{
+                "id": 1, "type": "root",
+                "nodes": [{
+                    "id": 2, "type": "workspace", "num": 1, "name": "1",
+                    "nodes": [{"id": 10, "type": "con", "app_id": "foot", "name": "shell"}],
+                    "floating_nodes": [{
+                        "id": 11, "type": "floating_con", "focused": true,
+                        "window_properties": {"class": "Steam"}, "name": "Steam"
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let windows = tree.windows();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(tree.workspaces().len(), 1);
+        assert_eq!(tree.focused_window().unwrap().app_class(), "Steam");
+    }
+}
@@ -0,0 +1,64 @@
+//! Keyboard layout and binding mode tracking for Sway.
+//!
+//! Binding modes are Sway's submaps, the `default` mode is reported as an empty submap.
+
+use crate::{WmEvent, WmResult, send_event};
+use log::{debug, warn};
+use std::path::Path;
+
+use super::ipc::{
+    BindingMode, GET_BINDING_STATE, GET_INPUTS, InputEvent, SwayConnection, SwayInput,
+};
+
+/// Publish the active layout of the first keyboard and the current binding mode.
+pub(crate) fn init(socket: &Path) -> WmResult<()> {
+    let mut conn = SwayConnection::connect(socket)?;
+    let inputs: Vec<SwayInput> = conn.request(GET_INPUTS, "")?;
+    match inputs.into_iter().find(|i| i.input_type == "keyboard") {
+        Some(keyboard) => publish_layout(keyboard),
+        None => debug!("No keyboards found from Sway"),
+    }
+
+    let mode: BindingMode = conn.request(GET_BINDING_STATE, "")?;
+    send_event(WmEvent::SubmapChanged(submap_name(mode.name)));
+    Ok(())
+}
+
+/// Handle an `input` event, only layout changes are of interest.
+pub(crate) fn handle_input_event(payload: &[u8]) {
+    let event: InputEvent = match serde_json::from_slice(payload) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("Failed to parse Sway input event: {}", e);
+            return;
+        }
+    };
+    if matches!(event.change.as_str(), "xkb_layout" | "xkb_keymap") {
+        publish_layout(event.input);
+    }
+}
+
+/// Handle a `mode` event.
+pub(crate) fn handle_mode_event(payload: &[u8]) {
+    match serde_json::from_slice::<BindingMode>(payload) {
+        Ok(mode) => send_event(WmEvent::SubmapChanged(submap_name(mode.name))),
+        Err(e) => warn!("Failed to parse Sway mode event: {}", e),
+    }
+}
+
+fn publish_layout(keyboard: SwayInput) {
+    if let Some(layout) = keyboard.xkb_active_layout_name {
+        send_event(WmEvent::KeyboardLayoutChanged {
+            keyboard: keyboard.identifier,
+            layout,
+        });
+    }
+}
+
+fn submap_name(mode: String) -> String {
+    if mode == "default" {
+        String::new()
+    } else {
+        mode
+    }
+}
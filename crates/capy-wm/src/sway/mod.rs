@@ -0,0 +1,676 @@
+//! Sway backend for capy-wm.
+//!
+//! Implements the WindowBackend trait over the i3/Sway IPC socket (`$SWAYSOCK`).
+
+mod active_window;
+mod ipc;
+mod keyboard;
+mod windows;
+mod workspaces;
+
+use crate::types::{MonitorInfo, MonitorTransform};
+use crate::{WindowBackend, WmError, WmEvent, WmResult, send_event, set_urgent, update_window};
+use ipc::{CommandResult, SwayConnection, SwayOutput, SwayWorkspace, WindowEvent};
+use log::{debug, error, info, warn};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Sway window manager backend.
+pub struct SwayBackend {
+    socket: PathBuf,
+}
+
+impl SwayBackend {
+    /// Create a new Sway backend using the socket from `$SWAYSOCK`.
+    pub fn new() -> Self {
+        Self::with_socket(ipc::socket_path().unwrap_or_default())
+    }
+
+    /// Create a Sway backend talking to a specific IPC socket.
+    pub fn with_socket(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    /// Active outputs.
+    fn outputs(&self) -> WmResult<Vec<SwayOutput>> {
+        Ok(self
+            .all_outputs()?
+            .into_iter()
+            .filter(|o| o.active)
+            .collect())
+    }
+
+    /// All outputs, including inactive ones.
+    fn all_outputs(&self) -> WmResult<Vec<SwayOutput>> {
+        let mut conn = SwayConnection::connect(&self.socket)?;
+        Ok(conn.request(ipc::GET_OUTPUTS, "")?)
+    }
+
+    /// Get workspace status for a specific output.
+    pub fn get_workspaces_status(
+        &self,
+        monitor_name: &str,
+    ) -> WmResult<crate::types::WorkspacesStatus> {
+        workspaces::get_status(&self.socket, monitor_name)
+    }
+
+    /// Run a Sway command, e.g. `workspace number 3`.
+    fn run_command(&self, command: &str) -> WmResult<()> {
+        let mut conn = SwayConnection::connect(&self.socket)?;
+        let results: Vec<CommandResult> = conn.request(ipc::RUN_COMMAND, command)?;
+        match results.into_iter().find(|r| !r.success) {
+            Some(failed) => Err(WmError::Dispatch(
+                failed.error.unwrap_or_else(|| "command failed".to_string()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Command argument addressing a workspace: by number, or by name for named workspaces.
+    fn workspace_target(&self, workspace_id: i32) -> WmResult<String> {
+        if workspace_id >= 0 {
+            return Ok(format!("number {}", workspace_id));
+        }
+
+        let mut conn = SwayConnection::connect(&self.socket)?;
+        let workspaces: Vec<SwayWorkspace> = conn.request(ipc::GET_WORKSPACES, "")?;
+        workspaces
+            .into_iter()
+            .find(|ws| ws.workspace_id() == workspace_id)
+            .map(|ws| format!("\"{}\"", ws.name.replace('"', "\\\"")))
+            .ok_or_else(|| WmError::Dispatch(format!("unknown workspace {}", workspace_id)))
+    }
+
+    /// Run an `output <name> ...` command.
+    fn run_on_output(&self, name: &str, command: &str) -> WmResult<()> {
+        self.run_command(&format!("output \"{}\" {}", name, command))
+    }
+
+    /// Run a Sway command on the container with the given ID.
+    fn run_on_window(&self, address: &str, command: &str) -> WmResult<()> {
+        self.run_command(&format!("[con_id={}] {}", address, command))
+    }
+}
+
+impl Default for SwayBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowBackend for SwayBackend {
+    fn get_active_monitor(&self) -> WmResult<String> {
+        active_window::get_active_monitor_name(&self.socket)
+    }
+
+    fn get_monitors(&self) -> WmResult<Vec<String>> {
+        Ok(self.outputs()?.into_iter().map(|o| o.name).collect())
+    }
+
+    fn get_monitor_info(&self) -> WmResult<Vec<MonitorInfo>> {
+        Ok(self.all_outputs()?.into_iter().map(monitor_info).collect())
+    }
+
+    fn set_monitor_mode(
+        &self,
+        name: &str,
+        width: i32,
+        height: i32,
+        refresh_rate: f32,
+    ) -> WmResult<()> {
+        self.run_on_output(
+            name,
+            &format!("mode {}x{}@{:.3}Hz", width, height, refresh_rate),
+        )
+    }
+
+    fn set_monitor_position(&self, name: &str, x: i32, y: i32) -> WmResult<()> {
+        self.run_on_output(name, &format!("position {} {}", x, y))
+    }
+
+    fn set_monitor_scale(&self, name: &str, scale: f32) -> WmResult<()> {
+        self.run_on_output(name, &format!("scale {}", scale))
+    }
+
+    fn set_monitor_transform(&self, name: &str, transform: MonitorTransform) -> WmResult<()> {
+        self.run_on_output(name, &format!("transform {}", transform.name()))
+    }
+
+    fn set_monitor_enabled(&self, name: &str, enabled: bool) -> WmResult<()> {
+        self.run_on_output(name, if enabled { "enable" } else { "disable" })
+    }
+
+    fn switch_workspace(&self, workspace_id: i32) -> WmResult<()> {
+        let target = self.workspace_target(workspace_id)?;
+        self.run_command(&format!("workspace {}", target))
+    }
+
+    fn close_window(&self, address: &str) -> WmResult<()> {
+        self.run_on_window(address, "kill")
+    }
+
+    fn focus_window(&self, address: &str) -> WmResult<()> {
+        self.run_on_window(address, "focus")
+    }
+
+    fn move_window_to_workspace(&self, address: &str, workspace_id: i32) -> WmResult<()> {
+        let target = self.workspace_target(workspace_id)?;
+        self.run_on_window(address, &format!("move container to workspace {}", target))
+    }
+
+    fn toggle_floating(&self, address: &str) -> WmResult<()> {
+        self.run_on_window(address, "floating toggle")
+    }
+
+    fn toggle_fullscreen(&self, address: &str) -> WmResult<()> {
+        self.run_on_window(address, "fullscreen toggle")
+    }
+
+    fn toggle_pin(&self, address: &str) -> WmResult<()> {
+        self.run_on_window(address, "sticky toggle")
+    }
+
+    fn swap_windows(&self, address: &str, other: &str) -> WmResult<()> {
+        self.run_on_window(address, &format!("swap container with con_id {}", other))
+    }
+
+    /// Sway has a single unnamed scratchpad instead of named special workspaces,
+    /// so only the default name ("", "special" or "scratchpad") is accepted.
+    fn toggle_special_workspace(&self, name: &str) -> WmResult<()> {
+        match name.strip_prefix("special:").unwrap_or(name) {
+            "" | "special" | "scratchpad" => self.run_command("scratchpad show"),
+            _ => Err(WmError::Unsupported("toggle_special_workspace")),
+        }
+    }
+
+    fn cycle_keyboard_layout(&self) -> WmResult<()> {
+        self.run_command("input type:keyboard xkb_switch_layout next")
+    }
+
+    fn start_listener(&self) -> WmResult<()> {
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            info!("Sway listener already running");
+            return Ok(());
+        }
+
+        info!("Starting Sway event listener...");
+
+        // Initialize active window, workspace and keyboard state
+        let initialized = self
+            .init_active_window()
+            .and_then(|()| workspaces::send_updates_to_all_monitors(&self.socket))
+            .and_then(|()| keyboard::init(&self.socket))
+            .and_then(|()| self.get_monitors());
+        let known_outputs: HashSet<String> = match initialized {
+            Ok(monitors) => monitors.into_iter().collect(),
+            Err(e) => {
+                RUNNING.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        };
+
+        let socket = self.socket.clone();
+        thread::spawn(move || {
+            if let Err(e) = listen(&socket, known_outputs) {
+                error!("Sway listener failed: {}", e);
+            }
+            RUNNING.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    fn trigger_refresh(&self) -> WmResult<()> {
+        info!("Triggering Sway state refresh...");
+        workspaces::send_updates_to_all_monitors(&self.socket)?;
+        keyboard::init(&self.socket)?;
+        self.init_active_window()
+    }
+
+    fn init_active_window(&self) -> WmResult<()> {
+        active_window::init(&self.socket)
+    }
+}
+
+/// Subscribe to Sway events and dispatch them until the socket closes.
+fn listen(socket: &Path, mut known_outputs: HashSet<String>) -> std::io::Result<()> {
+    let mut conn = SwayConnection::connect(socket)?;
+    conn.subscribe(&["workspace", "window", "output", "mode", "input"])?;
+    info!("Sway event listener active");
+
+    loop {
+        let (msg_type, payload) = conn.read_message()?;
+        handle_event(socket, msg_type, &payload, &mut known_outputs);
+    }
+}
+
+/// Convert a GET_OUTPUTS entry into monitor info.
+fn monitor_info(output: SwayOutput) -> MonitorInfo {
+    let mode = output.current_mode.unwrap_or_default();
+    MonitorInfo {
+        description: [&output.make, &output.model, &output.serial]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        make: output.make,
+        model: output.model,
+        width: mode.width,
+        height: mode.height,
+        refresh_rate: mode.refresh as f32 / 1000.0,
+        scale: output.scale.unwrap_or(1.0),
+        transform: output
+            .transform
+            .as_deref()
+            .and_then(MonitorTransform::from_name)
+            .unwrap_or_default(),
+        x: output.rect.x,
+        y: output.rect.y,
+        focused: output.focused,
+        disabled: !output.active,
+        name: output.name,
+    }
+}
+
+/// Republish workspaces from an event handler, where errors can only be logged.
+fn refresh_workspaces(socket: &Path) {
+    if let Err(e) = workspaces::send_updates_to_all_monitors(socket) {
+        warn!("Failed to refresh Sway workspaces: {}", e);
+    }
+}
+
+/// Handle a single Sway event.
+fn handle_event(socket: &Path, msg_type: u32, payload: &[u8], known_outputs: &mut HashSet<String>) {
+    match msg_type {
+        ipc::EVENT_WORKSPACE => {
+            debug!("Workspace event");
+            refresh_workspaces(socket);
+        }
+        ipc::EVENT_WINDOW => {
+            let event: WindowEvent = match serde_json::from_slice(payload) {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Failed to parse Sway window event: {}", e);
+                    return;
+                }
+            };
+            debug!("Window event: {} ({})", event.change, event.container.id);
+
+            match event.change.as_str() {
+                "focus" => {
+                    active_window::set(socket, &event.container);
+                    refresh_workspaces(socket);
+                }
+                "title" => {
+                    let title = event.container.name.clone().unwrap_or_default();
+                    update_window(&event.container.id.to_string(), |w| w.title = title);
+                    active_window::update_title(&event.container);
+                }
+                "close" => {
+                    active_window::clear_if_active(&event.container);
+                    refresh_workspaces(socket);
+                }
+                "urgent" => {
+                    set_urgent(&event.container.id.to_string(), event.container.urgent);
+                    refresh_workspaces(socket);
+                }
+                "new" | "move" | "floating" | "fullscreen_mode" => refresh_workspaces(socket),
+                _ => {}
+            }
+        }
+        ipc::EVENT_OUTPUT => {
+            // Output events carry no details, diff against the known outputs instead
+            let current: HashSet<String> = match SwayBackend::with_socket(socket).get_monitors() {
+                Ok(monitors) => monitors.into_iter().collect(),
+                Err(e) => {
+                    warn!("Failed to get Sway outputs: {}", e);
+                    return;
+                }
+            };
+
+            for name in current.difference(known_outputs) {
+                debug!("Monitor added: {}", name);
+                send_event(WmEvent::MonitorAdded(name.clone()));
+            }
+            for name in known_outputs.difference(&current) {
+                debug!("Monitor removed: {}", name);
+                send_event(WmEvent::MonitorRemoved(name.clone()));
+            }
+            *known_outputs = current;
+        }
+        ipc::EVENT_MODE => keyboard::handle_mode_event(payload),
+        ipc::EVENT_INPUT => keyboard::handle_input_event(payload),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventFilter, WmEventKind, WorkspaceState, subscribe_with};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::{Arc, Mutex};
+
+    const OUTPUTS: &str = r#"[
+        {"name": "SWAY-FAKE-1", "make": "Dell", "model": "U2720Q", "serial": "ABC", "active": true, "focused": true,
+         "scale": 1.5, "transform": "90", "rect": {"x": 0, "y": 0, "width": 1440, "height": 2560},
+         "current_mode": {"width": 3840, "height": 2160, "refresh": 59997}},
+        {"name": "SWAY-FAKE-2", "active": true, "focused": false},
+        {"name": "SWAY-OFF", "active": false, "focused": false}
+    ]"#;
+
+    const WORKSPACES: &str = r#"[
+        {"id": 3, "num": 1, "name": "1", "output": "SWAY-FAKE-1", "visible": true, "focused": true, "urgent": false},
+        {"id": 4, "num": 3, "name": "3", "output": "SWAY-FAKE-1", "visible": false, "focused": false, "urgent": true},
+        {"id": 6, "num": 11, "name": "11", "output": "SWAY-FAKE-2", "visible": true, "focused": false, "urgent": false},
+        {"id": 7, "num": -1, "name": "mail", "output": "SWAY-FAKE-2", "visible": false, "focused": false, "urgent": false}
+    ]"#;
+
+    const TREE: &str = r#"{
+        "id": 1, "type": "root",
+        "nodes": [
+            {"id": 8, "type": "output", "name": "__i3", "nodes": [
+                {"id": 9, "type": "workspace", "num": -1, "name": "__i3_scratch", "floating_nodes": [
+                    {"id": 23, "type": "floating_con", "app_id": "keepassxc", "name": "Passwords"}
+                ]}
+            ]},
+            {"id": 2, "type": "output", "name": "SWAY-FAKE-1", "nodes": [
+                {"id": 3, "type": "workspace", "num": 1, "name": "1", "nodes": [
+                    {"id": 20, "type": "con", "app_id": "foot", "name": "~", "focused": true}
+                ]},
+                {"id": 4, "type": "workspace", "num": 3, "name": "3", "nodes": [
+                    {"id": 21, "type": "con", "window_properties": {"class": "discord"}, "name": "Discord", "urgent": true}
+                ]}
+            ]},
+            {"id": 5, "type": "output", "name": "SWAY-FAKE-2", "nodes": [
+                {"id": 6, "type": "workspace", "num": 11, "name": "11", "nodes": []},
+                {"id": 7, "type": "workspace", "num": -1, "name": "mail", "nodes": [
+                    {"id": 22, "type": "con", "app_id": "thunderbird", "name": "Inbox"}
+                ]}
+            ]}
+        ]
+    }"#;
+
+    const INPUTS: &str = r#"[
+        {"identifier": "1:1:Mouse", "type": "pointer"},
+        {"identifier": "1:1:AT_Keyboard", "type": "keyboard", "xkb_active_layout_name": "English (US)"}
+    ]"#;
+
+    /// A fake Sway IPC server answering queries with canned replies.
+    struct FakeSway {
+        path: PathBuf,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeSway {
+        fn start(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "capy-wm-sway-{}-{}.sock",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            let commands = Arc::new(Mutex::new(Vec::new()));
+
+            let commands_clone = commands.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let commands = commands_clone.clone();
+                    thread::spawn(move || serve(stream, commands));
+                }
+            });
+
+            Self { path, commands }
+        }
+    }
+
+    impl Drop for FakeSway {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn serve(mut stream: UnixStream, commands: Arc<Mutex<Vec<String>>>) {
+        use std::io::Write;
+
+        while let Ok((msg_type, payload)) = ipc::read_message(&mut stream) {
+            let reply = match msg_type {
+                ipc::RUN_COMMAND => {
+                    commands
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8(payload).unwrap());
+                    r#"[{"success": true}]"#
+                }
+                ipc::GET_WORKSPACES => WORKSPACES,
+                ipc::GET_OUTPUTS => OUTPUTS,
+                ipc::GET_TREE => TREE,
+                ipc::GET_INPUTS => INPUTS,
+                ipc::GET_BINDING_STATE => r#"{"name": "resize"}"#,
+                ipc::SUBSCRIBE => r#"{"success": true}"#,
+                _ => r#"{"success": false}"#,
+            };
+            if stream
+                .write_all(&ipc::encode(msg_type, reply.as_bytes()))
+                .is_err()
+            {
+                break;
+            }
+
+            if msg_type == ipc::SUBSCRIBE {
+                let event = r#"{"change": "focus", "container": {"id": 21, "type": "con", "app_id": "discord", "name": "Discord"}}"#;
+                let _ = stream.write_all(&ipc::encode(ipc::EVENT_WINDOW, event.as_bytes()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_monitors_skip_inactive_outputs() {
+        let server = FakeSway::start("monitors");
+        let backend = SwayBackend::with_socket(&server.path);
+
+        assert_eq!(
+            backend.get_monitors().unwrap(),
+            vec!["SWAY-FAKE-1", "SWAY-FAKE-2"]
+        );
+        assert_eq!(backend.get_active_monitor().unwrap(), "SWAY-FAKE-1");
+    }
+
+    #[test]
+    fn test_monitor_info_and_configuration() {
+        let server = FakeSway::start("outputs");
+        let backend = SwayBackend::with_socket(&server.path);
+
+        let monitors = backend.get_monitor_info().unwrap();
+        assert_eq!(monitors.len(), 3);
+        let dell = &monitors[0];
+        assert_eq!(dell.description, "Dell U2720Q ABC");
+        assert_eq!((dell.width, dell.height), (3840, 2160));
+        assert!((dell.refresh_rate - 59.997).abs() < 0.001);
+        assert_eq!(dell.scale, 1.5);
+        assert_eq!(dell.transform, MonitorTransform::Rotate90);
+        assert!(dell.focused);
+        assert!(monitors[2].disabled);
+
+        backend
+            .set_monitor_mode("SWAY-FAKE-2", 1920, 1080, 60.0)
+            .unwrap();
+        backend
+            .set_monitor_transform("SWAY-FAKE-2", MonitorTransform::Flipped90)
+            .unwrap();
+        backend.set_monitor_enabled("SWAY-OFF", true).unwrap();
+
+        assert_eq!(
+            server.commands.lock().unwrap().as_slice(),
+            [
+                "output \"SWAY-FAKE-2\" mode 1920x1080@60.000Hz",
+                "output \"SWAY-FAKE-2\" transform flipped-90",
+                "output \"SWAY-OFF\" enable"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_switch_workspace_runs_command() {
+        let server = FakeSway::start("switch");
+        let backend = SwayBackend::with_socket(&server.path);
+
+        backend.switch_workspace(12).unwrap();
+        backend.switch_workspace(-7).unwrap();
+        assert!(backend.switch_workspace(-99).is_err());
+
+        assert_eq!(
+            server.commands.lock().unwrap().as_slice(),
+            ["workspace number 12", "workspace \"mail\""]
+        );
+    }
+
+    #[test]
+    fn test_window_actions_target_container() {
+        let server = FakeSway::start("actions");
+        let backend = SwayBackend::with_socket(&server.path);
+
+        backend.move_window_to_workspace("42", 3).unwrap();
+        backend.swap_windows("42", "43").unwrap();
+
+        assert_eq!(
+            server.commands.lock().unwrap().as_slice(),
+            [
+                "[con_id=42] move container to workspace number 3",
+                "[con_id=42] swap container with con_id 43"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scratchpad_toggle_only_accepts_default_name() {
+        let server = FakeSway::start("scratchpad");
+        let backend = SwayBackend::with_socket(&server.path);
+
+        backend.toggle_special_workspace("special").unwrap();
+        backend.toggle_special_workspace("").unwrap();
+        assert!(matches!(
+            backend.toggle_special_workspace("special:music"),
+            Err(WmError::Unsupported(_))
+        ));
+
+        assert_eq!(
+            server.commands.lock().unwrap().as_slice(),
+            ["scratchpad show", "scratchpad show"]
+        );
+    }
+
+    #[test]
+    fn test_status_per_output() {
+        let server = FakeSway::start("status");
+
+        let first = workspaces::get_status(&server.path, "SWAY-FAKE-1").unwrap();
+        assert_eq!(
+            first.workspaces.len(),
+            crate::layout::DEFAULT_WORKSPACES_PER_MONITOR as usize
+        );
+        assert_eq!(first.workspaces[0].state, WorkspaceState::Active);
+        assert!(first.workspaces[0].occupied);
+        assert_eq!(first.workspaces[0].app_class.as_deref(), Some("foot"));
+        assert_eq!(first.workspaces[1].state, WorkspaceState::Empty);
+        assert_eq!(first.workspaces[2].state, WorkspaceState::Attention);
+        assert_eq!(first.workspaces[2].app_class.as_deref(), Some("discord"));
+
+        let second = workspaces::get_status(&server.path, "SWAY-FAKE-2").unwrap();
+        assert_eq!(second.workspaces[0].absolute_id, 11);
+        assert_eq!(second.workspaces[0].id, 1);
+        assert_eq!(second.workspaces[0].state, WorkspaceState::Visible);
+        assert!(!second.workspaces[0].occupied);
+
+        // Named workspaces follow the numbered ones
+        let named = second.workspaces.last().unwrap();
+        assert_eq!(named.absolute_id, -7);
+        assert_eq!(named.name, "mail");
+        assert_eq!(named.kind, crate::WorkspaceKind::Named);
+        assert_eq!(named.app_class.as_deref(), Some("thunderbird"));
+    }
+
+    #[test]
+    fn test_subscription_receives_events() {
+        let server = FakeSway::start("subscribe");
+
+        let mut conn = SwayConnection::connect(&server.path).unwrap();
+        conn.subscribe(&["window"]).unwrap();
+
+        let (msg_type, payload) = conn.read_message().unwrap();
+        assert_eq!(msg_type, ipc::EVENT_WINDOW);
+
+        let event: WindowEvent = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(event.change, "focus");
+        assert_eq!(event.container.app_class(), "discord");
+    }
+
+    #[test]
+    fn test_workspace_event_updates_state() {
+        let server = FakeSway::start("events");
+        let mut known: HashSet<String> = HashSet::new();
+
+        handle_event(&server.path, ipc::EVENT_WORKSPACE, b"{}", &mut known);
+        let status = crate::get_workspaces_status("SWAY-FAKE-2");
+        // Fixed workspaces plus the named "mail" workspace
+        assert_eq!(
+            status.workspaces.len(),
+            crate::layout::DEFAULT_WORKSPACES_PER_MONITOR as usize + 1
+        );
+
+        handle_event(&server.path, ipc::EVENT_OUTPUT, b"{}", &mut known);
+        assert!(known.contains("SWAY-FAKE-1"));
+        assert!(!known.contains("SWAY-OFF"));
+    }
+
+    #[test]
+    fn test_keyboard_layout_and_mode_tracking() {
+        let server = FakeSway::start("keyboard");
+        let backend = SwayBackend::with_socket(&server.path);
+        let subscription = subscribe_with(EventFilter::new().kinds([
+            WmEventKind::KeyboardLayoutChanged,
+            WmEventKind::SubmapChanged,
+        ]));
+
+        keyboard::init(&server.path).unwrap();
+        let mut known: HashSet<String> = HashSet::new();
+        let layout = br#"{"change": "xkb_layout", "input": {"identifier": "1:1:AT_Keyboard", "type": "keyboard", "xkb_active_layout_name": "German"}}"#;
+        handle_event(&server.path, ipc::EVENT_INPUT, layout, &mut known);
+        let added =
+            br#"{"change": "added", "input": {"identifier": "2:2:Pad", "type": "keyboard"}}"#;
+        handle_event(&server.path, ipc::EVENT_INPUT, added, &mut known);
+        handle_event(
+            &server.path,
+            ipc::EVENT_MODE,
+            br#"{"change": "default"}"#,
+            &mut known,
+        );
+
+        let events: Vec<_> = subscription
+            .try_iter()
+            .map(|event| match event {
+                WmEvent::KeyboardLayoutChanged { layout, .. } => layout,
+                WmEvent::SubmapChanged(name) => format!("submap {}", name),
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            ["English (US)", "submap resize", "German", "submap "]
+        );
+
+        backend.cycle_keyboard_layout().unwrap();
+        assert_eq!(
+            server.commands.lock().unwrap().as_slice(),
+            ["input type:keyboard xkb_switch_layout next"]
+        );
+    }
+}
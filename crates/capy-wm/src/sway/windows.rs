@@ -0,0 +1,94 @@
+//! Window list tracking for Sway.
+
+use crate::resolve_icon;
+use crate::types::{WindowGeometry, WindowInfo};
+
+use super::ipc::SwayNode;
+
+/// Build the window list from a GET_TREE reply.
+pub(crate) fn build_windows(tree: &SwayNode) -> Vec<WindowInfo> {
+    let mut windows = Vec::new();
+
+    for output in tree.outputs() {
+        let monitor = output.name.clone().unwrap_or_default();
+
+        for workspace in output.nodes.iter().filter(|n| n.node_type == "workspace") {
+            let workspace_id = workspace.workspace_id();
+            let floating: Vec<i64> = workspace
+                .floating_nodes
+                .iter()
+                .flat_map(|n| std::iter::once(n).chain(n.descendants()))
+                .map(|n| n.id)
+                .collect();
+
+            for node in workspace.windows() {
+                let class = node.app_class();
+                windows.push(WindowInfo {
+                    address: node.id.to_string(),
+                    icon_path: resolve_icon(&class),
+                    initial_class: class.clone(),
+                    class,
+                    title: node.name.clone().unwrap_or_default(),
+                    workspace_id,
+                    monitor: monitor.clone(),
+                    floating: floating.contains(&node.id),
+                    fullscreen: node.fullscreen_mode != 0,
+                    pid: node.pid,
+                    geometry: WindowGeometry {
+                        x: node.rect.x,
+                        y: node.rect.y,
+                        width: node.rect.width,
+                        height: node.rect.height,
+                    },
+                });
+            }
+        }
+    }
+
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_carry_output_and_floating() {
+        let tree: SwayNode = serde_json::from_str(
+            r#"{
+                "id": 1, "type": "root",
+                "nodes": [
+                    {"id": 5, "type": "output", "name": "__i3", "nodes": [{
+                        "id": 6, "type": "workspace", "num": -1, "name": "__i3_scratch",
+                        "floating_nodes": [{"id": 12, "type": "floating_con",
+                                            "app_id": "keepassxc", "name": "Passwords"}]
+                    }]},
+                    {"id": 2, "type": "output", "name": "DP-1", "nodes": [{
+                        "id": 3, "type": "workspace", "num": 4, "name": "4",
+                        "nodes": [{"id": 10, "type": "con", "app_id": "foot", "name": "shell",
+                                   "pid": 300, "fullscreen_mode": 1,
+                                   "rect": {"x": 0, "y": 30, "width": 1920, "height": 1050}}],
+                        "floating_nodes": [{"id": 11, "type": "floating_con",
+                                            "window_properties": {"class": "Steam"}, "name": "Steam"}]
+                    }]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        // Hidden scratchpad windows are not listed
+        let windows = build_windows(&tree);
+        assert_eq!(windows.len(), 2);
+
+        let foot = &windows[0];
+        assert_eq!(foot.address, "10");
+        assert_eq!(foot.monitor, "DP-1");
+        assert_eq!(foot.workspace_id, 4);
+        assert_eq!(foot.pid, Some(300));
+        assert_eq!(foot.geometry.height, 1050);
+        assert!(foot.fullscreen && !foot.floating);
+
+        assert_eq!(windows[1].class, "Steam");
+        assert!(windows[1].floating);
+    }
+}
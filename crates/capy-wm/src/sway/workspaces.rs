@@ -0,0 +1,99 @@
+//! Workspace management for Sway.
+
+use crate::layout::{self, ClientSnapshot, LayoutSnapshot, MonitorSnapshot, WorkspaceSnapshot};
+use crate::types::WorkspacesStatus;
+use crate::{WmResult, sync_windows};
+use log::debug;
+use std::path::Path;
+
+use super::ipc::{
+    GET_OUTPUTS, GET_TREE, GET_WORKSPACES, SwayConnection, SwayNode, SwayOutput, SwayWorkspace,
+};
+use super::windows::build_windows;
+
+/// Get workspace status for a specific output.
+pub fn get_status(socket: &Path, monitor_name: &str) -> WmResult<WorkspacesStatus> {
+    let mut conn = SwayConnection::connect(socket)?;
+    let outputs: Vec<SwayOutput> = conn.request(GET_OUTPUTS, "")?;
+    let workspaces: Vec<SwayWorkspace> = conn.request(GET_WORKSPACES, "")?;
+    let tree: SwayNode = conn.request(GET_TREE, "")?;
+
+    Ok(layout::build_status(
+        &snapshot(&outputs, &workspaces, &tree),
+        monitor_name,
+    ))
+}
+
+/// Convert raw IPC replies into a layout snapshot.
+/// Sway has no IPC query for `workspace <n> output <name>` rules, so none are reported.
+fn snapshot(
+    outputs: &[SwayOutput],
+    all_workspaces: &[SwayWorkspace],
+    tree: &SwayNode,
+) -> LayoutSnapshot {
+    let monitors = outputs
+        .iter()
+        .filter(|o| o.active)
+        .map(|o| MonitorSnapshot {
+            name: o.name.clone(),
+            // Sway reports visibility per output
+            active_workspace: all_workspaces
+                .iter()
+                .find(|ws| ws.output == o.name && ws.visible)
+                .map(|ws| ws.workspace_id()),
+            // The scratchpad is not a workspace, so it is never shown as one
+            active_special: None,
+            focused: all_workspaces
+                .iter()
+                .any(|ws| ws.output == o.name && ws.focused),
+        })
+        .collect();
+
+    let workspaces = all_workspaces
+        .iter()
+        .map(|ws| WorkspaceSnapshot {
+            id: ws.workspace_id(),
+            name: ws.name.clone(),
+            monitor: ws.output.clone(),
+            urgent: ws.urgent,
+        })
+        .collect();
+
+    let clients = tree
+        .workspaces()
+        .into_iter()
+        .flat_map(|ws| {
+            let workspace_id = ws.workspace_id();
+            ws.windows().into_iter().map(move |w| ClientSnapshot {
+                class: w.app_class(),
+                workspace_id,
+                urgent: w.urgent,
+            })
+        })
+        .collect();
+
+    LayoutSnapshot {
+        monitors,
+        workspaces,
+        clients,
+        rules: Vec::new(),
+    }
+}
+
+/// Send workspace updates to all outputs.
+pub(crate) fn send_updates_to_all_monitors(socket: &Path) -> WmResult<()> {
+    let mut conn = SwayConnection::connect(socket)?;
+    let outputs: Vec<SwayOutput> = conn.request(GET_OUTPUTS, "")?;
+    if outputs.iter().all(|o| !o.active) {
+        debug!("No outputs found from Sway");
+        return Ok(());
+    }
+
+    let workspaces: Vec<SwayWorkspace> = conn.request(GET_WORKSPACES, "")?;
+    let tree: SwayNode = conn.request(GET_TREE, "")?;
+
+    layout::publish_changes(&snapshot(&outputs, &workspaces, &tree));
+
+    sync_windows(build_windows(&tree));
+    Ok(())
+}
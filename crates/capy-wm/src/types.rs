@@ -5,6 +5,7 @@ use std::path::PathBuf;
 /// Detected window manager type.
 /// More will come soon (define soon)
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WmType {
     Hyprland,
     Sway,
@@ -25,6 +26,7 @@ impl std::fmt::Display for WmType {
 
 /// State of a workspace in the taskbar.
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WorkspaceState {
     #[default]
     Empty,
@@ -33,13 +35,45 @@ pub enum WorkspaceState {
     Attention,
 }
 
+/// Kind of workspace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorkspaceKind {
+    /// Regular numbered workspace.
+    #[default]
+    Normal,
+    /// Workspace with a custom name.
+    Named,
+    /// Special workspace (scratchpad) toggled on top of the regular one.
+    Special,
+}
+
+impl WorkspaceKind {
+    /// Derive the kind from a workspace ID and name as reported by the WM.
+    /// Special workspaces are named `special` or `special:<name>`.
+    pub fn from_id_and_name(id: i32, name: &str) -> Self {
+        if name == "special" || name.starts_with("special:") {
+            WorkspaceKind::Special
+        } else if !name.is_empty() && name != id.to_string() {
+            WorkspaceKind::Named
+        } else {
+            WorkspaceKind::Normal
+        }
+    }
+}
+
 /// Information about a single workspace.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WorkspaceInfo {
     /// Relative workspace ID (1-... for display).
     pub id: i32,
-    /// Absolute workspace ID used by the WM.
+    /// Absolute workspace ID used by the WM. Named and special workspaces can be negative.
     pub absolute_id: i32,
+    /// Workspace name as reported by the WM.
+    pub name: String,
+    /// Normal, named or special workspace.
+    pub kind: WorkspaceKind,
     /// Current state of the workspace.
     pub state: WorkspaceState,
     /// Path to the icon for the primary app on this workspace.
@@ -51,44 +85,182 @@ pub struct WorkspaceInfo {
 }
 
 /// Workspace status for a specific monitor.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WorkspacesStatus {
     /// Name of the monitor.
     pub monitor_name: String,
     /// List of workspaces for this monitor.
     pub workspaces: Vec<WorkspaceInfo>,
+    /// Special workspaces (scratchpads) on this monitor.
+    pub special: Vec<WorkspaceInfo>,
+}
+
+impl WorkspacesStatus {
+    /// Name of the special workspace currently shown on this monitor, if any.
+    pub fn active_special(&self) -> Option<&str> {
+        self.special
+            .iter()
+            .find(|ws| matches!(ws.state, WorkspaceState::Active | WorkspaceState::Visible))
+            .map(|ws| ws.name.as_str())
+    }
 }
 
-impl Default for WorkspacesStatus {
-    fn default() -> Self {
-        Self {
-            monitor_name: String::new(),
-            workspaces: Vec::new(),
+/// Position and size of a window in layout coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Rotation and flip of a monitor, counter-clockwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MonitorTransform {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+impl MonitorTransform {
+    /// All transforms in Wayland `wl_output.transform` order.
+    pub const ALL: [MonitorTransform; 8] = [
+        MonitorTransform::Normal,
+        MonitorTransform::Rotate90,
+        MonitorTransform::Rotate180,
+        MonitorTransform::Rotate270,
+        MonitorTransform::Flipped,
+        MonitorTransform::Flipped90,
+        MonitorTransform::Flipped180,
+        MonitorTransform::Flipped270,
+    ];
+
+    /// `wl_output.transform` value, also used by Hyprland.
+    pub fn to_wayland(self) -> u8 {
+        Self::ALL
+            .iter()
+            .position(|t| *t == self)
+            .unwrap_or_default() as u8
+    }
+
+    /// Transform from a `wl_output.transform` value.
+    pub fn from_wayland(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    /// Parse the names used by Sway and niri ("normal", "90", "flipped-90", "Flipped90", ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "normal" | "0" => Some(MonitorTransform::Normal),
+            "90" => Some(MonitorTransform::Rotate90),
+            "180" => Some(MonitorTransform::Rotate180),
+            "270" => Some(MonitorTransform::Rotate270),
+            "flipped" => Some(MonitorTransform::Flipped),
+            "flipped90" => Some(MonitorTransform::Flipped90),
+            "flipped180" => Some(MonitorTransform::Flipped180),
+            "flipped270" => Some(MonitorTransform::Flipped270),
+            _ => None,
+        }
+    }
+
+    /// Name as accepted by Sway's `output transform` command.
+    pub fn name(self) -> &'static str {
+        match self {
+            MonitorTransform::Normal => "normal",
+            MonitorTransform::Rotate90 => "90",
+            MonitorTransform::Rotate180 => "180",
+            MonitorTransform::Rotate270 => "270",
+            MonitorTransform::Flipped => "flipped",
+            MonitorTransform::Flipped90 => "flipped-90",
+            MonitorTransform::Flipped180 => "flipped-180",
+            MonitorTransform::Flipped270 => "flipped-270",
+        }
+    }
+
+    /// Name as accepted by niri's `Transform` output action.
+    pub fn niri_name(self) -> &'static str {
+        match self {
+            MonitorTransform::Normal => "Normal",
+            MonitorTransform::Rotate90 => "90",
+            MonitorTransform::Rotate180 => "180",
+            MonitorTransform::Rotate270 => "270",
+            MonitorTransform::Flipped => "Flipped",
+            MonitorTransform::Flipped90 => "Flipped90",
+            MonitorTransform::Flipped180 => "Flipped180",
+            MonitorTransform::Flipped270 => "Flipped270",
         }
     }
 }
 
+/// Information about a monitor.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonitorInfo {
+    /// Connector name (e.g. "DP-1").
+    pub name: String,
+    /// Human readable description, usually make, model and serial.
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    /// Resolution of the current mode in pixels.
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate of the current mode in Hz.
+    pub refresh_rate: f32,
+    pub scale: f32,
+    pub transform: MonitorTransform,
+    /// Position in the global layout.
+    pub x: i32,
+    pub y: i32,
+    pub focused: bool,
+    pub disabled: bool,
+}
+
 /// Information about a window.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WindowInfo {
-    /// Unique window ID.
-    pub id: String,
+    /// Unique address of the window.
+    pub address: String,
     /// Window class (app identifier).
     pub class: String,
     /// Window title.
     pub title: String,
     /// Initial class when the window was created.
     pub initial_class: String,
-    /// Workspace the window is on.
-    pub workspace: String,
-    /// Whether this window is currently active.
-    pub is_active: bool,
+    /// Absolute ID of the workspace the window is on.
+    pub workspace_id: i32,
+    /// Name of the monitor the window is on.
+    pub monitor: String,
+    /// Whether the window is floating.
+    pub floating: bool,
+    /// Whether the window is fullscreen.
+    pub fullscreen: bool,
+    /// Process ID of the window's client, if known.
+    pub pid: Option<i32>,
+    /// Window position and size.
+    pub geometry: WindowGeometry,
     /// Path to the app icon.
     pub icon_path: Option<PathBuf>,
 }
 
 /// Information about the currently active window.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ActiveWindowInfo {
     /// Unique address of the window.
     pub address: String,
@@ -104,6 +276,7 @@ pub struct ActiveWindowInfo {
 
 /// Events emitted by the window manager backend.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WmEvent {
     /// Workspace state changed for a monitor.
     WorkspacesChanged(WorkspacesStatus),
@@ -113,4 +286,94 @@ pub enum WmEvent {
     MonitorAdded(String),
     /// Monitor removed, passes the name of the monitor that was removed.
     MonitorRemoved(String),
+    /// A new window was opened.
+    WindowOpened(WindowInfo),
+    /// A window was closed, passes the address of the window.
+    WindowClosed(String),
+    /// A window moved to another workspace or monitor.
+    WindowMoved(WindowInfo),
+    /// A special workspace was shown or hidden on a monitor.
+    /// `name` is the special workspace now shown, or `None` when it was hidden.
+    SpecialWorkspaceToggled {
+        monitor: String,
+        name: Option<String>,
+    },
+    /// A window started or stopped demanding attention.
+    UrgencyChanged { address: String, urgent: bool },
+    /// A monitor started or stopped showing a fullscreen window.
+    FullscreenChanged { monitor: String, fullscreen: bool },
+    /// A window was switched between floating and tiled.
+    FloatingChanged { address: String, floating: bool },
+    /// The active layout of a keyboard changed.
+    KeyboardLayoutChanged { keyboard: String, layout: String },
+    /// A submap (binding mode in Sway) was entered, passes its name.
+    /// Empty when returning to the default submap.
+    SubmapChanged(String),
+    /// The connection to the compositor was lost, the state is stale until it is back.
+    BackendDisconnected,
+    /// The connection to the compositor is back and the state was refreshed.
+    BackendReconnected,
+}
+
+/// Kind of a [`WmEvent`], used to filter subscriptions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WmEventKind {
+    WorkspacesChanged,
+    ActiveWindowChanged,
+    MonitorAdded,
+    MonitorRemoved,
+    WindowOpened,
+    WindowClosed,
+    WindowMoved,
+    SpecialWorkspaceToggled,
+    UrgencyChanged,
+    FullscreenChanged,
+    FloatingChanged,
+    KeyboardLayoutChanged,
+    SubmapChanged,
+    BackendDisconnected,
+    BackendReconnected,
+}
+
+impl WmEvent {
+    /// Kind of this event.
+    pub fn kind(&self) -> WmEventKind {
+        match self {
+            WmEvent::WorkspacesChanged(_) => WmEventKind::WorkspacesChanged,
+            WmEvent::ActiveWindowChanged(_) => WmEventKind::ActiveWindowChanged,
+            WmEvent::MonitorAdded(_) => WmEventKind::MonitorAdded,
+            WmEvent::MonitorRemoved(_) => WmEventKind::MonitorRemoved,
+            WmEvent::WindowOpened(_) => WmEventKind::WindowOpened,
+            WmEvent::WindowClosed(_) => WmEventKind::WindowClosed,
+            WmEvent::WindowMoved(_) => WmEventKind::WindowMoved,
+            WmEvent::SpecialWorkspaceToggled { .. } => WmEventKind::SpecialWorkspaceToggled,
+            WmEvent::UrgencyChanged { .. } => WmEventKind::UrgencyChanged,
+            WmEvent::FullscreenChanged { .. } => WmEventKind::FullscreenChanged,
+            WmEvent::FloatingChanged { .. } => WmEventKind::FloatingChanged,
+            WmEvent::KeyboardLayoutChanged { .. } => WmEventKind::KeyboardLayoutChanged,
+            WmEvent::SubmapChanged(_) => WmEventKind::SubmapChanged,
+            WmEvent::BackendDisconnected => WmEventKind::BackendDisconnected,
+            WmEvent::BackendReconnected => WmEventKind::BackendReconnected,
+        }
+    }
+
+    /// Monitor this event belongs to, `None` for global events like focus changes.
+    pub fn monitor(&self) -> Option<&str> {
+        match self {
+            WmEvent::WorkspacesChanged(status) => Some(&status.monitor_name),
+            WmEvent::MonitorAdded(name) | WmEvent::MonitorRemoved(name) => Some(name),
+            WmEvent::WindowOpened(info) | WmEvent::WindowMoved(info) => Some(&info.monitor),
+            WmEvent::SpecialWorkspaceToggled { monitor, .. }
+            | WmEvent::FullscreenChanged { monitor, .. } => Some(monitor),
+            WmEvent::ActiveWindowChanged(_)
+            | WmEvent::WindowClosed(_)
+            | WmEvent::UrgencyChanged { .. }
+            | WmEvent::FloatingChanged { .. }
+            | WmEvent::KeyboardLayoutChanged { .. }
+            | WmEvent::SubmapChanged(_)
+            | WmEvent::BackendDisconnected
+            | WmEvent::BackendReconnected => None,
+        }
+    }
 }
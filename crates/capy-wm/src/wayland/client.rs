@@ -0,0 +1,410 @@
+//! Wayland connection and protocol glue for the fallback backend.
+//!
+//! Binds `wl_output` (for connector names), `ext_workspace_manager_v1` and,
+//! when available, `ext_foreign_toplevel_list_v1`, and turns their events
+//! into [`ProtocolEvent`]s for the model.
+
+use log::warn;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum, event_created_child,
+};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+use wayland_protocols::ext::workspace::v1::client::{
+    ext_workspace_group_handle_v1::{self, ExtWorkspaceGroupHandleV1},
+    ext_workspace_handle_v1::{self, ExtWorkspaceHandleV1},
+    ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
+};
+
+use super::model::ProtocolEvent;
+
+pub(crate) const WORKSPACE_MANAGER: &str = "ext_workspace_manager_v1";
+
+// wl_output.name was added in version 4
+const OUTPUT_VERSION: u32 = 4;
+
+type WorkspaceHandles = Arc<Mutex<HashMap<u32, ExtWorkspaceHandleV1>>>;
+
+/// Check whether the compositor advertises the given globals.
+pub(crate) fn has_globals(interfaces: &[&str]) -> bool {
+    let Ok(conn) = Connection::connect_to_env() else {
+        return false;
+    };
+    let Ok((globals, _queue)) = registry_queue_init::<ClientState>(&conn) else {
+        return false;
+    };
+    globals.contents().with_list(|list| {
+        interfaces
+            .iter()
+            .all(|interface| list.iter().any(|g| g.interface == *interface))
+    })
+}
+
+/// A live connection with all protocol objects bound.
+pub(crate) struct Session {
+    queue: EventQueue<ClientState>,
+    state: ClientState,
+    controller: Controller,
+}
+
+impl Session {
+    /// Connect to `$WAYLAND_DISPLAY` and bind the workspace and toplevel globals.
+    pub(crate) fn connect() -> io::Result<Self> {
+        let conn = Connection::connect_to_env().map_err(io::Error::other)?;
+        let (globals, queue) =
+            registry_queue_init::<ClientState>(&conn).map_err(io::Error::other)?;
+        let qh = queue.handle();
+
+        let manager: ExtWorkspaceManagerV1 =
+            globals.bind(&qh, 1..=1, ()).map_err(io::Error::other)?;
+
+        // Toplevels are optional, workspaces work without them
+        let toplevel_list = globals
+            .bind::<ExtForeignToplevelListV1, _, _>(&qh, 1..=1, ())
+            .ok();
+
+        let workspaces = WorkspaceHandles::default();
+        let mut state = ClientState {
+            outputs: HashMap::new(),
+            workspaces: workspaces.clone(),
+            events: Vec::new(),
+            _toplevel_list: toplevel_list,
+        };
+
+        let registry = globals.registry();
+        for global in globals.contents().clone_list() {
+            if global.interface == WlOutput::interface().name {
+                state.bind_output(registry, &qh, global.name, global.version);
+            }
+        }
+
+        Ok(Self {
+            queue,
+            state,
+            controller: Controller {
+                conn,
+                manager,
+                workspaces,
+            },
+        })
+    }
+
+    /// Block until events arrive and return them in protocol order.
+    pub(crate) fn dispatch(&mut self) -> io::Result<Vec<ProtocolEvent>> {
+        self.queue
+            .blocking_dispatch(&mut self.state)
+            .map_err(io::Error::other)?;
+        Ok(std::mem::take(&mut self.state.events))
+    }
+
+    /// Wait for the compositor to send the current state and return it.
+    pub(crate) fn initial_state(&mut self) -> io::Result<Vec<ProtocolEvent>> {
+        // The second roundtrip collects events of objects created during the first
+        for _ in 0..2 {
+            self.queue
+                .roundtrip(&mut self.state)
+                .map_err(io::Error::other)?;
+        }
+        Ok(std::mem::take(&mut self.state.events))
+    }
+
+    /// Handle for sending workspace requests from other threads.
+    pub(crate) fn controller(&self) -> Controller {
+        self.controller.clone()
+    }
+}
+
+/// Sends workspace requests on a session's connection.
+#[derive(Clone)]
+pub(crate) struct Controller {
+    conn: Connection,
+    manager: ExtWorkspaceManagerV1,
+    workspaces: WorkspaceHandles,
+}
+
+impl Controller {
+    /// Activate the workspace with the given protocol object ID.
+    pub(crate) fn activate(&self, workspace: u32) -> io::Result<()> {
+        let handles = self
+            .workspaces
+            .lock()
+            .map_err(|_| io::Error::other("workspace handles poisoned"))?;
+        let handle = handles
+            .get(&workspace)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown workspace"))?;
+
+        handle.activate();
+        self.manager.commit();
+        self.conn.flush().map_err(io::Error::other)
+    }
+}
+
+/// Dispatch state: collects protocol events until the caller drains them.
+struct ClientState {
+    /// Bound outputs keyed by registry name, so they can be released on removal.
+    outputs: HashMap<u32, WlOutput>,
+    workspaces: WorkspaceHandles,
+    events: Vec<ProtocolEvent>,
+    _toplevel_list: Option<ExtForeignToplevelListV1>,
+}
+
+impl ClientState {
+    fn bind_output(
+        &mut self,
+        registry: &WlRegistry,
+        qh: &QueueHandle<Self>,
+        name: u32,
+        version: u32,
+    ) {
+        if version < OUTPUT_VERSION {
+            warn!(
+                "wl_output version {} has no names, skipping output",
+                version
+            );
+            return;
+        }
+        let output = registry.bind::<WlOutput, _, _>(name, OUTPUT_VERSION, qh, ());
+        self.outputs.insert(name, output);
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for ClientState {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == WlOutput::interface().name => {
+                state.bind_output(registry, qh, name, version);
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some(output) = state.outputs.remove(&name) {
+                    state.events.push(ProtocolEvent::OutputRemoved {
+                        output: output.id().protocol_id(),
+                    });
+                    output.release();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        output: &WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.events.push(ProtocolEvent::OutputName {
+                output: output.id().protocol_id(),
+                name,
+            });
+        }
+    }
+}
+
+impl Dispatch<ExtWorkspaceManagerV1, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        _: &ExtWorkspaceManagerV1,
+        event: ext_workspace_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_workspace_manager_v1::Event::WorkspaceGroup { workspace_group } => {
+                state.events.push(ProtocolEvent::GroupCreated {
+                    group: workspace_group.id().protocol_id(),
+                });
+            }
+            ext_workspace_manager_v1::Event::Workspace { workspace } => {
+                let id = workspace.id().protocol_id();
+                if let Ok(mut handles) = state.workspaces.lock() {
+                    handles.insert(id, workspace);
+                }
+                state
+                    .events
+                    .push(ProtocolEvent::WorkspaceCreated { workspace: id });
+            }
+            ext_workspace_manager_v1::Event::Done => {
+                state.events.push(ProtocolEvent::WorkspacesDone);
+            }
+            ext_workspace_manager_v1::Event::Finished => {
+                warn!("Compositor stopped sending workspace events");
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(ClientState, ExtWorkspaceManagerV1, [
+        ext_workspace_manager_v1::EVT_WORKSPACE_GROUP_OPCODE => (ExtWorkspaceGroupHandleV1, ()),
+        ext_workspace_manager_v1::EVT_WORKSPACE_OPCODE => (ExtWorkspaceHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtWorkspaceGroupHandleV1, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        handle: &ExtWorkspaceGroupHandleV1,
+        event: ext_workspace_group_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let group = handle.id().protocol_id();
+        let event = match event {
+            ext_workspace_group_handle_v1::Event::OutputEnter { output } => {
+                ProtocolEvent::GroupOutputEnter {
+                    group,
+                    output: output.id().protocol_id(),
+                }
+            }
+            ext_workspace_group_handle_v1::Event::OutputLeave { output } => {
+                ProtocolEvent::GroupOutputLeave {
+                    group,
+                    output: output.id().protocol_id(),
+                }
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceEnter { workspace } => {
+                ProtocolEvent::GroupWorkspaceEnter {
+                    group,
+                    workspace: workspace.id().protocol_id(),
+                }
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceLeave { workspace } => {
+                ProtocolEvent::GroupWorkspaceLeave {
+                    group,
+                    workspace: workspace.id().protocol_id(),
+                }
+            }
+            ext_workspace_group_handle_v1::Event::Removed => {
+                handle.destroy();
+                ProtocolEvent::GroupRemoved { group }
+            }
+            _ => return,
+        };
+        state.events.push(event);
+    }
+}
+
+impl Dispatch<ExtWorkspaceHandleV1, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        handle: &ExtWorkspaceHandleV1,
+        event: ext_workspace_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let workspace = handle.id().protocol_id();
+        let event = match event {
+            ext_workspace_handle_v1::Event::Name { name } => {
+                ProtocolEvent::WorkspaceName { workspace, name }
+            }
+            ext_workspace_handle_v1::Event::Coordinates { coordinates } => {
+                // Array of native-endian u32
+                let coordinates = coordinates
+                    .chunks_exact(4)
+                    .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                ProtocolEvent::WorkspaceCoordinates {
+                    workspace,
+                    coordinates,
+                }
+            }
+            ext_workspace_handle_v1::Event::State { state: bits } => {
+                let state = match bits {
+                    WEnum::Value(value) => value.bits(),
+                    WEnum::Unknown(raw) => raw,
+                };
+                ProtocolEvent::WorkspaceState { workspace, state }
+            }
+            ext_workspace_handle_v1::Event::Removed => {
+                if let Ok(mut handles) = state.workspaces.lock() {
+                    handles.remove(&workspace);
+                }
+                handle.destroy();
+                ProtocolEvent::WorkspaceRemoved { workspace }
+            }
+            _ => return,
+        };
+        state.events.push(event);
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        _: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } = event {
+            state.events.push(ProtocolEvent::ToplevelCreated {
+                toplevel: toplevel.id().protocol_id(),
+            });
+        }
+    }
+
+    event_created_child!(ClientState, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let toplevel = handle.id().protocol_id();
+        let event = match event {
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => {
+                ProtocolEvent::ToplevelTitle { toplevel, title }
+            }
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                ProtocolEvent::ToplevelAppId { toplevel, app_id }
+            }
+            ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                ProtocolEvent::ToplevelIdentifier {
+                    toplevel,
+                    identifier,
+                }
+            }
+            ext_foreign_toplevel_handle_v1::Event::Done => ProtocolEvent::ToplevelDone { toplevel },
+            ext_foreign_toplevel_handle_v1::Event::Closed => {
+                handle.destroy();
+                ProtocolEvent::ToplevelClosed { toplevel }
+            }
+            _ => return,
+        };
+        state.events.push(event);
+    }
+}
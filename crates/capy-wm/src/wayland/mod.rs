@@ -0,0 +1,205 @@
+//! Generic Wayland backend for capy-wm.
+//!
+//! Used when no specific WM is detected but the compositor implements
+//! `ext-workspace-v1`. Workspaces come from ext-workspace and open windows from
+//! `ext-foreign-toplevel-list-v1` when available. These protocols have no notion
+//! of focus or window placement, so the active window and workspace icons stay empty.
+
+mod client;
+mod model;
+
+use crate::types::WindowInfo;
+use crate::{
+    WindowBackend, WmError, WmEvent, WmResult, read, resolve_icon, send_event, sync_windows, write,
+};
+use client::{Controller, Session};
+use log::{debug, error, info};
+use model::{ProtocolEvent, Update, WaylandModel};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::thread;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+// Mirror of the compositor's workspace state, kept up to date by the listener
+static MODEL: LazyLock<RwLock<WaylandModel>> =
+    LazyLock::new(|| RwLock::new(WaylandModel::default()));
+
+// Request side of the listener's connection, set once the listener is connected
+static CONTROLLER: Mutex<Option<Controller>> = Mutex::new(None);
+
+/// Wayland protocol fallback backend.
+pub struct WaylandBackend;
+
+impl WaylandBackend {
+    /// Create a new Wayland backend.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Whether the compositor advertises the globals this backend needs.
+    pub fn is_supported() -> bool {
+        client::has_globals(&[client::WORKSPACE_MANAGER])
+    }
+}
+
+impl Default for WaylandBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowBackend for WaylandBackend {
+    fn get_active_monitor(&self) -> WmResult<String> {
+        // No protocol reports focus, use the first output
+        Ok(self.get_monitors()?.into_iter().next().unwrap_or_default())
+    }
+
+    fn get_monitors(&self) -> WmResult<Vec<String>> {
+        let monitors = read(&MODEL).monitor_names();
+        if !monitors.is_empty() {
+            return Ok(monitors);
+        }
+
+        // Listener not running yet, ask the compositor directly
+        let mut model = WaylandModel::default();
+        for event in Session::connect()?.initial_state()? {
+            model.apply(event);
+        }
+        Ok(model.monitor_names())
+    }
+
+    fn switch_workspace(&self, workspace_id: i32) -> WmResult<()> {
+        let object = read(&MODEL)
+            .workspace_object(workspace_id)
+            .ok_or_else(|| WmError::Dispatch(format!("unknown workspace {}", workspace_id)))?;
+        let controller = CONTROLLER
+            .lock()
+            .ok()
+            .and_then(|c| c.clone())
+            .ok_or_else(|| WmError::Dispatch("Wayland listener is not running".to_string()))?;
+
+        Ok(controller.activate(object)?)
+    }
+
+    fn start_listener(&self) -> WmResult<()> {
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            info!("Wayland listener already running");
+            return Ok(());
+        }
+
+        info!("Starting Wayland workspace listener...");
+
+        thread::spawn(|| {
+            if let Err(e) = listen() {
+                error!("Wayland listener failed: {}", e);
+            }
+            if let Ok(mut controller) = CONTROLLER.lock() {
+                *controller = None;
+            }
+            RUNNING.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    fn trigger_refresh(&self) -> WmResult<()> {
+        // The model is pushed by the compositor, so republishing it is enough
+        send_updates_to_all_monitors();
+        self.init_active_window()
+    }
+
+    fn init_active_window(&self) -> WmResult<()> {
+        send_event(WmEvent::ActiveWindowChanged(Default::default()));
+        Ok(())
+    }
+}
+
+/// Connect and apply protocol events until the connection fails.
+fn listen() -> std::io::Result<()> {
+    let mut session = Session::connect()?;
+    if let Ok(mut controller) = CONTROLLER.lock() {
+        *controller = Some(session.controller());
+    }
+    info!("Wayland workspace listener active");
+
+    loop {
+        let events = session.dispatch()?;
+        handle_events(events);
+    }
+}
+
+/// Apply a batch of protocol events and publish the result once per batch.
+fn handle_events(events: Vec<ProtocolEvent>) {
+    let (before, after, workspaces_changed, windows) = {
+        let mut model = write(&MODEL);
+        let before: BTreeSet<String> = model.monitor_names().into_iter().collect();
+        let mut workspaces_changed = false;
+        let mut toplevels_changed = false;
+        for event in events {
+            match model.apply(event) {
+                Some(Update::Workspaces) => workspaces_changed = true,
+                Some(Update::Toplevels) => toplevels_changed = true,
+                None => {}
+            }
+        }
+        let after: BTreeSet<String> = model.monitor_names().into_iter().collect();
+        let windows = toplevels_changed.then(|| window_list(&model));
+        (before, after, workspaces_changed, windows)
+    };
+
+    emit_monitor_changes(&before, &after);
+    if workspaces_changed {
+        send_updates_to_all_monitors();
+    }
+    if let Some(windows) = windows {
+        sync_windows(windows);
+    }
+}
+
+/// Windows from the toplevel list. The protocol has no workspace, monitor or geometry info.
+fn window_list(model: &WaylandModel) -> Vec<WindowInfo> {
+    model
+        .toplevels()
+        .into_iter()
+        .map(|toplevel| WindowInfo {
+            address: toplevel.identifier,
+            icon_path: resolve_icon(&toplevel.app_id),
+            initial_class: toplevel.app_id.clone(),
+            class: toplevel.app_id,
+            title: toplevel.title,
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn emit_monitor_changes(before: &BTreeSet<String>, after: &BTreeSet<String>) {
+    // The first output names are the initial state, not a hotplug
+    if before.is_empty() {
+        return;
+    }
+    for name in after.difference(before) {
+        debug!("Monitor added: {}", name);
+        send_event(WmEvent::MonitorAdded(name.clone()));
+    }
+    for name in before.difference(after) {
+        debug!("Monitor removed: {}", name);
+        send_event(WmEvent::MonitorRemoved(name.clone()));
+    }
+}
+
+/// Send workspace updates to all outputs.
+fn send_updates_to_all_monitors() {
+    let statuses: Vec<_> = {
+        let model = read(&MODEL);
+        model
+            .monitor_names()
+            .iter()
+            .map(|output| model.workspaces_status(output))
+            .collect()
+    };
+
+    for status in statuses {
+        send_event(WmEvent::WorkspacesChanged(status));
+    }
+}
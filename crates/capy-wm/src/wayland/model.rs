@@ -0,0 +1,486 @@
+//! Protocol-agnostic model of the ext-workspace and ext-foreign-toplevel-list state.
+//!
+//! The Wayland glue translates protocol events into [`ProtocolEvent`]s keyed by
+//! object protocol IDs. Keeping this free of `wayland-client` types lets the
+//! model be tested by replaying recorded event transcripts.
+
+use crate::types::{WorkspaceInfo, WorkspaceKind, WorkspaceState, WorkspacesStatus};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// ext_workspace_handle_v1.state bits
+const STATE_ACTIVE: u32 = 1;
+const STATE_URGENT: u32 = 2;
+const STATE_HIDDEN: u32 = 4;
+
+/// A decoded protocol event. Object fields are Wayland protocol object IDs.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ProtocolEvent {
+    OutputName {
+        output: u32,
+        name: String,
+    },
+    OutputRemoved {
+        output: u32,
+    },
+    GroupCreated {
+        group: u32,
+    },
+    GroupOutputEnter {
+        group: u32,
+        output: u32,
+    },
+    GroupOutputLeave {
+        group: u32,
+        output: u32,
+    },
+    GroupWorkspaceEnter {
+        group: u32,
+        workspace: u32,
+    },
+    GroupWorkspaceLeave {
+        group: u32,
+        workspace: u32,
+    },
+    GroupRemoved {
+        group: u32,
+    },
+    WorkspaceCreated {
+        workspace: u32,
+    },
+    WorkspaceName {
+        workspace: u32,
+        name: String,
+    },
+    WorkspaceCoordinates {
+        workspace: u32,
+        coordinates: Vec<u32>,
+    },
+    WorkspaceState {
+        workspace: u32,
+        state: u32,
+    },
+    WorkspaceRemoved {
+        workspace: u32,
+    },
+    /// ext_workspace_manager_v1.done: all pending workspace changes are applied.
+    WorkspacesDone,
+    ToplevelCreated {
+        toplevel: u32,
+    },
+    ToplevelTitle {
+        toplevel: u32,
+        title: String,
+    },
+    ToplevelAppId {
+        toplevel: u32,
+        app_id: String,
+    },
+    ToplevelIdentifier {
+        toplevel: u32,
+        identifier: String,
+    },
+    ToplevelDone {
+        toplevel: u32,
+    },
+    ToplevelClosed {
+        toplevel: u32,
+    },
+}
+
+/// What kind of state became consistent after applying an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Update {
+    Workspaces,
+    Toplevels,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Workspace {
+    /// Stable capy-wm ID, assigned in creation order.
+    absolute_id: i32,
+    name: String,
+    coordinates: Vec<u32>,
+    state: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Group {
+    outputs: HashSet<u32>,
+    workspaces: Vec<u32>,
+}
+
+/// A toplevel from ext-foreign-toplevel-list.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Toplevel {
+    pub identifier: String,
+    pub app_id: String,
+    pub title: String,
+}
+
+/// Workspace and toplevel state of the compositor.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WaylandModel {
+    outputs: BTreeMap<u32, String>,
+    groups: HashMap<u32, Group>,
+    workspaces: HashMap<u32, Workspace>,
+    toplevels: HashMap<u32, Toplevel>,
+    next_workspace_id: i32,
+}
+
+impl WaylandModel {
+    /// Apply a protocol event, returning which state is now consistent and should be published.
+    pub(crate) fn apply(&mut self, event: ProtocolEvent) -> Option<Update> {
+        match event {
+            ProtocolEvent::OutputName { output, name } => {
+                self.outputs.insert(output, name);
+                return Some(Update::Workspaces);
+            }
+            ProtocolEvent::OutputRemoved { output } => {
+                self.outputs.remove(&output);
+                for group in self.groups.values_mut() {
+                    group.outputs.remove(&output);
+                }
+                return Some(Update::Workspaces);
+            }
+            ProtocolEvent::GroupCreated { group } => {
+                self.groups.insert(group, Group::default());
+            }
+            ProtocolEvent::GroupOutputEnter { group, output } => {
+                self.groups.entry(group).or_default().outputs.insert(output);
+            }
+            ProtocolEvent::GroupOutputLeave { group, output } => {
+                if let Some(group) = self.groups.get_mut(&group) {
+                    group.outputs.remove(&output);
+                }
+            }
+            ProtocolEvent::GroupWorkspaceEnter { group, workspace } => {
+                let group = self.groups.entry(group).or_default();
+                if !group.workspaces.contains(&workspace) {
+                    group.workspaces.push(workspace);
+                }
+            }
+            ProtocolEvent::GroupWorkspaceLeave { group, workspace } => {
+                if let Some(group) = self.groups.get_mut(&group) {
+                    group.workspaces.retain(|ws| *ws != workspace);
+                }
+            }
+            ProtocolEvent::GroupRemoved { group } => {
+                self.groups.remove(&group);
+            }
+            ProtocolEvent::WorkspaceCreated { workspace } => {
+                self.next_workspace_id += 1;
+                self.workspaces.insert(
+                    workspace,
+                    Workspace {
+                        absolute_id: self.next_workspace_id,
+                        ..Default::default()
+                    },
+                );
+            }
+            ProtocolEvent::WorkspaceName { workspace, name } => {
+                if let Some(ws) = self.workspaces.get_mut(&workspace) {
+                    ws.name = name;
+                }
+            }
+            ProtocolEvent::WorkspaceCoordinates {
+                workspace,
+                coordinates,
+            } => {
+                if let Some(ws) = self.workspaces.get_mut(&workspace) {
+                    ws.coordinates = coordinates;
+                }
+            }
+            ProtocolEvent::WorkspaceState { workspace, state } => {
+                if let Some(ws) = self.workspaces.get_mut(&workspace) {
+                    ws.state = state;
+                }
+            }
+            ProtocolEvent::WorkspaceRemoved { workspace } => {
+                self.workspaces.remove(&workspace);
+                for group in self.groups.values_mut() {
+                    group.workspaces.retain(|ws| *ws != workspace);
+                }
+            }
+            ProtocolEvent::WorkspacesDone => return Some(Update::Workspaces),
+            ProtocolEvent::ToplevelCreated { toplevel } => {
+                self.toplevels.insert(toplevel, Toplevel::default());
+            }
+            ProtocolEvent::ToplevelTitle { toplevel, title } => {
+                if let Some(t) = self.toplevels.get_mut(&toplevel) {
+                    t.title = title;
+                }
+            }
+            ProtocolEvent::ToplevelAppId { toplevel, app_id } => {
+                if let Some(t) = self.toplevels.get_mut(&toplevel) {
+                    t.app_id = app_id;
+                }
+            }
+            ProtocolEvent::ToplevelIdentifier {
+                toplevel,
+                identifier,
+            } => {
+                if let Some(t) = self.toplevels.get_mut(&toplevel) {
+                    t.identifier = identifier;
+                }
+            }
+            ProtocolEvent::ToplevelDone { .. } => return Some(Update::Toplevels),
+            ProtocolEvent::ToplevelClosed { toplevel } => {
+                self.toplevels.remove(&toplevel);
+                return Some(Update::Toplevels);
+            }
+        }
+        None
+    }
+
+    /// Names of all known outputs.
+    pub(crate) fn monitor_names(&self) -> Vec<String> {
+        self.outputs.values().cloned().collect()
+    }
+
+    /// Protocol ID of the workspace with the given capy-wm ID.
+    pub(crate) fn workspace_object(&self, absolute_id: i32) -> Option<u32> {
+        self.workspaces
+            .iter()
+            .find(|(_, ws)| ws.absolute_id == absolute_id)
+            .map(|(object, _)| *object)
+    }
+
+    /// All open toplevels.
+    pub(crate) fn toplevels(&self) -> Vec<Toplevel> {
+        self.toplevels.values().cloned().collect()
+    }
+
+    /// Build the workspace status for one output from the groups shown on it.
+    /// Numeric workspace names are used as relative IDs, otherwise the position is used.
+    pub(crate) fn workspaces_status(&self, monitor_name: &str) -> WorkspacesStatus {
+        let output_ids: HashSet<u32> = self
+            .outputs
+            .iter()
+            .filter(|(_, name)| name.as_str() == monitor_name)
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut on_output: Vec<&Workspace> = self
+            .groups
+            .values()
+            .filter(|group| !group.outputs.is_disjoint(&output_ids))
+            .flat_map(|group| group.workspaces.iter())
+            .filter_map(|object| self.workspaces.get(object))
+            .filter(|ws| ws.state & STATE_HIDDEN == 0)
+            .collect();
+        on_output.sort_by(|a, b| {
+            a.coordinates
+                .cmp(&b.coordinates)
+                .then(a.absolute_id.cmp(&b.absolute_id))
+        });
+
+        let workspaces = on_output
+            .into_iter()
+            .enumerate()
+            .map(|(i, ws)| {
+                let state = if ws.state & STATE_ACTIVE != 0 {
+                    WorkspaceState::Active
+                } else if ws.state & STATE_URGENT != 0 {
+                    WorkspaceState::Attention
+                } else {
+                    WorkspaceState::Empty
+                };
+
+                let id = ws.name.parse().unwrap_or(i as i32 + 1);
+                WorkspaceInfo {
+                    id,
+                    absolute_id: ws.absolute_id,
+                    name: ws.name.clone(),
+                    kind: WorkspaceKind::from_id_and_name(id, &ws.name),
+                    state,
+                    // ext-workspace does not say which toplevels live on a workspace
+                    icon_path: None,
+                    occupied: false,
+                    app_class: None,
+                }
+            })
+            .collect();
+
+        WorkspacesStatus {
+            monitor_name: monitor_name.to_string(),
+            workspaces,
+            special: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProtocolEvent::{self, *};
+    use super::{STATE_ACTIVE, STATE_HIDDEN, STATE_URGENT, Update, WaylandModel};
+    use crate::types;
+
+    /// Transcript of a labwc-like compositor with two outputs, recorded from
+    /// WAYLAND_DEBUG output and reduced to the events the model consumes.
+    fn startup_transcript() -> Vec<ProtocolEvent> {
+        vec![
+            OutputName {
+                output: 10,
+                name: "eDP-1".into(),
+            },
+            OutputName {
+                output: 11,
+                name: "DP-2".into(),
+            },
+            GroupCreated { group: 20 },
+            GroupOutputEnter {
+                group: 20,
+                output: 10,
+            },
+            GroupCreated { group: 21 },
+            GroupOutputEnter {
+                group: 21,
+                output: 11,
+            },
+            WorkspaceCreated { workspace: 30 },
+            WorkspaceName {
+                workspace: 30,
+                name: "1".into(),
+            },
+            WorkspaceCoordinates {
+                workspace: 30,
+                coordinates: vec![0],
+            },
+            WorkspaceState {
+                workspace: 30,
+                state: STATE_ACTIVE,
+            },
+            WorkspaceCreated { workspace: 31 },
+            WorkspaceName {
+                workspace: 31,
+                name: "2".into(),
+            },
+            WorkspaceCoordinates {
+                workspace: 31,
+                coordinates: vec![1],
+            },
+            WorkspaceState {
+                workspace: 31,
+                state: STATE_URGENT,
+            },
+            WorkspaceCreated { workspace: 32 },
+            WorkspaceName {
+                workspace: 32,
+                name: "scratch".into(),
+            },
+            WorkspaceState {
+                workspace: 32,
+                state: STATE_HIDDEN,
+            },
+            WorkspaceCreated { workspace: 33 },
+            WorkspaceName {
+                workspace: 33,
+                name: "web".into(),
+            },
+            WorkspaceState {
+                workspace: 33,
+                state: STATE_ACTIVE,
+            },
+            GroupWorkspaceEnter {
+                group: 20,
+                workspace: 31,
+            },
+            GroupWorkspaceEnter {
+                group: 20,
+                workspace: 30,
+            },
+            GroupWorkspaceEnter {
+                group: 20,
+                workspace: 32,
+            },
+            GroupWorkspaceEnter {
+                group: 21,
+                workspace: 33,
+            },
+            WorkspacesDone,
+            ToplevelCreated { toplevel: 40 },
+            ToplevelIdentifier {
+                toplevel: 40,
+                identifier: "a1b2".into(),
+            },
+            ToplevelAppId {
+                toplevel: 40,
+                app_id: "foot".into(),
+            },
+            ToplevelTitle {
+                toplevel: 40,
+                title: "~".into(),
+            },
+            ToplevelDone { toplevel: 40 },
+        ]
+    }
+
+    fn replay(events: Vec<ProtocolEvent>) -> (WaylandModel, Vec<Update>) {
+        let mut model = WaylandModel::default();
+        let updates = events
+            .into_iter()
+            .filter_map(|event| model.apply(event))
+            .collect();
+        (model, updates)
+    }
+
+    #[test]
+    fn test_transcript_builds_status_per_output() {
+        let (model, updates) = replay(startup_transcript());
+        assert!(updates.contains(&Update::Workspaces));
+        assert_eq!(updates.last(), Some(&Update::Toplevels));
+        assert_eq!(model.monitor_names(), vec!["eDP-1", "DP-2"]);
+
+        let edp = model.workspaces_status("eDP-1");
+        assert_eq!(edp.workspaces.len(), 2, "hidden workspaces are skipped");
+        assert_eq!(edp.workspaces[0].id, 1);
+        assert_eq!(edp.workspaces[0].state, types::WorkspaceState::Active);
+        assert_eq!(edp.workspaces[1].id, 2);
+        assert_eq!(edp.workspaces[1].state, types::WorkspaceState::Attention);
+
+        let dp = model.workspaces_status("DP-2");
+        assert_eq!(dp.workspaces.len(), 1);
+        assert_eq!(
+            dp.workspaces[0].id, 1,
+            "non-numeric names fall back to position"
+        );
+        assert_eq!(
+            model.workspace_object(dp.workspaces[0].absolute_id),
+            Some(33)
+        );
+    }
+
+    #[test]
+    fn test_workspace_moves_between_groups() {
+        let mut events = startup_transcript();
+        events.extend([
+            GroupWorkspaceLeave {
+                group: 20,
+                workspace: 31,
+            },
+            GroupWorkspaceEnter {
+                group: 21,
+                workspace: 31,
+            },
+            WorkspacesDone,
+        ]);
+        let (model, _) = replay(events);
+
+        assert_eq!(model.workspaces_status("eDP-1").workspaces.len(), 1);
+        assert_eq!(model.workspaces_status("DP-2").workspaces.len(), 2);
+    }
+
+    #[test]
+    fn test_output_and_toplevel_removal() {
+        let mut events = startup_transcript();
+        events.extend([
+            OutputRemoved { output: 11 },
+            ToplevelClosed { toplevel: 40 },
+        ]);
+        let (model, _) = replay(events);
+
+        assert_eq!(model.monitor_names(), vec!["eDP-1"]);
+        assert!(model.workspaces_status("DP-2").workspaces.is_empty());
+        assert!(model.toplevels().is_empty());
+    }
+}
@@ -1,27 +1,151 @@
-use crate::WmType;
+#[cfg(feature = "hyprland")]
 use crate::hyprland;
+use crate::monitors::MonitorConfig;
+#[cfg(feature = "niri")]
+use crate::niri;
+#[cfg(feature = "sway")]
+use crate::sway;
+use crate::types::{MonitorInfo, MonitorTransform};
+#[cfg(feature = "wayland")]
+use crate::wayland;
+use crate::{WmError, WmResult, WmType};
 
 /// Trait that all window manager backends must implement.
 /// This provides a unified interface regardless of the underlying WM.
 pub trait WindowBackend: Send + Sync {
     /// Get the name of the currently focused monitor.
-    fn get_active_monitor(&self) -> String;
+    fn get_active_monitor(&self) -> WmResult<String>;
 
     /// Get all monitor names.
-    fn get_monitors(&self) -> Vec<String>;
+    fn get_monitors(&self) -> WmResult<Vec<String>>;
+
+    /// Get details of all monitors. Backends without monitor details only fill in the names.
+    fn get_monitor_info(&self) -> WmResult<Vec<MonitorInfo>> {
+        Ok(self
+            .get_monitors()?
+            .into_iter()
+            .map(|name| MonitorInfo {
+                name,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Set the resolution and refresh rate (Hz) of a monitor.
+    fn set_monitor_mode(
+        &self,
+        _name: &str,
+        _width: i32,
+        _height: i32,
+        _refresh_rate: f32,
+    ) -> WmResult<()> {
+        Err(WmError::Unsupported("set_monitor_mode"))
+    }
+
+    /// Move a monitor in the global layout.
+    fn set_monitor_position(&self, _name: &str, _x: i32, _y: i32) -> WmResult<()> {
+        Err(WmError::Unsupported("set_monitor_position"))
+    }
+
+    /// Set the scale factor of a monitor.
+    fn set_monitor_scale(&self, _name: &str, _scale: f32) -> WmResult<()> {
+        Err(WmError::Unsupported("set_monitor_scale"))
+    }
+
+    /// Rotate or flip a monitor.
+    fn set_monitor_transform(&self, _name: &str, _transform: MonitorTransform) -> WmResult<()> {
+        Err(WmError::Unsupported("set_monitor_transform"))
+    }
+
+    /// Enable or disable a monitor.
+    fn set_monitor_enabled(&self, _name: &str, _enabled: bool) -> WmResult<()> {
+        Err(WmError::Unsupported("set_monitor_enabled"))
+    }
+
+    /// Apply a full monitor configuration, e.g. from a profile.
+    fn apply_monitor_config(&self, config: &MonitorConfig) -> WmResult<()> {
+        if !config.enabled {
+            return self.set_monitor_enabled(&config.name, false);
+        }
+        self.set_monitor_enabled(&config.name, true)?;
+        self.set_monitor_mode(
+            &config.name,
+            config.width,
+            config.height,
+            config.refresh_rate,
+        )?;
+        self.set_monitor_scale(&config.name, config.scale)?;
+        self.set_monitor_transform(&config.name, config.transform)?;
+        self.set_monitor_position(&config.name, config.x, config.y)
+    }
 
     /// Switch to a specific workspace by absolute ID.
-    fn switch_workspace(&self, workspace_id: i32);
+    fn switch_workspace(&self, workspace_id: i32) -> WmResult<()>;
+
+    /// Close the window with the given address.
+    fn close_window(&self, _address: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("close_window"))
+    }
+
+    /// Focus the window with the given address.
+    fn focus_window(&self, _address: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("focus_window"))
+    }
+
+    /// Move a window to a workspace by absolute ID without following it.
+    fn move_window_to_workspace(&self, _address: &str, _workspace_id: i32) -> WmResult<()> {
+        Err(WmError::Unsupported("move_window_to_workspace"))
+    }
+
+    /// Toggle floating for the window with the given address.
+    fn toggle_floating(&self, _address: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("toggle_floating"))
+    }
+
+    /// Toggle fullscreen for the window with the given address.
+    /// The window may be focused, Hyprland can only make the focused window fullscreen.
+    fn toggle_fullscreen(&self, _address: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("toggle_fullscreen"))
+    }
+
+    /// Toggle pinning (visible on all workspaces) for the window with the given address.
+    fn toggle_pin(&self, _address: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("toggle_pin"))
+    }
+
+    /// Swap the positions of two windows. Focus stays on the focused window.
+    fn swap_windows(&self, _address: &str, _other: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("swap_windows"))
+    }
+
+    /// Clear the attention request of a window and refresh the workspace status.
+    /// Compositors that track urgency themselves (Sway, niri) also clear it once the window is focused.
+    fn clear_urgency(&self, address: &str) -> WmResult<()> {
+        crate::set_urgent(address, false);
+        self.trigger_refresh()
+    }
+
+    /// Show or hide a special workspace (scratchpad) on the focused monitor.
+    fn toggle_special_workspace(&self, _name: &str) -> WmResult<()> {
+        Err(WmError::Unsupported("toggle_special_workspace"))
+    }
+
+    /// Switch all keyboards to their next layout.
+    fn cycle_keyboard_layout(&self) -> WmResult<()> {
+        Err(WmError::Unsupported("cycle_keyboard_layout"))
+    }
 
     /// Start the background event listener.
-    /// This spawns a thread that monitors WM events and calls the event callback.
-    fn start_listener(&self);
+    /// This spawns a thread that monitors WM events and publishes them to subscribers.
+    /// Fails if the compositor cannot be reached. Backends that reconnect on their own
+    /// send [`WmEvent::BackendDisconnected`](crate::WmEvent::BackendDisconnected) while the connection is lost.
+    fn start_listener(&self) -> WmResult<()>;
 
     /// Trigger a refresh of all WM state.
-    fn trigger_refresh(&self);
+    fn trigger_refresh(&self) -> WmResult<()>;
 
     /// Initialize the active window state.
-    fn init_active_window(&self);
+    fn init_active_window(&self) -> WmResult<()>;
 }
 
 /// Detect the current window manager from environment variables.
@@ -48,32 +172,37 @@ pub fn detect_wm() -> WmType {
         return WmType::Sway;
     }
 
-    // TODO: check for niri
-    // if std::env::var("NIRISOCK").is_ok() {
-    //     return WmType::Niri;
-    // }
+    if std::env::var("NIRI_SOCKET").is_ok() {
+        return WmType::Niri;
+    }
 
     WmType::Unknown
 }
 
 /// Create the appropriate backend for the detected window manager.
+/// Unknown compositors fall back to the generic Wayland backend if they support ext-workspace.
 /// Returns None if no supported WM is detected.
 pub fn create_backend() -> Option<Box<dyn WindowBackend>> {
     match detect_wm() {
         #[cfg(feature = "hyprland")]
         WmType::Hyprland => Some(Box::new(hyprland::HyprlandBackend::new())),
+        #[cfg(feature = "sway")]
+        WmType::Sway => Some(Box::new(sway::SwayBackend::new())),
+        #[cfg(feature = "niri")]
+        WmType::Niri => Some(Box::new(niri::NiriBackend::new())),
+        #[cfg(feature = "wayland")]
+        WmType::Unknown if wayland::WaylandBackend::is_supported() => {
+            Some(Box::new(wayland::WaylandBackend::new()))
+        }
 
-        // Future backends:
-        // WmType::Sway => Some(Box::new(sway::SwayBackend::new())),
-        // WmType::Niri => Some(Box::new(niri::NiriBackend::new())),
         _ => None,
     }
 }
 
-/// Get the backend, panicking if no supported WM is detected.
-/// Use this when you require a WM to be present.
-pub fn get_backend() -> Box<dyn WindowBackend> {
-    create_backend().expect("No supported window manager detected")
+/// Get the backend, or [`WmError::NoBackend`] if no supported WM is detected.
+/// Callers can fall back to running without a WM instead of aborting.
+pub fn get_backend() -> WmResult<Box<dyn WindowBackend>> {
+    create_backend().ok_or(WmError::NoBackend)
 }
 
 #[cfg(test)]
@@ -107,8 +236,40 @@ mod tests {
             env::remove_var("XDG_CURRENT_DESKTOP");
             env::remove_var("HYPRLAND_INSTANCE_SIGNATURE");
             env::remove_var("SWAYSOCK");
+            env::remove_var("NIRI_SOCKET");
         }
 
         assert_eq!(detect_wm(), WmType::Unknown);
     }
+
+    #[test]
+    fn test_detect_wm_sway_socket() {
+        let _guard = ENV_LOCK.lock().unwrap();
+
+        unsafe {
+            env::remove_var("XDG_CURRENT_DESKTOP");
+            env::remove_var("HYPRLAND_INSTANCE_SIGNATURE");
+            env::set_var("SWAYSOCK", "/tmp/sway-ipc.sock");
+        }
+        assert_eq!(detect_wm(), WmType::Sway);
+        unsafe {
+            env::remove_var("SWAYSOCK");
+        }
+    }
+
+    #[test]
+    fn test_detect_wm_niri_socket() {
+        let _guard = ENV_LOCK.lock().unwrap();
+
+        unsafe {
+            env::remove_var("XDG_CURRENT_DESKTOP");
+            env::remove_var("HYPRLAND_INSTANCE_SIGNATURE");
+            env::remove_var("SWAYSOCK");
+            env::set_var("NIRI_SOCKET", "/tmp/niri.sock");
+        }
+        assert_eq!(detect_wm(), WmType::Niri);
+        unsafe {
+            env::remove_var("NIRI_SOCKET");
+        }
+    }
 }
@@ -1,6 +1,8 @@
 //! CapyShell - Hyper-performant shell for Hyprland
 //!
-//! Single-process multi-window architecture with restart-on-hotplug.
+//! Single-process multi-window architecture. Removed monitors are handled
+//! in-process, a monitor added at runtime still restarts the shell because
+//! spell-framework cannot create windows once the event loop is running.
 
 mod event_bus;
 mod functions;
@@ -8,30 +10,48 @@ mod panel_manager;
 mod panels;
 mod services;
 
-use hyprland::data::{Monitor, Monitors};
-use hyprland::shared::HyprData;
-use log::{error, info};
+use log::warn;
 use std::error::Error;
 
 use panel_manager::PanelManager;
+use services::wm::MonitorInfo;
 
 use crate::panels::media_selector::MediaSelectorFactory;
+use crate::panels::overview::OverviewFactory;
 use crate::panels::taskbar::TaskbarFactory;
+use crate::panels::window_switcher::WindowSwitcherFactory;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    println!("Welcome to CapyShell!"); // TODO: add more info
+    // `capyshell msg <command>` controls the running shell instead of starting one
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(("msg", command)) = args
+        .split_first()
+        .map(|(first, rest)| (first.as_str(), rest))
+    {
+        return services::commands::send(&command.join(" "));
+    }
 
-    let monitors: Vec<Monitor> = match Monitors::get() {
-        Ok(monitors) => monitors.iter().cloned().collect(),
-        Err(e) => {
-            error!("Failed to get monitors: {}", e);
-            return Err(e.into());
-        }
-    };
+    println!("Welcome to CapyShell!"); // TODO: add more info
 
     // Start background services once before init of panels
     let service_status = services::start_all();
 
+    let monitors = if service_status.has_wm {
+        services::wm::get_monitors().unwrap_or_else(|e| {
+            warn!("Failed to get monitors from the window manager: {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let monitors = if monitors.is_empty() {
+        // Degraded mode: one set of panels on the compositor's default output
+        warn!("No monitors from the window manager, using the default output");
+        vec![MonitorInfo::default()]
+    } else {
+        monitors
+    };
+
     let mut wm = PanelManager::new();
 
     let taskbar_factory =
@@ -39,8 +59,14 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let media_selector_factory = MediaSelectorFactory::new();
 
+    let window_switcher_factory = WindowSwitcherFactory::new();
+
+    let overview_factory = OverviewFactory::new();
+
     wm.register_factory(taskbar_factory);
     wm.register_factory(media_selector_factory);
+    wm.register_factory(window_switcher_factory);
+    wm.register_factory(overview_factory);
 
     wm.start(&monitors)?;
 
@@ -1,5 +1,5 @@
 use crate::panel_manager::{PanelInstance, WindowConf};
-use hyprland::data::Monitor;
+use crate::services::wm::MonitorInfo;
 use std::error::Error;
 
 /// Trait that defines a factory for creating a specific type of window (e.g., Taskbar, Launcher).
@@ -9,7 +9,7 @@ pub trait PanelFactory {
 
     /// Generates window configurations based on the available monitors.
     /// Returns a list of (UniqueName, WindowConfig, Monitor).
-    fn generate_configs(&self, monitors: &[Monitor]) -> Vec<(String, WindowConf, Monitor)>;
+    fn generate_configs(&self, monitors: &[MonitorInfo]) -> Vec<(String, WindowConf, MonitorInfo)>;
 
     /// Initializes a window instance for a specific monitor and configuration.
     /// This is where the Slint UI is created and event handlers are attached.
@@ -17,6 +17,6 @@ pub trait PanelFactory {
     fn create_instance(
         &self,
         unique_name: &str,
-        monitor: &Monitor,
+        monitor: &MonitorInfo,
     ) -> Result<Box<dyn PanelInstance>, Box<dyn Error>>;
 }
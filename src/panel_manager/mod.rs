@@ -1,29 +1,126 @@
-use hyprland::data::Monitor;
-use log::{debug, info};
+use crate::services::wm::MonitorInfo;
+use log::{debug, error, info, warn};
 use spell_framework::{
-    layer_properties::WindowConf, slint_adapter::SpellMultiWinHandler, wayland_adapter::SpellWin,
+    layer_properties::WindowConf,
+    slint_adapter::SpellMultiWinHandler,
+    wayland_adapter::{SpellWin, WinHandle},
 };
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, Receiver, Sender};
 
 pub mod factory;
 
 pub use factory::PanelFactory;
 
+const MONITOR_POLL_INTERVAL_MS: u64 = 100;
+
+// Monitor events from the WM service, handled on the UI thread
+static MONITOR_EVENTS: OnceLock<Sender<MonitorEvent>> = OnceLock::new();
+
+thread_local! {
+    // Handles of the windows running in the event loop, by unique name
+    static WINDOW_HANDLES: RefCell<HashMap<String, WinHandle>> = RefCell::new(HashMap::new());
+    // Windows whose pointer input is disabled, all others take it in their whole area
+    static NO_POINTER_INPUT: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
 /// Trait representing a tangible window instance (mostly for keeping the UI handle alive).
 pub trait PanelInstance {
     fn on_show(&self) {}
     fn on_hide(&self) {}
+    /// Called when the monitor starts or stops showing a fullscreen window.
+    fn on_fullscreen(&self, _fullscreen: bool) {}
+}
+
+/// A change to a monitor while the shell is running.
+#[derive(Clone, Debug)]
+pub enum MonitorEvent {
+    Added(MonitorInfo),
+    Removed(String),
+    Fullscreen { monitor: String, fullscreen: bool },
+}
+
+/// Queue a monitor event for the panel manager. Can be called from any thread.
+pub fn send_monitor_event(event: MonitorEvent) {
+    match MONITOR_EVENTS.get() {
+        Some(sender) => {
+            let _ = sender.send(event);
+        }
+        None => debug!("Panel manager not started, ignoring {:?}", event),
+    }
+}
+
+/// Let a panel window take pointer input in its whole `width` x `height` area, or in none of it.
+/// Overlays disable it while closed and bars while hidden, so their transparent
+/// surface does not block what is below.
+pub fn set_pointer_input(name: &str, width: u32, height: u32, enabled: bool) {
+    let changed = NO_POINTER_INPUT.with_borrow_mut(|disabled| {
+        if enabled {
+            disabled.remove(name)
+        } else {
+            disabled.insert(name.to_string())
+        }
+    });
+    if !changed {
+        return;
+    }
+
+    WINDOW_HANDLES.with_borrow(|handles| {
+        let Some(handle) = handles.get(name) else {
+            debug!("No window '{}' to set pointer input on", name);
+            return;
+        };
+        let (width, height) = (width as i32, height as i32);
+        if enabled {
+            handle.add_input_region(0, 0, width, height);
+        } else {
+            handle.subtract_input_region(0, 0, width, height);
+        }
+    });
+}
+
+/// Creates and closes the layer shell windows that panels draw into.
+pub trait PanelHost {
+    /// Create a window. The panel instance for it is created right after.
+    fn open(&mut self, name: &str, conf: WindowConf) -> Result<(), Box<dyn Error>>;
+
+    /// Close a window whose panel instance was torn down.
+    fn close(&mut self, name: &str);
+}
+
+/// What applying a monitor event left to do.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MonitorChange {
+    Applied,
+    /// A new monitor needs windows, which only a restart can create for now.
+    NeedsRestart,
+}
+
+/// A panel instance alive on a monitor.
+struct LivePanel {
+    name: String,
+    instance: Box<dyn PanelInstance>,
 }
 
 /// The main manager that coordinates all windows.
+/// Panels are keyed by monitor, so hotplug only touches the affected monitor.
 pub struct PanelManager {
     factories: Vec<Box<dyn PanelFactory>>,
+    panels: HashMap<String, Vec<LivePanel>>,
+    // Monitors showing a fullscreen window
+    fullscreen: HashSet<String>,
 }
 
 impl PanelManager {
     pub fn new() -> Self {
         Self {
             factories: Vec::new(),
+            panels: HashMap::new(),
+            fullscreen: HashSet::new(),
         }
     }
 
@@ -31,62 +128,473 @@ impl PanelManager {
         self.factories.push(Box::new(factory));
     }
 
-    // Split the run to easier testing maybe? or just fix the ownership.
+    /// Names of the monitors that currently have panels, sorted.
+    pub fn monitors(&self) -> Vec<&str> {
+        let mut monitors: Vec<&str> = self.panels.keys().map(String::as_str).collect();
+        monitors.sort();
+        monitors
+    }
+
+    /// Open the windows of every factory on a monitor.
+    /// Returns the (FactoryIndex, UniqueName, Monitor) of each opened window.
+    fn open_windows(
+        &self,
+        monitor: &MonitorInfo,
+        host: &mut dyn PanelHost,
+    ) -> Result<Vec<(usize, String, MonitorInfo)>, Box<dyn Error>> {
+        if self.panels.contains_key(&monitor.name) {
+            debug!("Monitor '{}' already has panels", monitor.name);
+            return Ok(Vec::new());
+        }
+
+        let mut pending = Vec::new();
+        for (index, factory) in self.factories.iter().enumerate() {
+            for (name, conf, monitor) in factory.generate_configs(std::slice::from_ref(monitor)) {
+                host.open(&name, conf)?;
+                pending.push((index, name, monitor));
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Create the panel instances for windows opened by `open_windows`.
+    fn create_panels(
+        &mut self,
+        monitor_name: &str,
+        pending: Vec<(usize, String, MonitorInfo)>,
+    ) -> Result<(), Box<dyn Error>> {
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut live = Vec::new();
+        for (index, name, monitor) in pending {
+            let instance = self.factories[index].create_instance(&name, &monitor)?;
+            instance.on_show();
+            if self.fullscreen.contains(monitor_name) {
+                instance.on_fullscreen(true);
+            }
+            live.push(LivePanel { name, instance });
+        }
+
+        info!("Created {} panel(s) on '{}'", live.len(), monitor_name);
+        self.panels.insert(monitor_name.to_string(), live);
+        Ok(())
+    }
+
+    /// Tear down the panels on a monitor.
+    pub fn remove_monitor(&mut self, monitor_name: &str, host: &mut dyn PanelHost) {
+        let Some(live) = self.panels.remove(monitor_name) else {
+            debug!("Monitor '{}' has no panels", monitor_name);
+            return;
+        };
+
+        info!("Removing {} panel(s) from '{}'", live.len(), monitor_name);
+        for panel in live {
+            panel.instance.on_hide();
+            host.close(&panel.name);
+        }
+    }
+
+    /// Tell the panels on a monitor whether it shows a fullscreen window.
+    pub fn set_fullscreen(&mut self, monitor_name: &str, fullscreen: bool) {
+        let changed = if fullscreen {
+            self.fullscreen.insert(monitor_name.to_string())
+        } else {
+            self.fullscreen.remove(monitor_name)
+        };
+        if !changed {
+            return;
+        }
+
+        debug!("Monitor '{}' fullscreen: {}", monitor_name, fullscreen);
+        for panel in self.panels.get(monitor_name).into_iter().flatten() {
+            panel.instance.on_fullscreen(fullscreen);
+        }
+    }
+
+    /// Apply a monitor event.
+    /// Removal and fullscreen are handled in-process. spell-framework cannot create
+    /// windows once the event loop runs, so a new monitor asks for a restart.
+    pub fn handle_monitor_event(
+        &mut self,
+        event: MonitorEvent,
+        host: &mut dyn PanelHost,
+    ) -> MonitorChange {
+        match event {
+            MonitorEvent::Added(monitor) => {
+                if self.panels.contains_key(&monitor.name) {
+                    debug!("Monitor '{}' already has panels", monitor.name);
+                    return MonitorChange::Applied;
+                }
+                MonitorChange::NeedsRestart
+            }
+            MonitorEvent::Removed(name) => {
+                self.remove_monitor(&name, host);
+                MonitorChange::Applied
+            }
+            MonitorEvent::Fullscreen {
+                monitor,
+                fullscreen,
+            } => {
+                self.set_fullscreen(&monitor, fullscreen);
+                MonitorChange::Applied
+            }
+        }
+    }
+
+    /// Create panels on all monitors and run the event loop. Blocks until the shell exits.
+    pub fn start(self, monitors: &[MonitorInfo]) -> Result<(), Box<dyn Error>> {
+        let manager = Rc::new(RefCell::new(self));
+        let host = Rc::new(RefCell::new(SpellHost::default()));
+
+        // 1. Configuration phase
+        let mut opened = Vec::new();
+        for monitor in monitors {
+            let pending = manager
+                .borrow()
+                .open_windows(monitor, &mut *host.borrow_mut())?;
+            opened.push((monitor.name.clone(), pending));
+        }
+
+        // 2. Window creation phase, the UI can only be created once the windows exist
+        let windows = host.borrow_mut().take_windows();
+
+        // 3. UI initialization phase
+        for (monitor_name, pending) in opened {
+            manager.borrow_mut().create_panels(&monitor_name, pending)?;
+        }
+
+        // 4. Hotplug and fullscreen handling on the UI thread
+        let (sender, receiver) = mpsc::channel();
+        if MONITOR_EVENTS.set(sender).is_err() {
+            warn!("Panel manager started twice, monitor events go to the first instance");
+        }
+        let monitor_timer = watch_monitor_events(receiver, manager.clone(), host.clone());
+
+        // 5. Event loop phase
+        let result = run(windows);
+
+        drop(monitor_timer);
+        result
+    }
 }
 
-/// Helper to run the main loop, we might need to expose a method that takes the instances
-/// and the windows and runs them.
-/// But `PanelManager` holds factories, it shouldn't hold the runtime state necessarily forever?
-/// Actually `run` should block.
+/// Poll monitor events and apply them to the manager.
+fn watch_monitor_events(
+    receiver: Receiver<MonitorEvent>,
+    manager: Rc<RefCell<PanelManager>>,
+    host: Rc<RefCell<SpellHost>>,
+) -> slint::Timer {
+    let timer = slint::Timer::default();
+    timer.start(
+        slint::TimerMode::Repeated,
+        std::time::Duration::from_millis(MONITOR_POLL_INTERVAL_MS),
+        move || {
+            for event in receiver.try_iter() {
+                debug!("Monitor event: {:?}", event);
+                let change = manager
+                    .borrow_mut()
+                    .handle_monitor_event(event, &mut *host.borrow_mut());
+                if change == MonitorChange::NeedsRestart {
+                    info!("New monitor connected, restarting to create its panels");
+                    restart();
+                }
+            }
+        },
+    );
+    timer
+}
+
+/// Windows created through spell-framework.
 ///
-/// Let's refine `run`.
-impl PanelManager {
-    // ... (new, register_factory)
+/// spell-framework cannot add windows to an event loop that is already running,
+/// so windows can only be opened before [`SpellHost::take_windows`].
+#[derive(Default)]
+struct SpellHost {
+    pending: Vec<(String, WindowConf)>,
+    running: bool,
+}
 
-    pub fn start(&self, monitors: &[Monitor]) -> Result<(), Box<dyn Error>> {
-        // 1. Configuration Phase
-        let mut factory_configs: Vec<(&Box<dyn PanelFactory>, Vec<(String, WindowConf, Monitor)>)> =
-            Vec::new();
+impl SpellHost {
+    /// Hand the pending windows over to the event loop.
+    fn take_windows(&mut self) -> Vec<SpellWin> {
+        self.running = true;
+        let configs: Vec<(&str, WindowConf)> = self
+            .pending
+            .iter()
+            .map(|(name, conf)| (name.as_str(), conf.clone()))
+            .collect();
+        let windows = SpellMultiWinHandler::conjure_spells(configs);
 
-        for factory in &self.factories {
-            let configs = factory.generate_configs(monitors);
-            if !configs.is_empty() {
-                factory_configs.push((factory, configs));
+        // Windows are returned in the order of their configs
+        WINDOW_HANDLES.with_borrow_mut(|handles| {
+            for ((name, _), window) in self.pending.iter().zip(&windows) {
+                handles.insert(name.clone(), window.get_handler());
             }
+        });
+        self.pending.clear();
+        windows
+    }
+}
+
+impl PanelHost for SpellHost {
+    fn open(&mut self, name: &str, conf: WindowConf) -> Result<(), Box<dyn Error>> {
+        if self.running {
+            return Err(format!("cannot open '{}' while the event loop is running", name).into());
         }
+        self.pending.push((name.to_string(), conf));
+        Ok(())
+    }
 
-        let mut flat_configs: Vec<(&str, WindowConf)> = Vec::new();
-        for (_, configs) in &factory_configs {
-            for (name, conf, _) in configs {
-                flat_configs.push((name.as_str(), conf.clone()));
-            }
+    fn close(&mut self, name: &str) {
+        // The compositor closes layer surfaces of removed outputs, dropping the instance is enough
+        WINDOW_HANDLES.with_borrow_mut(|handles| handles.remove(name));
+        NO_POINTER_INPUT.with_borrow_mut(|disabled| disabled.remove(name));
+        debug!("Closed panel window '{}'", name);
+    }
+}
+
+fn run(windows: Vec<SpellWin>) -> Result<(), Box<dyn Error>> {
+    use spell_framework::enchant_spells;
+
+    let num_windows = windows.len();
+    let states: Vec<_> = (0..num_windows).map(|_| None).collect();
+    let callbacks: Vec<_> = (0..num_windows).map(|_| None).collect();
+
+    enchant_spells(windows, states, callbacks)?;
+
+    Ok(())
+}
+
+/// Restart CapyShell, used when a new monitor cannot get panels in-process.
+fn restart() {
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    let exe = match std::env::current_exe() {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Failed to get current executable: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    info!("Restarting CapyShell...");
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let err = Command::new(exe).args(args).exec();
+
+    error!("Failed to restart CapyShell: {}", err);
+    std::process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spell_framework::layer_properties::{BoardType, LayerAnchor, LayerType};
+    use std::cell::Cell;
+
+    /// Host recording window operations.
+    #[derive(Default)]
+    struct FakeHost {
+        log: Vec<String>,
+    }
+
+    impl PanelHost for FakeHost {
+        fn open(&mut self, name: &str, _conf: WindowConf) -> Result<(), Box<dyn Error>> {
+            self.log.push(format!("open {}", name));
+            Ok(())
         }
 
-        // 2. Window Creation Phase
-        let windows: Vec<SpellWin> = SpellMultiWinHandler::conjure_spells(flat_configs.clone());
+        fn close(&mut self, name: &str) {
+            self.log.push(format!("close {}", name));
+        }
+    }
+
+    /// Factory creating one panel per monitor and counting live and hidden instances.
+    struct FakeFactory {
+        alive: Rc<Cell<usize>>,
+        hidden: Rc<Cell<usize>>,
+    }
 
-        // 3. UI Initialization Phase
-        // We match them by order.
-        let mut ui_instances: Vec<Box<dyn PanelInstance>> = Vec::new();
+    struct FakeInstance {
+        name: String,
+        alive: Rc<Cell<usize>>,
+        hidden: Rc<Cell<usize>>,
+    }
 
-        // We iterate through our factory config groupings, they should match the flattened list structure.
-        for (factory, configs) in &factory_configs {
-            for (name, _, monitor) in configs {
-                let instance = factory.create_instance(name, monitor)?;
-                ui_instances.push(instance);
+    impl PanelInstance for FakeInstance {
+        fn on_fullscreen(&self, fullscreen: bool) {
+            if fullscreen {
+                self.hidden.set(self.hidden.get() + 1);
+            } else {
+                self.hidden.set(self.hidden.get() - 1);
             }
+            // Like the taskbar, a hidden panel lets clicks through
+            set_pointer_input(&self.name, 1920, 10, !fullscreen);
         }
+    }
 
-        // 4. Event Loop Phase
-        let num_windows = windows.len();
-        let states: Vec<_> = (0..num_windows).map(|_| None).collect();
-        let callbacks: Vec<_> = (0..num_windows).map(|_| None).collect();
+    impl Drop for FakeInstance {
+        fn drop(&mut self) {
+            self.alive.set(self.alive.get() - 1);
+        }
+    }
 
-        use spell_framework::enchant_spells;
-        // imports needed
+    impl PanelFactory for FakeFactory {
+        fn type_id(&self) -> &str {
+            "fake"
+        }
 
-        enchant_spells(windows, states, callbacks)?;
+        fn generate_configs(
+            &self,
+            monitors: &[MonitorInfo],
+        ) -> Vec<(String, WindowConf, MonitorInfo)> {
+            monitors
+                .iter()
+                .map(|monitor| {
+                    let conf = WindowConf::new(
+                        monitor.width as u32,
+                        10,
+                        (Some(LayerAnchor::TOP), None),
+                        (0, 0, 0, 0),
+                        LayerType::Top,
+                        BoardType::None,
+                        None,
+                        Some(monitor.name.clone()),
+                    );
+                    (format!("fake-{}", monitor.name), conf, monitor.clone())
+                })
+                .collect()
+        }
 
-        Ok(())
+        fn create_instance(
+            &self,
+            unique_name: &str,
+            _monitor: &MonitorInfo,
+        ) -> Result<Box<dyn PanelInstance>, Box<dyn Error>> {
+            self.alive.set(self.alive.get() + 1);
+            Ok(Box::new(FakeInstance {
+                name: unique_name.to_string(),
+                alive: self.alive.clone(),
+                hidden: self.hidden.clone(),
+            }))
+        }
+    }
+
+    fn monitor(name: &str) -> MonitorInfo {
+        MonitorInfo {
+            name: name.to_string(),
+            width: 1920,
+            height: 1080,
+            ..Default::default()
+        }
+    }
+
+    /// Windows whose pointer input is disabled, sorted.
+    fn without_pointer_input() -> Vec<String> {
+        let mut names: Vec<String> =
+            NO_POINTER_INPUT.with_borrow(|disabled| disabled.iter().cloned().collect());
+        names.sort();
+        names
+    }
+
+    fn manager(alive: &Rc<Cell<usize>>) -> PanelManager {
+        manager_with_hidden(alive, &Rc::new(Cell::new(0)))
+    }
+
+    fn manager_with_hidden(alive: &Rc<Cell<usize>>, hidden: &Rc<Cell<usize>>) -> PanelManager {
+        let mut manager = PanelManager::new();
+        manager.register_factory(FakeFactory {
+            alive: alive.clone(),
+            hidden: hidden.clone(),
+        });
+        manager
+    }
+
+    /// Create the panels of a monitor like the startup phase of `PanelManager::start`.
+    fn add_monitor(manager: &mut PanelManager, monitor: &MonitorInfo, host: &mut FakeHost) {
+        let pending = manager.open_windows(monitor, host).unwrap();
+        manager.create_panels(&monitor.name, pending).unwrap();
+    }
+
+    #[test]
+    fn test_removal_only_touches_affected_monitor() {
+        let alive = Rc::new(Cell::new(0));
+        let mut manager = manager(&alive);
+        let mut host = FakeHost::default();
+        add_monitor(&mut manager, &monitor("DP-1"), &mut host);
+        add_monitor(&mut manager, &monitor("DP-2"), &mut host);
+
+        let removed = MonitorEvent::Removed("DP-1".to_string());
+        assert_eq!(
+            manager.handle_monitor_event(removed.clone(), &mut host),
+            MonitorChange::Applied
+        );
+        // Duplicate events are ignored
+        assert_eq!(
+            manager.handle_monitor_event(removed, &mut host),
+            MonitorChange::Applied
+        );
+
+        assert_eq!(manager.monitors(), ["DP-2"]);
+        assert_eq!(alive.get(), 1);
+        assert_eq!(
+            host.log,
+            ["open fake-DP-1", "open fake-DP-2", "close fake-DP-1"]
+        );
+    }
+
+    #[test]
+    fn test_new_monitor_needs_restart() {
+        let alive = Rc::new(Cell::new(0));
+        let mut manager = manager(&alive);
+        let mut host = FakeHost::default();
+        add_monitor(&mut manager, &monitor("DP-1"), &mut host);
+
+        // A monitor that already has panels needs nothing
+        assert_eq!(
+            manager.handle_monitor_event(MonitorEvent::Added(monitor("DP-1")), &mut host),
+            MonitorChange::Applied
+        );
+        // No window is opened for a new one, the restart creates them
+        assert_eq!(
+            manager.handle_monitor_event(MonitorEvent::Added(monitor("HDMI-A-1")), &mut host),
+            MonitorChange::NeedsRestart
+        );
+
+        assert_eq!(manager.monitors(), ["DP-1"]);
+        assert_eq!(alive.get(), 1);
+        assert_eq!(host.log, ["open fake-DP-1"]);
+    }
+
+    #[test]
+    fn test_fullscreen_hides_panels_of_that_monitor() {
+        let alive = Rc::new(Cell::new(0));
+        let hidden = Rc::new(Cell::new(0));
+        let mut manager = manager_with_hidden(&alive, &hidden);
+        let mut host = FakeHost::default();
+        add_monitor(&mut manager, &monitor("DP-1"), &mut host);
+        add_monitor(&mut manager, &monitor("DP-2"), &mut host);
+
+        let fullscreen = |monitor: &str, fullscreen| MonitorEvent::Fullscreen {
+            monitor: monitor.to_string(),
+            fullscreen,
+        };
+        manager.handle_monitor_event(fullscreen("DP-1", true), &mut host);
+        manager.handle_monitor_event(fullscreen("DP-1", true), &mut host);
+        assert_eq!(hidden.get(), 1);
+        assert_eq!(without_pointer_input(), ["fake-DP-1"]);
+
+        // Panels created on a fullscreen monitor start hidden
+        manager.set_fullscreen("HDMI-A-1", true);
+        add_monitor(&mut manager, &monitor("HDMI-A-1"), &mut host);
+        assert_eq!(hidden.get(), 2);
+        assert_eq!(without_pointer_input(), ["fake-DP-1", "fake-HDMI-A-1"]);
+
+        manager.handle_monitor_event(fullscreen("DP-1", false), &mut host);
+        assert_eq!(hidden.get(), 1);
+        assert_eq!(without_pointer_input(), ["fake-HDMI-A-1"]);
     }
 }
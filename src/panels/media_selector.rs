@@ -1,16 +1,22 @@
 use std::error::Error;
+use std::rc::Rc;
 
-use hyprland::data::Monitor;
+use capy_mpris::{MprisData, PlayerCommand};
 use log::info;
+use slint::{ComponentHandle, ModelRc, VecModel};
 use spell_framework::layer_properties::{BoardType, LayerAnchor, LayerType, WindowConf};
 
 use crate::panel_manager::{PanelFactory, PanelInstance};
+use crate::services::media;
+use crate::services::wm::MonitorInfo;
 mod slint_media_selector {
     include!(concat!(env!("OUT_DIR"), "/media_selector.rs"));
     pub use slint_generatedMediaSelector::*;
 }
 pub use self::slint_media_selector::*;
 
+const PLAYERS_POLL_INTERVAL_MS: u64 = 250;
+
 pub struct MediaSelectorFactory {}
 
 impl MediaSelectorFactory {
@@ -24,14 +30,14 @@ impl PanelFactory for MediaSelectorFactory {
         "music-player-selector"
     }
 
-    fn generate_configs(&self, monitors: &[Monitor]) -> Vec<(String, WindowConf, Monitor)> {
+    fn generate_configs(&self, monitors: &[MonitorInfo]) -> Vec<(String, WindowConf, MonitorInfo)> {
         let mut configs = Vec::new();
         for monitor in monitors {
             configs.push((
                 String::from("music-player-selector"),
                 WindowConf::new(
                     300,
-                    100,
+                    240,
                     (Some(LayerAnchor::TOP | LayerAnchor::LEFT), None),
                     (0, 0, 0, 300),
                     LayerType::Top,
@@ -48,7 +54,7 @@ impl PanelFactory for MediaSelectorFactory {
     fn create_instance(
         &self,
         unique_name: &str,
-        monitor: &Monitor,
+        monitor: &MonitorInfo,
     ) -> Result<Box<dyn PanelInstance>, Box<dyn Error>> {
         info!(
             "Creating MediaSelector instance for monitor '{}' ({})",
@@ -57,12 +63,64 @@ impl PanelFactory for MediaSelectorFactory {
 
         let ui = MediaSelector::new()?;
 
-        Ok(Box::new(MediaSelectorInstance { _ui: ui }))
+        ui.on_select(|short_name| {
+            media::send_command(PlayerCommand::SwitchSource(short_name.to_string()));
+        });
+        ui.on_play_pause(|bus_name| {
+            media::send_player_command(&bus_name, PlayerCommand::PlayPause);
+        });
+
+        // Rebuild the list whenever a player changed
+        let ui_weak = ui.as_weak();
+        let mut shown_version = None;
+        let players_timer = slint::Timer::default();
+        players_timer.start(
+            slint::TimerMode::Repeated,
+            std::time::Duration::from_millis(PLAYERS_POLL_INTERVAL_MS),
+            move || {
+                let version = media::players_version();
+                if shown_version == Some(version) {
+                    return;
+                }
+                shown_version = Some(version);
+                if let Some(ui) = ui_weak.upgrade() {
+                    update_ui(&ui);
+                }
+            },
+        );
+
+        Ok(Box::new(MediaSelectorInstance {
+            _ui: ui,
+            _players_timer: players_timer,
+        }))
     }
 }
 
 struct MediaSelectorInstance {
     _ui: MediaSelector,
+    _players_timer: slint::Timer,
 }
 
 impl PanelInstance for MediaSelectorInstance {}
+
+fn update_ui(ui: &MediaSelector) {
+    let active = media::active_source();
+    let players: Vec<MediaPlayer> = media::players()
+        .iter()
+        .map(|data| to_slint(data, active.as_deref()))
+        .collect();
+    ui.set_players(ModelRc::from(Rc::new(VecModel::from(players))));
+}
+
+fn to_slint(data: &MprisData, active: Option<&str>) -> MediaPlayer {
+    MediaPlayer {
+        bus_name: data.source_bus_name.clone().into(),
+        short_name: data.source_name.clone().into(),
+        name: data.source_identity.clone().into(),
+        title: data.title.clone().into(),
+        artist: data.artist.clone().into(),
+        is_playing: data.status.is_playing(),
+        is_active: active == Some(data.source_bus_name.as_str()),
+        can_play_pause: data.can_play_pause(),
+    }
+}
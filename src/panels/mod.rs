@@ -1,5 +1,7 @@
 pub mod media_selector;
+pub mod overview;
 pub mod taskbar;
+pub mod window_switcher;
 
 // Add more panels as you create them:
 // pub mod menu;
@@ -0,0 +1,470 @@
+//! Workspace overview.
+//!
+//! A centered overlay listing every workspace of every monitor together with
+//! its windows. Clicking a window focuses it, dragging it onto another workspace
+//! moves it there. Toggled with `capyshell msg overview toggle`, only the
+//! overview on the focused monitor opens. While closed the surface takes no
+//! pointer input.
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::rc::Rc;
+
+use log::{debug, info, warn};
+use slint::{ComponentHandle, ModelRc, VecModel};
+use spell_framework::layer_properties::{BoardType, LayerType, WindowConf};
+
+use crate::panel_manager::{self, PanelFactory, PanelInstance};
+use crate::panels::taskbar::window_icon;
+use crate::services::commands::{self, Command, OverviewCommand};
+use crate::services::wm::{
+    self, EventFilter, MonitorInfo, WindowInfo, WmEventKind, WorkspaceInfo, WorkspaceState,
+    WorkspacesStatus,
+};
+mod slint_overview {
+    include!(concat!(env!("OUT_DIR"), "/overview.rs"));
+    pub use slint_generatedOverview::*;
+}
+pub use self::slint_overview::*;
+
+// Share of the monitor covered by the overview
+const OVERVIEW_SIZE_FACTOR: f32 = 0.8;
+// Used when the monitor size is unknown
+const DEFAULT_WIDTH: u32 = 1280;
+const DEFAULT_HEIGHT: u32 = 720;
+const OPEN_POLL_INTERVAL_MS: u64 = 50;
+const CLOSED_POLL_INTERVAL_MS: u64 = 100;
+
+pub struct OverviewFactory {}
+
+impl OverviewFactory {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PanelFactory for OverviewFactory {
+    fn type_id(&self) -> &str {
+        "overview"
+    }
+
+    fn generate_configs(&self, monitors: &[MonitorInfo]) -> Vec<(String, WindowConf, MonitorInfo)> {
+        monitors
+            .iter()
+            .map(|monitor| {
+                let name = format!("overview-{}", monitor.name);
+                let (width, height) = overview_size(monitor);
+                // No anchors, layer shell centers the surface on the output
+                let conf = WindowConf::new(
+                    width,
+                    height,
+                    (None, None),
+                    (0, 0, 0, 0),
+                    LayerType::Overlay,
+                    BoardType::None,
+                    None,
+                    (!monitor.name.is_empty()).then(|| monitor.name.clone()),
+                );
+                (name, conf, monitor.clone())
+            })
+            .collect()
+    }
+
+    fn create_instance(
+        &self,
+        unique_name: &str,
+        monitor: &MonitorInfo,
+    ) -> Result<Box<dyn PanelInstance>, Box<dyn Error>> {
+        info!(
+            "Creating Overview instance for monitor '{}' ({})",
+            monitor.name, unique_name
+        );
+
+        let ui = Overview::new()?;
+        let (width, height) = overview_size(monitor);
+        let open = Rc::new(OpenState {
+            open: Cell::new(false),
+            window: unique_name.to_string(),
+            size: (width, height),
+        });
+        panel_manager::set_pointer_input(unique_name, width, height, false);
+
+        let ui_weak = ui.as_weak();
+        let open_for_close = open.clone();
+        ui.on_close(move || {
+            if let Some(ui) = ui_weak.upgrade() {
+                set_open(&ui, &open_for_close, false);
+            }
+        });
+
+        let ui_weak = ui.as_weak();
+        let open_for_focus = open.clone();
+        ui.on_focus_window(move |address| {
+            debug!("Focusing window {} from the overview", address);
+            if let Err(e) = wm::focus_window(&address) {
+                warn!("Failed to focus window {}: {}", address, e);
+            }
+            if let Some(ui) = ui_weak.upgrade() {
+                set_open(&ui, &open_for_focus, false);
+            }
+        });
+
+        let ui_weak = ui.as_weak();
+        let open_for_switch = open.clone();
+        ui.on_switch_workspace(move |id| {
+            if let Err(e) = wm::switch_workspace(id) {
+                warn!("Failed to switch to workspace {}: {}", id, e);
+            }
+            if let Some(ui) = ui_weak.upgrade() {
+                set_open(&ui, &open_for_switch, false);
+            }
+        });
+
+        // The overview stays open, the window events refresh it once the move is done
+        ui.on_move_window(|address, workspace_id| {
+            debug!("Moving window {} to workspace {}", address, workspace_id);
+            if let Err(e) = wm::move_window_to_workspace(&address, workspace_id) {
+                warn!(
+                    "Failed to move window {} to workspace {}: {}",
+                    address, workspace_id, e
+                );
+            }
+        });
+
+        let mut command_rx = commands::subscribe();
+        let wm_events = wm::subscribe_with(EventFilter::new().kinds([
+            WmEventKind::WorkspacesChanged,
+            WmEventKind::ActiveWindowChanged,
+            WmEventKind::WindowOpened,
+            WmEventKind::WindowClosed,
+            WmEventKind::WindowMoved,
+        ]));
+        let monitor_name = monitor.name.clone();
+        let ui_weak = ui.as_weak();
+
+        let poll_timer = Rc::new(slint::Timer::default());
+        let timer_weak = Rc::downgrade(&poll_timer);
+        // Whether the timer runs at the open interval, the overview also closes from callbacks
+        let mut polling_open = false;
+        poll_timer.start(
+            slint::TimerMode::Repeated,
+            poll_interval(false),
+            move || {
+                let Some(ui) = ui_weak.upgrade() else {
+                    return;
+                };
+
+                while let Ok(command) = command_rx.try_recv() {
+                    let Command::Overview(command) = command else {
+                        continue;
+                    };
+                    let should_open = match command {
+                        OverviewCommand::Toggle => !open.get(),
+                        OverviewCommand::Open => true,
+                        OverviewCommand::Close => false,
+                    };
+                    if should_open && !wm::is_focused_monitor(&monitor_name) {
+                        continue;
+                    }
+                    set_open(&ui, &open, should_open);
+                }
+
+                // Events are drained while closed too, the list is rebuilt when opening
+                let changed = wm_events.try_iter().count() > 0;
+                if changed && open.get() {
+                    update_ui(&ui);
+                }
+
+                if open.get() != polling_open {
+                    polling_open = open.get();
+                    if let Some(timer) = timer_weak.upgrade() {
+                        timer.set_interval(poll_interval(polling_open));
+                    }
+                }
+            },
+        );
+
+        Ok(Box::new(OverviewInstance {
+            _ui: ui,
+            _poll_timer: poll_timer,
+        }))
+    }
+}
+
+struct OverviewInstance {
+    _ui: Overview,
+    // Stops handling commands once the monitor is removed
+    _poll_timer: Rc<slint::Timer>,
+}
+
+impl PanelInstance for OverviewInstance {}
+
+fn poll_interval(open: bool) -> std::time::Duration {
+    std::time::Duration::from_millis(if open {
+        OPEN_POLL_INTERVAL_MS
+    } else {
+        CLOSED_POLL_INTERVAL_MS
+    })
+}
+
+/// Logical size of the overview on a monitor.
+fn overview_size(monitor: &MonitorInfo) -> (u32, u32) {
+    if monitor.width <= 0 || monitor.height <= 0 {
+        return (DEFAULT_WIDTH, DEFAULT_HEIGHT);
+    }
+    let scale = if monitor.scale > 0.0 {
+        monitor.scale
+    } else {
+        1.0
+    };
+    let size = |pixels: i32| (pixels as f32 / scale * OVERVIEW_SIZE_FACTOR) as u32;
+    (size(monitor.width), size(monitor.height))
+}
+
+/// Whether the overview is open, and the window whose pointer input follows it.
+struct OpenState {
+    open: Cell<bool>,
+    window: String,
+    size: (u32, u32),
+}
+
+impl OpenState {
+    fn get(&self) -> bool {
+        self.open.get()
+    }
+}
+
+fn set_open(ui: &Overview, open: &OpenState, value: bool) {
+    if value != open.get() {
+        if value {
+            update_ui(ui);
+        }
+        let (width, height) = open.size;
+        panel_manager::set_pointer_input(&open.window, width, height, value);
+    }
+    open.open.set(value);
+    ui.set_open(value);
+}
+
+/// Workspaces of one monitor with their windows.
+struct MonitorGroup {
+    name: String,
+    workspaces: Vec<WorkspaceGroup>,
+}
+
+struct WorkspaceGroup {
+    info: WorkspaceInfo,
+    windows: Vec<WindowInfo>,
+}
+
+/// Sort the windows into the workspaces of each monitor, keeping their order.
+/// Workspaces no monitor reports, e.g. past the range of the layout, are added
+/// after the others to the monitor of their windows.
+fn group_windows(statuses: Vec<WorkspacesStatus>, windows: &[WindowInfo]) -> Vec<MonitorGroup> {
+    let mut groups: Vec<MonitorGroup> = statuses
+        .into_iter()
+        .map(|status| MonitorGroup {
+            name: status.monitor_name,
+            workspaces: status
+                .workspaces
+                .into_iter()
+                .chain(status.special)
+                .map(|info| WorkspaceGroup {
+                    windows: windows
+                        .iter()
+                        .filter(|w| w.workspace_id == info.absolute_id)
+                        .cloned()
+                        .collect(),
+                    info,
+                })
+                .collect(),
+        })
+        .collect();
+
+    let reported: HashSet<i32> = groups
+        .iter()
+        .flat_map(|group| &group.workspaces)
+        .map(|workspace| workspace.info.absolute_id)
+        .collect();
+    let mut unreported: BTreeMap<(&str, i32), Vec<WindowInfo>> = BTreeMap::new();
+    for window in windows
+        .iter()
+        .filter(|w| !reported.contains(&w.workspace_id))
+    {
+        unreported
+            .entry((window.monitor.as_str(), window.workspace_id))
+            .or_default()
+            .push(window.clone());
+    }
+
+    for ((monitor, absolute_id), windows) in unreported {
+        let index = match groups.iter().position(|group| group.name == monitor) {
+            Some(index) => index,
+            None => {
+                groups.push(MonitorGroup {
+                    name: monitor.to_string(),
+                    workspaces: Vec::new(),
+                });
+                groups.len() - 1
+            }
+        };
+        groups[index].workspaces.push(WorkspaceGroup {
+            info: WorkspaceInfo {
+                id: absolute_id,
+                absolute_id,
+                name: absolute_id.to_string(),
+                occupied: true,
+                ..Default::default()
+            },
+            windows,
+        });
+    }
+    groups
+}
+
+fn update_ui(ui: &Overview) {
+    let focused = wm::hyprland_wm::active_window::get_active_window().address;
+    let groups = group_windows(wm::get_all_workspaces_status(), &wm::get_windows());
+
+    let monitors: Vec<OverviewMonitor> = groups
+        .into_iter()
+        .map(|group| OverviewMonitor {
+            name: group.name.into(),
+            workspaces: model(
+                group
+                    .workspaces
+                    .into_iter()
+                    .map(|workspace| to_slint_workspace(workspace, &focused))
+                    .collect(),
+            ),
+        })
+        .collect();
+    ui.set_monitors(model(monitors));
+}
+
+fn to_slint_workspace(workspace: WorkspaceGroup, focused: &str) -> OverviewWorkspace {
+    let windows = workspace
+        .windows
+        .iter()
+        .map(|window| OverviewWindow {
+            address: window.address.clone().into(),
+            app: window.class.clone().into(),
+            title: window.title.clone().into(),
+            icon: window_icon(window),
+            focused: window.address == focused,
+        })
+        .collect();
+
+    OverviewWorkspace {
+        id: workspace.info.absolute_id,
+        name: workspace.info.name.into(),
+        active: matches!(
+            workspace.info.state,
+            WorkspaceState::Active | WorkspaceState::Visible
+        ),
+        windows: model(windows),
+    }
+}
+
+fn model<T: Clone + 'static>(items: Vec<T>) -> ModelRc<T> {
+    ModelRc::from(Rc::new(VecModel::from(items)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(absolute_id: i32) -> WorkspaceInfo {
+        WorkspaceInfo {
+            id: absolute_id,
+            absolute_id,
+            ..Default::default()
+        }
+    }
+
+    fn window(address: &str, workspace_id: i32, monitor: &str) -> WindowInfo {
+        WindowInfo {
+            address: address.to_string(),
+            workspace_id,
+            monitor: monitor.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_windows_grouped_by_workspace() {
+        let statuses = vec![
+            WorkspacesStatus {
+                monitor_name: "DP-1".to_string(),
+                workspaces: vec![workspace(1), workspace(2)],
+                special: vec![workspace(-98)],
+            },
+            WorkspacesStatus {
+                monitor_name: "HDMI-A-1".to_string(),
+                workspaces: vec![workspace(3)],
+                ..Default::default()
+            },
+        ];
+        let windows = [
+            window("0xa", 1, "DP-1"),
+            window("0xb", 3, "HDMI-A-1"),
+            window("0xc", 1, "DP-1"),
+            window("0xd", -98, "DP-1"),
+        ];
+
+        let groups = group_windows(statuses, &windows);
+        let addresses = |group: &WorkspaceGroup| -> Vec<String> {
+            group.windows.iter().map(|w| w.address.clone()).collect()
+        };
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "DP-1");
+        assert_eq!(addresses(&groups[0].workspaces[0]), ["0xa", "0xc"]);
+        assert!(groups[0].workspaces[1].windows.is_empty());
+        assert_eq!(addresses(&groups[0].workspaces[2]), ["0xd"]);
+        assert_eq!(addresses(&groups[1].workspaces[0]), ["0xb"]);
+    }
+
+    #[test]
+    fn test_unreported_workspaces_get_their_own_entry() {
+        let statuses = vec![WorkspacesStatus {
+            monitor_name: "DP-1".to_string(),
+            workspaces: vec![workspace(1)],
+            ..Default::default()
+        }];
+        // Past the per-monitor range of the layout, and on a monitor without a status
+        let windows = [
+            window("0xa", 15, "DP-1"),
+            window("0xb", 1, "DP-1"),
+            window("0xc", 12, "DP-1"),
+            window("0xd", 15, "DP-1"),
+            window("0xe", 21, "HDMI-A-1"),
+        ];
+
+        let groups = group_windows(statuses, &windows);
+        let workspaces = |group: &MonitorGroup| -> Vec<(i32, Vec<String>)> {
+            group
+                .workspaces
+                .iter()
+                .map(|w| {
+                    let addresses = w.windows.iter().map(|w| w.address.clone()).collect();
+                    (w.info.absolute_id, addresses)
+                })
+                .collect()
+        };
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            workspaces(&groups[0]),
+            [
+                (1, vec!["0xb".to_string()]),
+                (12, vec!["0xc".to_string()]),
+                (15, vec!["0xa".to_string(), "0xd".to_string()]),
+            ]
+        );
+        assert_eq!(groups[1].name, "HDMI-A-1");
+        assert_eq!(workspaces(&groups[1]), [(21, vec!["0xe".to_string()])]);
+        assert_eq!(groups[0].workspaces[2].info.name, "15");
+    }
+}
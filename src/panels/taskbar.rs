@@ -6,10 +6,9 @@ use std::path::PathBuf;
 
 use crate::panels::taskbar::events::TaskbarEvent;
 
-use crate::panel_manager::{PanelFactory, PanelInstance};
+use crate::panel_manager::{self, PanelFactory, PanelInstance};
 use crate::services;
-use crate::services::wm::hyprland_wm;
-use hyprland::data::Monitor;
+use crate::services::wm::{MonitorInfo, WindowInfo, hyprland_wm};
 use slint::ComponentHandle;
 use spell_framework::layer_properties::{BoardType, LayerAnchor, LayerType, WindowConf};
 use std::error::Error;
@@ -25,6 +24,7 @@ pub mod bluetooth;
 pub mod clock;
 pub mod distro_icon;
 pub mod events;
+pub mod keyboard;
 pub mod media;
 pub mod network;
 pub mod volume;
@@ -67,6 +67,16 @@ pub fn load_icon(path: &std::path::Path) -> Option<Image> {
     })
 }
 
+/// Icon of a window, resolved from its class when capy-wm did not find one.
+pub fn window_icon(window: &WindowInfo) -> Image {
+    window
+        .icon_path
+        .clone()
+        .or_else(|| services::apps::get_icon(&window.class))
+        .and_then(|path| load_icon(&path))
+        .unwrap_or_default()
+}
+
 pub struct TaskbarFactory {
     has_battery: bool,
     has_bluetooth: bool,
@@ -86,7 +96,7 @@ impl PanelFactory for TaskbarFactory {
         "taskbar"
     }
 
-    fn generate_configs(&self, monitors: &[Monitor]) -> Vec<(String, WindowConf, Monitor)> {
+    fn generate_configs(&self, monitors: &[MonitorInfo]) -> Vec<(String, WindowConf, MonitorInfo)> {
         monitors
             .iter()
             .map(|monitor| {
@@ -102,7 +112,8 @@ impl PanelFactory for TaskbarFactory {
                     LayerType::Top,
                     BoardType::None,
                     Some(TASKBAR_HEIGHT as i32),
-                    Some(monitor.name.clone()),
+                    // Unnamed in degraded mode, the compositor picks the output
+                    (!monitor.name.is_empty()).then(|| monitor.name.clone()),
                 );
                 (name, conf, monitor.clone())
             })
@@ -112,7 +123,7 @@ impl PanelFactory for TaskbarFactory {
     fn create_instance(
         &self,
         unique_name: &str,
-        monitor: &Monitor,
+        monitor: &MonitorInfo,
     ) -> Result<Box<dyn PanelInstance>, Box<dyn Error>> {
         info!(
             "Creating Taskbar instance for monitor '{}' ({})",
@@ -137,6 +148,14 @@ impl PanelFactory for TaskbarFactory {
             workspaces::switch_to_workspace(workspace_id);
         });
 
+        // Special workspace click callback
+        ui.on_special_workspace_clicked(move |name| {
+            workspaces::toggle_special_workspace(&name);
+        });
+
+        // Keyboard layout click callback
+        ui.on_keyboard_layout_clicked(keyboard::cycle_layout);
+
         // Event polling timer
         let mut event_rx = events::subscribe();
         let ui_weak_events = ui.as_weak();
@@ -179,6 +198,15 @@ impl PanelFactory for TaskbarFactory {
                             TaskbarEvent::SystemStatus(_data) => {
                                 // TODO: Implement UI update for system status
                             }
+                            TaskbarEvent::WmConnection(connected) => {
+                                ui.set_wm_stale(!connected);
+                            }
+                            TaskbarEvent::KeyboardLayout(layout) => {
+                                keyboard::update_layout(&ui, &layout);
+                            }
+                            TaskbarEvent::Submap(name) => {
+                                keyboard::update_submap(&ui, &name);
+                            }
                         }
                     }
                 }
@@ -214,18 +242,59 @@ impl PanelFactory for TaskbarFactory {
 
         media::attach_callbacks(&ui);
 
+        keyboard::update_layout(&ui, &services::wm::get_keyboard_layout());
+        keyboard::update_submap(&ui, &services::wm::get_submap());
+
         let initial_active_window = hyprland_wm::active_window::get_active_window();
         active_window::update_ui(&ui, &initial_active_window, &monitor_name);
 
-        // Keep timer alive
-        std::mem::forget(event_timer);
-
-        Ok(Box::new(TaskbarInstance { _ui: ui }))
+        Ok(Box::new(TaskbarInstance {
+            ui,
+            window: unique_name.to_string(),
+            width: monitor.width as u32,
+            _event_timer: event_timer,
+        }))
     }
 }
 
 struct TaskbarInstance {
-    _ui: Taskbar,
+    ui: Taskbar,
+    window: String,
+    width: u32,
+    // Stops polling events once the monitor is removed
+    _event_timer: slint::Timer,
 }
 
-impl PanelInstance for TaskbarInstance {}
+impl PanelInstance for TaskbarInstance {
+    fn on_fullscreen(&self, fullscreen: bool) {
+        self.ui.set_fullscreen_hidden(fullscreen);
+        // The surface stays mapped, clicks must reach the fullscreen window below
+        panel_manager::set_pointer_input(&self.window, self.width, TASKBAR_HEIGHT, !fullscreen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, width: i32) -> MonitorInfo {
+        MonitorInfo {
+            name: name.to_string(),
+            width,
+            height: 1080,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_one_taskbar_per_monitor() {
+        let factory = TaskbarFactory::new(false, false);
+        let monitors = [monitor("DP-1", 2560), monitor("HDMI-A-1", 1920)];
+
+        let configs = factory.generate_configs(&monitors);
+
+        let names: Vec<_> = configs.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, ["taskbar-DP-1", "taskbar-HDMI-A-1"]);
+        assert_eq!(configs[1].2, monitors[1]);
+    }
+}
@@ -20,3 +20,27 @@ fn active_window_to_ui_data(data: &ActiveWindowInfo, monitor_name: &str) -> Acti
         active_monitor: data.focused_monitor == monitor_name,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use capy_wm::mock::MockBackend;
+
+    #[test]
+    fn test_active_monitor_follows_focus() {
+        let backend = MockBackend::new();
+        backend.set_active_window(ActiveWindowInfo {
+            address: "0x1".to_string(),
+            app: "foot".to_string(),
+            window_title: "~".to_string(),
+            focused_monitor: "DP-1".to_string(),
+            ..Default::default()
+        });
+
+        let active = capy_wm::get_active_window();
+        let on_focused = active_window_to_ui_data(&active, "DP-1");
+        assert_eq!(on_focused.app, "foot");
+        assert!(on_focused.active_monitor);
+        assert!(!active_window_to_ui_data(&active, "HDMI-A-1").active_monitor);
+    }
+}
@@ -27,6 +27,10 @@ pub enum TaskbarEvent {
     Mpris(Box<crate::services::media::MprisData>), // Boxed to keep enum size small
     ActiveWindow(ActiveWindowInfo),
     SystemStatus(SystemStatus),
+    /// Whether the window manager state is current (false while disconnected).
+    WmConnection(bool),
+    KeyboardLayout(String),
+    Submap(String),
 }
 
 impl TaskbarEvent {
@@ -42,6 +46,9 @@ impl TaskbarEvent {
             TaskbarEvent::Mpris(_) => 5,
             TaskbarEvent::ActiveWindow(_) => 6,
             TaskbarEvent::SystemStatus(_) => 7,
+            TaskbarEvent::WmConnection(_) => 8,
+            TaskbarEvent::KeyboardLayout(_) => 9,
+            TaskbarEvent::Submap(_) => 10,
         }
     }
 }
@@ -105,6 +112,24 @@ pub fn send_active_window(data: ActiveWindowInfo) {
     send(TaskbarEvent::ActiveWindow(data));
 }
 
+/// Send the window manager connection state to all taskbars.
+#[inline]
+pub fn send_wm_connection(connected: bool) {
+    send(TaskbarEvent::WmConnection(connected));
+}
+
+/// Send the active keyboard layout to all taskbars.
+#[inline]
+pub fn send_keyboard_layout(layout: String) {
+    send(TaskbarEvent::KeyboardLayout(layout));
+}
+
+/// Send the active submap to all taskbars.
+#[inline]
+pub fn send_submap(name: String) {
+    send(TaskbarEvent::Submap(name));
+}
+
 /// Subscribe to the event bus. Each taskbar gets its own receiver.
 /// Returns a new receiver that will receive all future events.
 pub fn subscribe() -> Receiver<TaskbarEvent> {
@@ -133,7 +158,7 @@ pub fn drain_latest(rx: &mut Receiver<TaskbarEvent>) -> Vec<TaskbarEvent> {
 
     // Deduplicate: keep only the latest of each variant
     // EXCEPT Workspaces events (variant 4) which are per-monitor
-    let mut seen = [false; 8]; // Support up to 8 event types
+    let mut seen = [false; 11]; // Support up to 11 event types
     let mut result = Vec::with_capacity(events.len());
 
     for event in events.into_iter().rev() {
@@ -0,0 +1,21 @@
+//! Keyboard layout indicator and submap badge for the taskbar.
+
+use crate::panels::taskbar::Taskbar;
+use crate::services::wm;
+use log::{debug, warn};
+
+pub fn update_layout(ui: &Taskbar, layout: &str) {
+    ui.set_keyboard_layout(layout.into());
+}
+
+pub fn update_submap(ui: &Taskbar, submap: &str) {
+    ui.set_submap(submap.into());
+}
+
+/// Switch all keyboards to their next layout.
+pub fn cycle_layout() {
+    debug!("Cycling keyboard layout");
+    if let Err(e) = wm::cycle_keyboard_layout() {
+        warn!("Failed to cycle keyboard layout: {}", e);
+    }
+}
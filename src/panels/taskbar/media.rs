@@ -111,6 +111,10 @@ pub fn update_ui(ui: &Taskbar, data: &ServiceMprisData) {
         position_secs: data.position_secs,
         is_playing: data.is_playing,
         has_media: data.has_media,
+        can_prev: data.can_go_previous,
+        can_next: data.can_go_next,
+        can_play_pause: data.can_play_pause,
+        can_seek: data.can_seek,
         text_color,
     };
 
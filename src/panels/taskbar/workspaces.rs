@@ -3,8 +3,12 @@
 //! Receives workspace events from the service layer and updates the Slint UI.
 
 use crate::panels::taskbar::load_icon;
-use crate::panels::taskbar::{Taskbar, WorkspaceData, WorkspaceState as SlintWorkspaceState};
-use crate::services::wm::{WorkspaceInfo, WorkspaceState, WorkspacesStatus};
+use crate::panels::taskbar::{
+    Taskbar, WorkspaceData, WorkspaceKind as SlintWorkspaceKind,
+    WorkspaceState as SlintWorkspaceState,
+};
+use crate::services::wm::{self, WorkspaceInfo, WorkspaceKind, WorkspaceState, WorkspacesStatus};
+use log::{debug, warn};
 use slint::{ModelRc, VecModel};
 use std::rc::Rc;
 
@@ -29,6 +33,15 @@ pub fn update_ui(ui: &Taskbar, status: &WorkspacesStatus, monitor_name: &str) {
 
     let model: Rc<VecModel<WorkspaceData>> = Rc::new(VecModel::from(workspace_data));
     ui.set_workspaces(ModelRc::from(model));
+
+    let special_data: Vec<WorkspaceData> = status
+        .special
+        .iter()
+        .map(|ws| workspace_to_slint(ws, false, false))
+        .collect();
+
+    let special_model: Rc<VecModel<WorkspaceData>> = Rc::new(VecModel::from(special_data));
+    ui.set_special_workspaces(ModelRc::from(special_model));
 }
 
 /// Convert service workspace info to Slint WorkspaceData.
@@ -44,6 +57,12 @@ fn workspace_to_slint(
         WorkspaceState::Attention => SlintWorkspaceState::Attention,
     };
 
+    let kind = match ws.kind {
+        WorkspaceKind::Normal => SlintWorkspaceKind::Normal,
+        WorkspaceKind::Named => SlintWorkspaceKind::Named,
+        WorkspaceKind::Special => SlintWorkspaceKind::Special,
+    };
+
     // Load icon if path exists
     let icon = ws
         .icon_path
@@ -54,6 +73,8 @@ fn workspace_to_slint(
     WorkspaceData {
         id: ws.id,
         absolute_id: ws.absolute_id,
+        name: ws.name.as_str().into(),
+        kind,
         state,
         icon,
         occupied: ws.occupied,
@@ -64,11 +85,16 @@ fn workspace_to_slint(
 
 /// Switch to the specified workspace.
 pub fn switch_to_workspace(workspace_id: i32) {
-    use hyprland::dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial};
-    use log::debug;
-
     debug!("Switching to workspace {}", workspace_id);
-    let _ = Dispatch::call(DispatchType::Workspace(WorkspaceIdentifierWithSpecial::Id(
-        workspace_id,
-    )));
+    if let Err(e) = wm::switch_workspace(workspace_id) {
+        warn!("Failed to switch to workspace {}: {}", workspace_id, e);
+    }
+}
+
+/// Show or hide the specified special workspace.
+pub fn toggle_special_workspace(name: &str) {
+    debug!("Toggling special workspace {}", name);
+    if let Err(e) = wm::toggle_special_workspace(name) {
+        warn!("Failed to toggle special workspace {}: {}", name, e);
+    }
 }
@@ -0,0 +1,275 @@
+//! Alt-tab window switcher.
+//!
+//! A centered overlay listing windows in most recently used order. It is driven
+//! entirely by `switcher` commands (see [`crate::services::commands`]), e.g.
+//! `capyshell msg switcher next` bound to Alt+Tab and `switcher confirm` bound to
+//! releasing Alt. Only the switcher on the focused monitor opens. While closed
+//! the surface takes no pointer input and commands are polled less often.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use log::{debug, info, warn};
+use slint::{ComponentHandle, ModelRc, VecModel};
+use spell_framework::layer_properties::{BoardType, LayerType, WindowConf};
+
+use crate::panel_manager::{self, PanelFactory, PanelInstance};
+use crate::panels::taskbar::window_icon;
+use crate::services::commands::{self, Command, SwitcherCommand};
+use crate::services::wm::{self, MonitorInfo, WindowInfo};
+mod slint_window_switcher {
+    include!(concat!(env!("OUT_DIR"), "/window_switcher.rs"));
+    pub use slint_generatedWindowSwitcher::*;
+}
+pub use self::slint_window_switcher::*;
+
+const SWITCHER_WIDTH: u32 = 800;
+const SWITCHER_HEIGHT: u32 = 144;
+// Commands are polled quickly while open so cycling keeps up with key repeat
+const OPEN_POLL_INTERVAL_MS: u64 = 20;
+const CLOSED_POLL_INTERVAL_MS: u64 = 100;
+
+pub struct WindowSwitcherFactory {}
+
+impl WindowSwitcherFactory {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PanelFactory for WindowSwitcherFactory {
+    fn type_id(&self) -> &str {
+        "window-switcher"
+    }
+
+    fn generate_configs(&self, monitors: &[MonitorInfo]) -> Vec<(String, WindowConf, MonitorInfo)> {
+        monitors
+            .iter()
+            .map(|monitor| {
+                let name = format!("window-switcher-{}", monitor.name);
+                // No anchors, layer shell centers the surface on the output
+                let conf = WindowConf::new(
+                    SWITCHER_WIDTH,
+                    SWITCHER_HEIGHT,
+                    (None, None),
+                    (0, 0, 0, 0),
+                    LayerType::Overlay,
+                    BoardType::None,
+                    None,
+                    (!monitor.name.is_empty()).then(|| monitor.name.clone()),
+                );
+                (name, conf, monitor.clone())
+            })
+            .collect()
+    }
+
+    fn create_instance(
+        &self,
+        unique_name: &str,
+        monitor: &MonitorInfo,
+    ) -> Result<Box<dyn PanelInstance>, Box<dyn Error>> {
+        info!(
+            "Creating WindowSwitcher instance for monitor '{}' ({})",
+            monitor.name, unique_name
+        );
+
+        let ui = WindowSwitcher::new()?;
+        let switcher = Rc::new(RefCell::new(Switcher::default()));
+        let monitor_name = monitor.name.clone();
+        let window_name = unique_name.to_string();
+        panel_manager::set_pointer_input(&window_name, SWITCHER_WIDTH, SWITCHER_HEIGHT, false);
+
+        let mut command_rx = commands::subscribe();
+        let ui_weak = ui.as_weak();
+        let switcher_for_commands = switcher.clone();
+
+        let command_timer = Rc::new(slint::Timer::default());
+        let timer_weak = Rc::downgrade(&command_timer);
+        command_timer.start(
+            slint::TimerMode::Repeated,
+            poll_interval(false),
+            move || {
+                let mut switcher = switcher_for_commands.borrow_mut();
+                let was_open = switcher.open;
+                let mut changed = false;
+
+                while let Ok(command) = command_rx.try_recv() {
+                    let Command::Switcher(command) = command else {
+                        continue;
+                    };
+                    if !switcher.open && !wm::is_focused_monitor(&monitor_name) {
+                        continue;
+                    }
+
+                    if let Some(address) = switcher.apply(command, wm::get_windows_by_recency) {
+                        focus(&address);
+                    }
+                    changed = true;
+                }
+
+                if changed && let Some(ui) = ui_weak.upgrade() {
+                    update_ui(&ui, &switcher);
+                }
+                if switcher.open != was_open {
+                    panel_manager::set_pointer_input(
+                        &window_name,
+                        SWITCHER_WIDTH,
+                        SWITCHER_HEIGHT,
+                        switcher.open,
+                    );
+                    if let Some(timer) = timer_weak.upgrade() {
+                        timer.set_interval(poll_interval(switcher.open));
+                    }
+                }
+            },
+        );
+
+        Ok(Box::new(WindowSwitcherInstance {
+            _ui: ui,
+            _command_timer: command_timer,
+        }))
+    }
+}
+
+struct WindowSwitcherInstance {
+    _ui: WindowSwitcher,
+    // Stops handling commands once the monitor is removed
+    _command_timer: Rc<slint::Timer>,
+}
+
+impl PanelInstance for WindowSwitcherInstance {}
+
+fn poll_interval(open: bool) -> std::time::Duration {
+    std::time::Duration::from_millis(if open {
+        OPEN_POLL_INTERVAL_MS
+    } else {
+        CLOSED_POLL_INTERVAL_MS
+    })
+}
+
+/// Selection state of the switcher.
+#[derive(Default)]
+struct Switcher {
+    windows: Vec<WindowInfo>,
+    selected: usize,
+    open: bool,
+}
+
+impl Switcher {
+    /// Apply a command, listing the windows with `windows` when opening.
+    /// Returns the address of the window to focus once a selection is confirmed.
+    fn apply<F>(&mut self, command: SwitcherCommand, windows: F) -> Option<String>
+    where
+        F: FnOnce() -> Vec<WindowInfo>,
+    {
+        match command {
+            SwitcherCommand::Next | SwitcherCommand::Prev if !self.open => {
+                self.windows = windows();
+                if self.windows.is_empty() {
+                    return None;
+                }
+                self.open = true;
+                // The first window is the focused one, next starts at the one before it
+                self.selected = match command {
+                    SwitcherCommand::Next => 1 % self.windows.len(),
+                    _ => self.windows.len() - 1,
+                };
+                None
+            }
+            SwitcherCommand::Next => {
+                self.selected = (self.selected + 1) % self.windows.len();
+                None
+            }
+            SwitcherCommand::Prev => {
+                self.selected = (self.selected + self.windows.len() - 1) % self.windows.len();
+                None
+            }
+            SwitcherCommand::Confirm if self.open => {
+                let address = self.windows.get(self.selected).map(|w| w.address.clone());
+                self.close();
+                address
+            }
+            SwitcherCommand::Confirm | SwitcherCommand::Cancel => {
+                self.close();
+                None
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.windows.clear();
+        self.selected = 0;
+    }
+}
+
+fn focus(address: &str) {
+    debug!("Switching to window {}", address);
+    if let Err(e) = wm::focus_window(address) {
+        warn!("Failed to focus window {}: {}", address, e);
+    }
+}
+
+fn update_ui(ui: &WindowSwitcher, switcher: &Switcher) {
+    let windows: Vec<SwitcherWindow> = switcher.windows.iter().map(to_slint).collect();
+    ui.set_windows(ModelRc::from(Rc::new(VecModel::from(windows))));
+    ui.set_selected(switcher.selected as i32);
+    ui.set_open(switcher.open);
+}
+
+fn to_slint(window: &WindowInfo) -> SwitcherWindow {
+    SwitcherWindow {
+        app: window.class.clone().into(),
+        title: window.title.clone().into(),
+        icon: window_icon(window),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn windows() -> Vec<WindowInfo> {
+        ["0xa", "0xb", "0xc"]
+            .iter()
+            .map(|address| WindowInfo {
+                address: address.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_next_starts_at_previous_window() {
+        let mut switcher = Switcher::default();
+
+        assert_eq!(switcher.apply(SwitcherCommand::Next, windows), None);
+        assert!(switcher.open);
+        assert_eq!(switcher.selected, 1);
+
+        switcher.apply(SwitcherCommand::Next, windows);
+        switcher.apply(SwitcherCommand::Next, windows);
+        assert_eq!(switcher.selected, 0);
+        switcher.apply(SwitcherCommand::Prev, windows);
+
+        assert_eq!(
+            switcher.apply(SwitcherCommand::Confirm, windows),
+            Some("0xc".to_string())
+        );
+        assert!(!switcher.open);
+    }
+
+    #[test]
+    fn test_cancel_and_empty_list() {
+        let mut switcher = Switcher::default();
+
+        switcher.apply(SwitcherCommand::Prev, windows);
+        assert_eq!(switcher.selected, 2);
+        switcher.apply(SwitcherCommand::Cancel, windows);
+        assert_eq!(switcher.apply(SwitcherCommand::Confirm, windows), None);
+
+        switcher.apply(SwitcherCommand::Next, Vec::new);
+        assert!(!switcher.open);
+    }
+}
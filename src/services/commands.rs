@@ -0,0 +1,250 @@
+//! Command socket for controlling the running shell.
+//!
+//! Keybinds run `capyshell msg <command>`, which sends the command as a line to
+//! `$XDG_RUNTIME_DIR/capyshell.sock`. The service parses it and broadcasts it
+//! to all panels, each panel picks the commands meant for it.
+
+use crate::event_bus::CHANNEL_CAPACITY;
+use log::{debug, info, warn};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+const SOCKET_NAME: &str = "capyshell.sock";
+
+/// Commands accepted by the shell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Switcher(SwitcherCommand),
+    Overview(OverviewCommand),
+}
+
+/// Commands for the window switcher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwitcherCommand {
+    /// Open the switcher, or select the next window if it is open.
+    Next,
+    /// Open the switcher, or select the previous window if it is open.
+    Prev,
+    /// Focus the selected window and close the switcher.
+    Confirm,
+    /// Close the switcher without changing focus.
+    Cancel,
+}
+
+/// Commands for the workspace overview.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverviewCommand {
+    /// Open the overview, or close it if it is open.
+    Toggle,
+    Open,
+    Close,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["switcher", action] => {
+                let action = match *action {
+                    "next" => SwitcherCommand::Next,
+                    "prev" => SwitcherCommand::Prev,
+                    "confirm" => SwitcherCommand::Confirm,
+                    "cancel" => SwitcherCommand::Cancel,
+                    other => return Err(format!("unknown switcher action '{}'", other)),
+                };
+                Ok(Command::Switcher(action))
+            }
+            ["overview", action] => {
+                let action = match *action {
+                    "toggle" => OverviewCommand::Toggle,
+                    "open" => OverviewCommand::Open,
+                    "close" => OverviewCommand::Close,
+                    other => return Err(format!("unknown overview action '{}'", other)),
+                };
+                Ok(Command::Overview(action))
+            }
+            _ => Err(format!("unknown command '{}'", s.trim())),
+        }
+    }
+}
+
+static COMMAND_SENDER: OnceLock<Sender<Command>> = OnceLock::new();
+
+fn get_sender() -> &'static Sender<Command> {
+    COMMAND_SENDER.get_or_init(|| {
+        let (tx, _rx) = broadcast::channel(CHANNEL_CAPACITY);
+        tx
+    })
+}
+
+/// Subscribe to commands. Each panel gets its own receiver.
+pub fn subscribe() -> Receiver<Command> {
+    get_sender().subscribe()
+}
+
+/// Path of the command socket.
+pub fn socket_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(SOCKET_NAME)
+}
+
+/// Start listening for commands in the background.
+pub fn start() {
+    let path = socket_path();
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Failed to bind command socket {:?}: {}", path, e);
+            return;
+        }
+    };
+    info!("Listening for commands on {:?}", path);
+
+    std::thread::spawn(move || serve(listener));
+}
+
+/// Bind the command socket, replacing one left behind by a previous run.
+/// A socket another running shell still answers on is left alone.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    match UnixListener::bind(path) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "another shell is listening on it",
+                ));
+            }
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        result => result,
+    }
+}
+
+/// Accept connections, each client is handled on its own thread so a stalled one blocks no other.
+fn serve(listener: UnixListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                std::thread::spawn(move || handle_client(stream));
+            }
+            Err(e) => warn!("Failed to accept command connection: {}", e),
+        }
+    }
+}
+
+/// Read commands line by line, answering each with `ok` or an error.
+fn handle_client(stream: UnixStream) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            warn!("Failed to set up command connection: {}", e);
+            return;
+        }
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        let reply = match line.parse::<Command>() {
+            Ok(command) => {
+                debug!("Command: {:?}", command);
+                let _ = get_sender().send(command);
+                "ok".to_string()
+            }
+            Err(e) => format!("error: {}", e),
+        };
+        if writeln!(writer, "{}", reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// Send a command to the running shell and wait for its answer.
+pub fn send(command: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    writeln!(stream, "{}", command)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        other => Err(other.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            "switcher next".parse(),
+            Ok(Command::Switcher(SwitcherCommand::Next))
+        );
+        assert_eq!(
+            "  switcher   confirm\n".parse(),
+            Ok(Command::Switcher(SwitcherCommand::Confirm))
+        );
+        assert!("switcher".parse::<Command>().is_err());
+        assert!("switcher jump".parse::<Command>().is_err());
+        assert_eq!(
+            "overview toggle".parse(),
+            Ok(Command::Overview(OverviewCommand::Toggle))
+        );
+        assert!("overview next".parse::<Command>().is_err());
+        assert!("launcher open".parse::<Command>().is_err());
+    }
+
+    fn test_socket(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("capyshell-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_bind_keeps_live_socket_and_replaces_stale_one() {
+        let path = test_socket("bind");
+
+        let running = bind(&path).unwrap();
+        assert!(bind(&path).is_err());
+
+        // The file stays behind once the listener is gone, nobody answers on it
+        drop(running);
+        assert!(path.exists());
+        let _listener = bind(&path).unwrap();
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_stalled_client_blocks_no_other() {
+        let path = test_socket("serve");
+        let listener = bind(&path).unwrap();
+        std::thread::spawn(move || serve(listener));
+
+        // Connected, but never sends a full line
+        let mut stalled = UnixStream::connect(&path).unwrap();
+        write!(stalled, "switcher").unwrap();
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        writeln!(client, "switcher next").unwrap();
+        let mut reply = String::new();
+        BufReader::new(client).read_line(&mut reply).unwrap();
+        assert_eq!(reply.trim(), "ok");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -4,19 +4,23 @@
 //! Handles album art processing and event bus integration.
 
 use crate::panels::taskbar::events;
-use capy_mpris::{MprisClient, MprisData as ClientMprisData, PlayerCommand, PlayerSource};
+use capy_mpris::{
+    MprisClient, MprisCommands, MprisData as ClientMprisData, MprisError, MprisEvent,
+    PlayerCommand, PlayerTracker,
+};
+use futures_util::StreamExt;
 use image::imageops::FilterType;
 use log::{error, info, warn};
 use sha2::{Digest, Sha256};
 use slint::SharedString;
 use std::fs;
+use std::future::Future;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::OnceLock;
 use std::sync::RwLock;
 use std::sync::atomic::{AtomicU64, Ordering};
-use tokio::sync::mpsc;
 
 /// Data sent to UI for display
 #[derive(Clone, Debug, Default)]
@@ -31,6 +35,12 @@ pub struct MprisData {
     pub is_playing: bool,
     pub has_media: bool,
     pub is_track_change: bool,
+    // Controls the player supports
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    /// Whether the play/pause button can do anything in the current state
+    pub can_play_pause: bool,
+    pub can_seek: bool,
     /// Timestamp when position was fetched (for client-side interpolation)
     pub position_timestamp_ms: u64,
     /// Current source short name
@@ -43,21 +53,80 @@ const CONFIG_DIR: &str = ".config/capyshell";
 // Generation counter to handle race conditions for async image loading
 static GENERATION: AtomicU64 = AtomicU64::new(0);
 
-// Global command sender for UI callbacks
-static COMMAND_SENDER: OnceLock<mpsc::Sender<PlayerCommand>> = OnceLock::new();
+// Commands for the active player, replaced whenever the client restarts
+static COMMANDS: RwLock<Option<MprisCommands>> = RwLock::new(None);
+// Runtime of the MPRIS thread, commands from UI callbacks run on it
+static RUNTIME: OnceLock<tokio::runtime::Handle> = OnceLock::new();
 
-/// Get the command sender for sending playback commands
-pub fn get_command_sender() -> Option<&'static mpsc::Sender<PlayerCommand>> {
-    COMMAND_SENDER.get()
-}
+// Every player on the bus for the media selector, tracked by the running client
+static PLAYER_TRACKER: RwLock<Option<PlayerTracker>> = RwLock::new(None);
+// Bumped whenever a player or the active source changes, panels poll it
+static PLAYERS_VERSION: AtomicU64 = AtomicU64::new(0);
+static ACTIVE_SOURCE: RwLock<Option<String>> = RwLock::new(None);
+// Bus names of the sources allowed by the policy, in order of preference
+static SOURCES: RwLock<Vec<String>> = RwLock::new(Vec::new());
 
-/// Send a command to the MPRIS client
+/// Send a command to the active player, failures are logged
 pub fn send_command(cmd: PlayerCommand) {
-    if let Some(sender) = COMMAND_SENDER.get() {
-        let _ = sender.try_send(cmd);
-    } else {
-        warn!("MPRIS command sender not initialized");
-    }
+    let Some(commands) = COMMANDS.read().unwrap().clone() else {
+        warn!("MPRIS client not running");
+        return;
+    };
+    spawn_command(
+        format!("{:?}", cmd),
+        async move { commands.send(cmd).await },
+    );
+}
+
+/// State of every player allowed by the source policy, in order of preference
+pub fn players() -> Vec<ClientMprisData> {
+    let Some(tracker) = PLAYER_TRACKER.read().unwrap().clone() else {
+        return Vec::new();
+    };
+    SOURCES
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|bus_name| tracker.player(bus_name))
+        .collect()
+}
+
+/// Changes whenever [`players`] or [`active_source`] may have changed
+pub fn players_version() -> u64 {
+    PLAYERS_VERSION.load(Ordering::SeqCst)
+}
+
+/// Bus name of the player shown in the taskbar
+pub fn active_source() -> Option<String> {
+    ACTIVE_SOURCE.read().unwrap().clone()
+}
+
+/// Send a command to a specific player, failures are logged
+pub fn send_player_command(bus_name: &str, cmd: PlayerCommand) {
+    let Some(tracker) = PLAYER_TRACKER.read().unwrap().clone() else {
+        warn!("MPRIS client not running");
+        return;
+    };
+    let bus_name = bus_name.to_string();
+    spawn_command(format!("{:?} to {}", cmd, bus_name), async move {
+        tracker.send(&bus_name, cmd).await
+    });
+}
+
+/// Run a command on the MPRIS runtime without blocking the UI
+fn spawn_command<F>(description: String, command: F)
+where
+    F: Future<Output = Result<(), MprisError>> + Send + 'static,
+{
+    let Some(runtime) = RUNTIME.get() else {
+        warn!("MPRIS runtime not started");
+        return;
+    };
+    runtime.spawn(async move {
+        if let Err(e) = command.await {
+            warn!("MPRIS command {} failed: {}", description, e);
+        }
+    });
 }
 
 pub fn start() {
@@ -87,6 +156,7 @@ async fn run_mpris_loop() {
     }
 
     info!("Starting MPRIS D-Bus client with capy-mpris");
+    let _ = RUNTIME.set(tokio::runtime::Handle::current());
 
     // Shared state for tracking art processing
     let cache_dir_for_update = cache_dir.clone();
@@ -109,6 +179,13 @@ async fn run_mpris_loop() {
             *cached_art_paths.write().unwrap() = (String::new(), String::new());
         }
 
+        // Players that cannot be controlled report no capabilities
+        let can_control = data.can_control;
+        let can_go_next = can_control && data.can_go_next;
+        let can_go_previous = can_control && data.can_go_previous;
+        let can_play_pause = data.can_play_pause();
+        let can_seek = can_control && data.can_seek;
+
         info!(
             "MPRIS update: title='{}', playing={}, pos={:.1}s, track_change={}",
             data.title,
@@ -135,6 +212,10 @@ async fn run_mpris_loop() {
             is_playing: data.status.is_playing(),
             has_media: true,
             is_track_change,
+            can_go_next,
+            can_go_previous,
+            can_play_pause,
+            can_seek,
             position_timestamp_ms: data.position_timestamp_ms,
             source_name: data.source_name.clone().into(),
         };
@@ -184,6 +265,10 @@ async fn run_mpris_loop() {
                         is_playing,
                         has_media: true,
                         is_track_change: false,
+                        can_go_next,
+                        can_go_previous,
+                        can_play_pause,
+                        can_seek,
                         position_timestamp_ms,
                         source_name: source_name.into(),
                     };
@@ -193,44 +278,66 @@ async fn run_mpris_loop() {
         }
     };
 
-    let on_sources_changed = |sources: Vec<PlayerSource>, active: Option<String>| {
-        info!(
-            "MPRIS sources: {:?}, active: {:?}",
-            sources.iter().map(|s| &s.short_name).collect::<Vec<_>>(),
-            active
-        );
-    };
-
     loop {
-        match MprisClient::start(
-            on_update.clone(),
-            on_sources_changed.clone(),
-            Some(config_path.clone()),
-        )
-        .await
-        {
-            Ok(sender) => {
-                // Store the sender globally for UI callbacks
-                let _ = COMMAND_SENDER.set(sender);
-                info!("MPRIS client started, command sender available");
-
-                // Keep running - the client loop runs in a spawned task
-                loop {
-                    tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-                }
-            }
+        let mut client = match MprisClient::start(Some(config_path.clone())).await {
+            Ok(client) => client,
             Err(e) => {
                 warn!("Failed to start MPRIS client: {}. Retrying in 2s...", e);
                 tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                continue;
+            }
+        };
+        *COMMANDS.write().unwrap() = Some(client.commands());
+        // The media selector reads every player from the client's tracker
+        *PLAYER_TRACKER.write().unwrap() = Some(client.tracker());
+        set_sources(&client);
+        info!("MPRIS client started, command sender available");
+
+        while let Some(event) = client.next().await {
+            match event {
+                MprisEvent::Updated(data) => on_update(*data),
+                MprisEvent::PlayerAdded(_) | MprisEvent::PlayerRemoved(_) => set_sources(&client),
+                MprisEvent::PlayerChanged(_) => {
+                    PLAYERS_VERSION.fetch_add(1, Ordering::SeqCst);
+                }
+                MprisEvent::ActiveChanged(active) => {
+                    info!("MPRIS active source: {:?}", active);
+                    let has_player = active.is_some();
+                    *ACTIVE_SOURCE.write().unwrap() = active;
+                    PLAYERS_VERSION.fetch_add(1, Ordering::SeqCst);
+                    // Nothing left to show in the taskbar
+                    if !has_player {
+                        GENERATION.fetch_add(1, Ordering::SeqCst);
+                        events::send_mpris(MprisData::default());
+                    }
+                }
+                MprisEvent::Error(e) => warn!("MPRIS error: {}", e),
             }
         }
 
         // Clear state when client exits
+        warn!("MPRIS client stopped, restarting");
+        *COMMANDS.write().unwrap() = None;
+        *PLAYER_TRACKER.write().unwrap() = None;
+        SOURCES.write().unwrap().clear();
+        *ACTIVE_SOURCE.write().unwrap() = None;
+        PLAYERS_VERSION.fetch_add(1, Ordering::SeqCst);
         GENERATION.fetch_add(1, Ordering::SeqCst);
         events::send_mpris(MprisData::default());
     }
 }
 
+/// Remember the sources allowed by the policy, for the media selector
+fn set_sources(client: &MprisClient) {
+    let sources = client.sources();
+    info!(
+        "MPRIS sources: {:?}",
+        sources.iter().map(|s| &s.short_name).collect::<Vec<_>>()
+    );
+    *SOURCES.write().unwrap() = sources.into_iter().map(|s| s.bus_name).collect();
+    PLAYERS_VERSION.fetch_add(1, Ordering::SeqCst);
+}
+
 fn process_album_art(url: &str, cache_dir: &Path) -> Option<(String, String)> {
     let mut hasher = Sha256::new();
     hasher.update(url.as_bytes());
@@ -4,6 +4,7 @@
 //! Each service spawns a single background thread that all panels share.
 //!
 //! - `apps` - App catalog and icon lookup with caching
+//! - `commands` - Command socket for keybinds (`capyshell msg ...`)
 //! - `wm` - Window manager abstraction (currently supports Hyprland)
 //! - `volume` - PulseAudio/PipeWire volume monitoring
 //! - `battery` - Battery status via D-Bus
@@ -13,6 +14,7 @@
 pub mod apps;
 pub mod battery;
 pub mod bluetooth;
+pub mod commands;
 pub mod media;
 pub mod network;
 pub mod system_info;
@@ -27,17 +29,19 @@ pub fn start_all() -> ServiceStatus {
     info!("Starting shared services...");
 
     apps::start_indexing();
+    commands::start();
 
     let has_battery = battery::start_monitor();
     media::start();
     volume::start_monitor();
     network::start_monitor();
     let has_bluetooth = bluetooth::start_monitor();
-    wm::start_monitor();
+    let has_wm = wm::start_monitor();
 
     ServiceStatus {
         has_battery,
         has_bluetooth,
+        has_wm,
     }
 }
 
@@ -45,4 +49,6 @@ pub fn start_all() -> ServiceStatus {
 pub struct ServiceStatus {
     pub has_battery: bool,
     pub has_bluetooth: bool,
+    /// Whether a supported window manager is running. Without one, panels show no workspaces.
+    pub has_wm: bool,
 }
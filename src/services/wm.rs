@@ -3,21 +3,35 @@
 //! This module wraps the capy-wm crate and bridges it to CapyShell's event system.
 //! It uses the unified WindowBackend trait for WM-agnostic operation.
 
+use crate::panel_manager::{self, MonitorEvent};
 use crate::panels::taskbar::events;
-use log::info;
+use log::{info, warn};
 use std::sync::OnceLock;
 
 // Re-export types from capy-wm
 pub use capy_wm::{
-    ActiveWindowInfo, WindowBackend, WmEvent, WmType, WorkspaceInfo, WorkspaceState,
-    WorkspacesStatus,
+    ActiveWindowInfo, EventFilter, MonitorInfo, Subscription, WindowBackend, WindowInfo, WmError,
+    WmEvent, WmEventKind, WmResult, WmType, WorkspaceInfo, WorkspaceKind, WorkspaceState,
+    WorkspacesStatus, get_active_monitor, get_all_workspaces_status, get_keyboard_layout,
+    get_submap, get_windows, get_windows_by_recency, subscribe_with,
 };
 
-// Global backend instance
-static BACKEND: OnceLock<Box<dyn WindowBackend>> = OnceLock::new();
-
-fn get_backend() -> &'static dyn WindowBackend {
-    BACKEND.get_or_init(|| capy_wm::get_backend()).as_ref()
+// Global backend instance, None when no supported WM is running
+static BACKEND: OnceLock<Option<Box<dyn WindowBackend>>> = OnceLock::new();
+
+/// Get the shared backend, e.g. for window actions.
+/// Fails with [`WmError::NoBackend`] when the shell runs without a supported WM.
+pub fn get_backend() -> WmResult<&'static dyn WindowBackend> {
+    BACKEND
+        .get_or_init(|| match capy_wm::get_backend() {
+            Ok(backend) => Some(backend),
+            Err(e) => {
+                warn!("{}, running without window manager integration", e);
+                None
+            }
+        })
+        .as_deref()
+        .ok_or(WmError::NoBackend)
 }
 
 /// Re-export for backwards compatibility with existing code.
@@ -47,60 +61,136 @@ pub fn detect_wm() -> WmType {
 }
 
 /// Start the window manager monitoring.
-/// Sets up callbacks to bridge capy-wm events to CapyShell's event bus.
-pub fn start_monitor() {
+/// Subscribes to capy-wm events and bridges them to CapyShell's event bus.
+/// Returns false if no WM could be reached, the shell then runs in a degraded mode.
+pub fn start_monitor() -> bool {
     let wm = detect_wm();
     info!("Starting WM service (detected: {})...", wm);
 
+    let backend = match get_backend() {
+        Ok(backend) => backend,
+        Err(_) => return false,
+    };
+
     // Set up icon resolver callback
     capy_wm::set_icon_resolver(Box::new(|class| crate::services::apps::get_icon(class)));
 
-    // Set up event callback to bridge to CapyShell's event bus
-    capy_wm::set_event_callback(|event| match event {
-        WmEvent::WorkspacesChanged(status) => {
-            events::send_workspaces(status);
-        }
-        WmEvent::ActiveWindowChanged(info) => {
-            events::send_active_window(info);
-        }
-        WmEvent::MonitorAdded(_) | WmEvent::MonitorRemoved(_) => {
-            hotplug();
+    // Forward capy-wm events to CapyShell's event bus
+    let subscription = capy_wm::subscribe();
+    std::thread::spawn(move || {
+        for event in subscription.iter() {
+            match event {
+                WmEvent::WorkspacesChanged(status) => {
+                    events::send_workspaces(status);
+                }
+                WmEvent::ActiveWindowChanged(info) => {
+                    events::send_active_window(info);
+                }
+                WmEvent::MonitorAdded(name) => {
+                    panel_manager::send_monitor_event(MonitorEvent::Added(monitor_info(&name)));
+                }
+                WmEvent::MonitorRemoved(name) => {
+                    panel_manager::send_monitor_event(MonitorEvent::Removed(name));
+                }
+                // The window list is read from the capy-wm state on demand
+                WmEvent::WindowOpened(_) | WmEvent::WindowClosed(_) | WmEvent::WindowMoved(_) => {}
+                // Already reflected in the special list of the workspace status
+                WmEvent::SpecialWorkspaceToggled { .. } => {}
+                // Urgent workspaces are shown through the workspace status
+                WmEvent::UrgencyChanged { .. } => {}
+                WmEvent::FullscreenChanged {
+                    monitor,
+                    fullscreen,
+                } => {
+                    panel_manager::send_monitor_event(MonitorEvent::Fullscreen {
+                        monitor,
+                        fullscreen,
+                    });
+                }
+                WmEvent::KeyboardLayoutChanged { layout, .. } => {
+                    events::send_keyboard_layout(layout);
+                }
+                WmEvent::SubmapChanged(name) => {
+                    events::send_submap(name);
+                }
+                // Nothing shows the tiling state of windows yet
+                WmEvent::FloatingChanged { .. } => {}
+                WmEvent::BackendDisconnected => {
+                    warn!("Lost the connection to the window manager, reconnecting...");
+                    events::send_wm_connection(false);
+                }
+                WmEvent::BackendReconnected => {
+                    info!("Window manager connection restored");
+                    events::send_wm_connection(true);
+                }
+            }
         }
     });
 
-    get_backend().start_listener();
+    match backend.start_listener() {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("Failed to start the WM listener: {}", e);
+            false
+        }
+    }
+}
+
+/// Get the enabled monitors from the active backend.
+pub fn get_monitors() -> WmResult<Vec<MonitorInfo>> {
+    Ok(get_backend()?
+        .get_monitor_info()?
+        .into_iter()
+        .filter(|m| !m.disabled)
+        .collect())
 }
 
 /// Switch to a workspace by absolute ID.
-pub fn switch_workspace(id: i32) {
-    get_backend().switch_workspace(id);
+pub fn switch_workspace(id: i32) -> WmResult<()> {
+    get_backend()?.switch_workspace(id)
 }
 
-/// Trigger a refresh of WM state (after icon indexing, etc.).
-pub fn trigger_refresh() {
-    get_backend().trigger_refresh();
+/// Show or hide a special workspace by name.
+pub fn toggle_special_workspace(name: &str) -> WmResult<()> {
+    get_backend()?.toggle_special_workspace(name)
 }
 
-/// Handle monitor hotplug by restarting the application.
-/// This can later be handled differently, but i don't bother yet.
-fn hotplug() {
-    use log::error;
-    use std::os::unix::process::CommandExt;
-    use std::process::Command;
+/// Focus a window by address.
+pub fn focus_window(address: &str) -> WmResult<()> {
+    get_backend()?.focus_window(address)
+}
 
-    let exe = match std::env::current_exe() {
-        Ok(e) => e,
-        Err(e) => {
-            error!("Failed to get current executable: {}", e);
-            std::process::exit(1);
-        }
-    };
+/// Move a window to a workspace by absolute ID without following it.
+pub fn move_window_to_workspace(address: &str, workspace_id: i32) -> WmResult<()> {
+    get_backend()?.move_window_to_workspace(address, workspace_id)
+}
+
+/// Switch all keyboards to their next layout.
+pub fn cycle_keyboard_layout() -> WmResult<()> {
+    get_backend()?.cycle_keyboard_layout()
+}
 
-    info!("Hotplugging CapyShell...");
+/// Whether a monitor has focus. Without monitor names (degraded mode) every monitor counts as focused.
+pub fn is_focused_monitor(monitor_name: &str) -> bool {
+    let active = get_active_monitor();
+    monitor_name.is_empty() || active.is_empty() || active == monitor_name
+}
 
-    let args: Vec<String> = std::env::args().skip(1).collect();
-    let err = Command::new(exe).args(args).exec();
+/// Trigger a refresh of WM state (after icon indexing, etc.).
+pub fn trigger_refresh() {
+    if let Err(e) = get_backend().and_then(|backend| backend.trigger_refresh()) {
+        warn!("Failed to refresh WM state: {}", e);
+    }
+}
 
-    error!("Failed to hotplug CapyShell: {}", err);
-    std::process::exit(1);
+/// Look up a newly added monitor, falling back to its name if the backend does not know it yet.
+fn monitor_info(name: &str) -> MonitorInfo {
+    get_monitors()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|m| m.name == name)
+        .unwrap_or_else(|| MonitorInfo {
+            name: name.to_string(),
+            ..Default::default()
+        })
 }